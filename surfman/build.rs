@@ -46,11 +46,28 @@ fn main() {
     // Generate GL bindings.
     if target_os == "android" {
         let mut file = File::create(&dest.join("gl_bindings.rs")).unwrap();
-        let registry = Registry::new(Api::Gles2, (3, 0), Profile::Core, Fallbacks::All, []);
+        let registry = Registry::new(
+            Api::Gles2,
+            (3, 0),
+            Profile::Core,
+            Fallbacks::All,
+            ["GL_OVR_multiview"],
+        );
         registry.write_bindings(StructGenerator, &mut file).unwrap();
     } else {
         let mut file = File::create(&dest.join("gl_bindings.rs")).unwrap();
-        let registry = Registry::new(Api::Gl, (3, 3), Profile::Core, Fallbacks::All, []);
+        let registry = Registry::new(
+            Api::Gl,
+            (3, 3),
+            Profile::Core,
+            Fallbacks::All,
+            [
+                "GL_ARB_invalidate_subdata",
+                "GL_EXT_semaphore",
+                "GL_EXT_semaphore_win32",
+                "GL_OVR_multiview",
+            ],
+        );
         registry.write_bindings(StructGenerator, &mut file).unwrap();
     }
 }
@@ -103,6 +103,7 @@ fn main() {
     let context_attributes = ContextAttributes {
         version: GLVersion::new(3, 3),
         flags: ContextAttributeFlags::empty(),
+        ..Default::default()
     };
     let context_descriptor = device
         .create_context_descriptor(&context_attributes)
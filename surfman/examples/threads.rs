@@ -108,6 +108,7 @@ fn main() {
     let context_attributes = ContextAttributes {
         version: GLVersion::new(3, 0),
         flags: ContextAttributeFlags::ALPHA,
+        ..Default::default()
     };
     let context_descriptor = device
         .create_context_descriptor(&context_attributes)
@@ -0,0 +1,192 @@
+// surfman/surfman/src/array_surface.rs
+//
+//! Surfaces backed by array textures, for multiview (e.g. stereo) rendering.
+
+use crate::context::ContextID;
+use crate::gl;
+use crate::gl::types::{GLint, GLuint};
+use crate::gl_utils::{self, GLStateGuard};
+use crate::{Error, GLCapability, GLInfo, Gl, WindowingApiError};
+
+use euclid::default::Size2D;
+use std::ptr;
+use std::thread;
+
+/// A surface made up of the layers of a `GL_TEXTURE_2D_ARRAY`, such as one layer per eye for
+/// stereo rendering.
+///
+/// The array texture and its framebuffer objects are local to the context the array surface was
+/// created with. Each layer can be rendered to individually by binding its framebuffer object. If
+/// `GL_OVR_multiview` is available, all layers can also be rendered to at once with a multiview
+/// framebuffer object.
+///
+/// Array surfaces must be destroyed with the `destroy_array_surface()` method, or a panic will
+/// occur.
+pub struct ArraySurface {
+    pub(crate) size: Size2D<i32>,
+    pub(crate) context_id: ContextID,
+    pub(crate) texture_object: GLuint,
+    pub(crate) layer_framebuffer_objects: Vec<GLuint>,
+    pub(crate) multiview_framebuffer_object: Option<GLuint>,
+}
+
+impl Drop for ArraySurface {
+    fn drop(&mut self) {
+        if self.texture_object != 0 && !thread::panicking() {
+            panic!("Should have destroyed the array surface first with `destroy_array_surface()`!")
+        }
+    }
+}
+
+impl ArraySurface {
+    // Creates the array texture and its framebuffer objects. The context that the surface is to
+    // be used with must be current, and `gl_info` must describe it.
+    pub(crate) fn new(
+        gl: &Gl,
+        gl_info: &GLInfo,
+        context_id: ContextID,
+        size: Size2D<i32>,
+        layers: u32,
+    ) -> Result<ArraySurface, Error> {
+        unsafe {
+            let mut max_layers = 0;
+            gl.GetIntegerv(gl::MAX_ARRAY_TEXTURE_LAYERS, &mut max_layers);
+            if size.width <= 0 || size.height <= 0 || layers == 0 || layers > max_layers as u32 {
+                return Err(Error::SurfaceCreationFailed(WindowingApiError::BadValue));
+            }
+
+            let _state_guard = GLStateGuard::new(gl);
+            let mut array_surface = ArraySurface {
+                size,
+                context_id,
+                texture_object: 0,
+                layer_framebuffer_objects: vec![],
+                multiview_framebuffer_object: None,
+            };
+            gl.GenTextures(1, &mut array_surface.texture_object);
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, array_surface.texture_object);
+            gl.TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                gl::RGBA as GLint,
+                size.width,
+                size.height,
+                layers as GLint,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                ptr::null(),
+            );
+            gl.TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_MIN_FILTER,
+                gl::LINEAR as GLint,
+            );
+            gl.TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_MAG_FILTER,
+                gl::LINEAR as GLint,
+            );
+            gl.BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+
+            for layer in 0..layers {
+                let mut framebuffer_object = 0;
+                gl.GenFramebuffers(1, &mut framebuffer_object);
+                array_surface
+                    .layer_framebuffer_objects
+                    .push(framebuffer_object);
+                gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer_object);
+                gl.FramebufferTextureLayer(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    array_surface.texture_object,
+                    0,
+                    layer as GLint,
+                );
+                if let Err(err) = check_framebuffer_status(gl) {
+                    array_surface.destroy(gl);
+                    return Err(err);
+                }
+            }
+
+            if gl_info.supports(GLCapability::Multiview)
+                && gl.FramebufferTextureMultiviewOVR.is_loaded()
+            {
+                let mut framebuffer_object = 0;
+                gl.GenFramebuffers(1, &mut framebuffer_object);
+                array_surface.multiview_framebuffer_object = Some(framebuffer_object);
+                gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer_object);
+                gl.FramebufferTextureMultiviewOVR(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    array_surface.texture_object,
+                    0,
+                    0,
+                    layers as GLint,
+                );
+                if let Err(err) = check_framebuffer_status(gl) {
+                    array_surface.destroy(gl);
+                    return Err(err);
+                }
+            }
+
+            Ok(array_surface)
+        }
+    }
+
+    // Deletes the array texture and its framebuffer objects. The context that the surface was
+    // created with must be current.
+    pub(crate) fn destroy(&mut self, gl: &Gl) {
+        let framebuffer_objects = self
+            .layer_framebuffer_objects
+            .drain(..)
+            .chain(self.multiview_framebuffer_object.take());
+        for framebuffer_object in framebuffer_objects {
+            gl_utils::destroy_framebuffer(gl, framebuffer_object);
+        }
+        unsafe {
+            gl.DeleteTextures(1, &self.texture_object);
+        }
+        self.texture_object = 0;
+    }
+
+    /// Returns the size of each layer.
+    #[inline]
+    pub fn size(&self) -> Size2D<i32> {
+        self.size
+    }
+
+    /// Returns the ID of the context that this array surface belongs to.
+    #[inline]
+    pub fn context_id(&self) -> ContextID {
+        self.context_id
+    }
+
+    /// Returns the number of layers.
+    #[inline]
+    pub fn layers(&self) -> u32 {
+        self.layer_framebuffer_objects.len() as u32
+    }
+
+    /// Returns the OpenGL `GL_TEXTURE_2D_ARRAY` texture object holding the layers.
+    #[inline]
+    pub fn texture_object(&self) -> GLuint {
+        self.texture_object
+    }
+
+    /// Returns the OpenGL framebuffer object that renders to a single layer.
+    ///
+    /// Panics if the layer is out of range.
+    #[inline]
+    pub fn layer_framebuffer_object(&self, layer: u32) -> GLuint {
+        self.layer_framebuffer_objects[layer as usize]
+    }
+}
+
+// Returns an error if the currently-bound framebuffer is incomplete.
+unsafe fn check_framebuffer_status(gl: &Gl) -> Result<(), Error> {
+    match gl.CheckFramebufferStatus(gl::FRAMEBUFFER) {
+        gl::FRAMEBUFFER_COMPLETE => Ok(()),
+        status => Err(Error::SurfaceFramebufferIncomplete(status)),
+    }
+}
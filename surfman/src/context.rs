@@ -10,6 +10,7 @@ use crate::info::GLVersion;
 use crate::Gl;
 
 use std::ffi::CStr;
+use std::fmt::{self, Display, Formatter};
 use std::os::raw::c_char;
 use std::sync::Mutex;
 
@@ -19,6 +20,12 @@ use std::sync::Mutex;
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct ContextID(pub u64);
 
+impl Display for ContextID {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 lazy_static! {
     #[doc(hidden)]
     pub static ref CREATE_CONTEXT_MUTEX: Mutex<ContextID> = Mutex::new(ContextID(0));
@@ -51,7 +58,7 @@ bitflags! {
 /// Attributes that control aspects of a context and/or surfaces created from that context.
 ///
 /// Similar to: https://www.khronos.org/registry/webgl/specs/latest/1.0/#WEBGLCONTEXTATTRIBUTES
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct ContextAttributes {
     /// The OpenGL or OpenGL ES version that this context supports.
     ///
@@ -60,18 +67,136 @@ pub struct ContextAttributes {
     pub version: GLVersion,
     /// Various flags.
     pub flags: ContextAttributeFlags,
+    /// What happens to pending commands when this context stops being current.
+    pub flush_behavior: FlushBehavior,
+    /// The number of bits in each color channel of the framebuffer.
+    pub color_bits: ColorBits,
+    /// How the GPU should schedule this context's work relative to other contexts.
+    pub priority: ContextPriority,
+    /// What the context does when the GPU is reset.
+    pub reset_notification: ResetNotification,
 }
 
-impl ContextAttributes {
-    #[allow(dead_code)]
-    pub(crate) fn zeroed() -> ContextAttributes {
+/// The number of bits in each color channel of a context's framebuffer.
+///
+/// The default is 8 bits per channel. This makes it possible to request formats such as RGB565
+/// or RGB10A2. The alpha size is only used if `ContextAttributeFlags::ALPHA` is set.
+///
+/// This is only honored on EGL backends. Elsewhere, 8-bit channels are always used, and
+/// `Device::context_descriptor_attributes()` reports the default.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ColorBits {
+    /// The number of bits in the red channel.
+    pub red: u8,
+    /// The number of bits in the green channel.
+    pub green: u8,
+    /// The number of bits in the blue channel.
+    pub blue: u8,
+    /// The number of bits in the alpha channel.
+    pub alpha: u8,
+}
+
+impl Default for ColorBits {
+    #[inline]
+    fn default() -> ColorBits {
+        ColorBits {
+            red: 8,
+            green: 8,
+            blue: 8,
+            alpha: 8,
+        }
+    }
+}
+
+/// Controls whether a context flushes its pending commands when it is released.
+///
+/// This is only honored on EGL backends that support `EGL_KHR_context_flush_control`. Elsewhere,
+/// contexts always flush, and `Device::context_descriptor_attributes()` reports `Flush`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FlushBehavior {
+    /// The context's pending commands are flushed when it is made not current. This is the
+    /// default.
+    Flush,
+    /// Making the context not current does not flush it. You must flush it yourself before
+    /// expecting other contexts to see its results.
+    None,
+}
+
+impl Default for FlushBehavior {
+    #[inline]
+    fn default() -> FlushBehavior {
+        FlushBehavior::Flush
+    }
+}
+
+/// A scheduling priority hint for a context.
+///
+/// This is only honored on EGL backends that support `EGL_IMG_context_priority`, and even there
+/// the driver may grant a different priority. Elsewhere, it is ignored, and
+/// `Device::context_descriptor_attributes()` reports `Medium`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ContextPriority {
+    /// The context's work may be delayed in favor of other contexts, e.g. for background work.
+    Low,
+    /// The context's work is scheduled normally. This is the default.
+    Medium,
+    /// The context's work should be scheduled ahead of other contexts, e.g. for a compositor.
+    High,
+}
+
+impl Default for ContextPriority {
+    #[inline]
+    fn default() -> ContextPriority {
+        ContextPriority::Medium
+    }
+}
+
+/// The reset notification strategy of a context, which decides what happens to it when the GPU
+/// is reset.
+///
+/// This is independent of robust buffer access. It is only honored on EGL backends that support
+/// `EGL_EXT_create_context_robustness`. Elsewhere, it is ignored, and
+/// `Device::context_descriptor_attributes()` reports `None`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ResetNotification {
+    /// The context isn't told about resets, and its state after one is undefined. This is the
+    /// default.
+    None,
+    /// The context is lost on a reset, which `glGetGraphicsResetStatus()` reports, and must be
+    /// recreated.
+    LoseContext,
+}
+
+impl Default for ResetNotification {
+    #[inline]
+    fn default() -> ResetNotification {
+        ResetNotification::None
+    }
+}
+
+/// The default attributes ask for version 0.0 and no flags, and every other field takes its own
+/// default. Set the fields you care about and fill in the rest with `..Default::default()`.
+impl Default for ContextAttributes {
+    #[inline]
+    fn default() -> ContextAttributes {
         ContextAttributes {
             version: GLVersion::new(0, 0),
             flags: ContextAttributeFlags::empty(),
+            flush_behavior: FlushBehavior::default(),
+            color_bits: ColorBits::default(),
+            priority: ContextPriority::default(),
+            reset_notification: ResetNotification::default(),
         }
     }
 }
 
+impl ContextAttributes {
+    #[allow(dead_code)]
+    pub(crate) fn zeroed() -> ContextAttributes {
+        ContextAttributes::default()
+    }
+}
+
 #[cfg(target_os = "android")]
 pub(crate) fn current_context_uses_compatibility_profile(_gl: &Gl) -> bool {
     false
@@ -2,6 +2,10 @@
 //
 //! Various errors that methods can produce.
 
+use crate::gl::types::GLenum;
+
+use euclid::default::Size2D;
+
 /// Various errors that methods can produce.
 #[derive(Debug)]
 pub enum Error {
@@ -25,32 +29,72 @@ pub enum Error {
     PixelFormatSelectionFailed(WindowingApiError),
     /// The system couldn't choose an OpenGL pixel format.
     NoPixelFormatFound,
+    /// The chosen pixel format doesn't support rendering with the requested OpenGL API.
+    ConfigNotRenderableForApi,
     /// The system couldn't create an OpenGL context.
     ContextCreationFailed(WindowingApiError),
     /// The system couldn't destroy the OpenGL context.
     ContextDestructionFailed(WindowingApiError),
     /// The system couldn't make the OpenGL context current or not current.
     MakeCurrentFailed(WindowingApiError),
+    /// Making the OpenGL context current took longer than the device's make-current timeout.
+    MakeCurrentTimedOut,
+    /// The context couldn't be made current because the surface was created with an
+    /// incompatible config.
+    SurfaceConfigMismatch {
+        /// The ID of the EGL config that the surface was created with.
+        surface_config: i32,
+        /// The ID of the EGL config that the context was created with.
+        context_config: i32,
+    },
     /// The system OpenGL library couldn't be located.
     NoGLLibraryFound,
     /// An extension necessary for this library to function isn't supported.
     RequiredExtensionUnavailable,
     /// Looking up an OpenGL function address failed.
     GLFunctionNotFound,
+    /// An OpenGL function that surfman relies on internally didn't load.
+    RequiredGLFunctionMissing(&'static str),
     /// This context renders to an externally-managed render target.
     ExternalRenderTarget,
     /// A surface was already attached to this context.
     SurfaceAlreadyBound,
+    /// The surface was created as texture-only, so it can't be attached to a context.
+    SurfaceNotRenderable,
+    /// The context has no surface attached to it.
+    NoSurfaceAttached,
     /// No suitable adapter could be found.
     NoAdapterFound,
     /// The device couldn't be opened.
     DeviceOpenFailed,
+    /// The device's display has been terminated, possibly by another library, so nothing can be
+    /// done with it.
+    DisplayNotInitialized,
     /// The system couldn't create a surface.
     SurfaceCreationFailed(WindowingApiError),
+    /// The system ran out of memory while allocating a surface.
+    ///
+    /// Freeing other surfaces and trying again may succeed.
+    OutOfSurfaceMemory,
+    /// The requested surface is larger than the device can allocate.
+    SurfaceTooLarge {
+        /// The size that was requested.
+        requested: Size2D<i32>,
+        /// The largest width or height that a surface can have on this device.
+        max: i32,
+    },
+    /// The surface's framebuffer was incomplete, with the given `glCheckFramebufferStatus()`
+    /// status. This can happen if the requested depth or stencil buffers couldn't be allocated.
+    SurfaceFramebufferIncomplete(GLenum),
+    /// Copying a surface into an OpenGL texture failed with the given `glGetError()` error,
+    /// usually because the texture is too small or has an incompatible format.
+    TextureCopyFailed(GLenum),
     /// The system couldn't import a surface from another thread.
     SurfaceImportFailed(WindowingApiError),
     /// The system couldn't create a surface texture from a surface.
     SurfaceTextureCreationFailed(WindowingApiError),
+    /// The surface texture could not be released from its OpenGL texture.
+    SurfaceTextureReleaseFailed(WindowingApiError),
     /// The system couldn't present a widget surface.
     PresentFailed(WindowingApiError),
     /// A context couldn't be created because there is no current context.
@@ -80,6 +124,10 @@ pub enum Error {
     SurfaceDataInaccessible,
     /// The surface could not be locked for CPU reading due to an OS error.
     SurfaceLockFailed,
+    /// The pixel data supplied for a surface doesn't match its size.
+    InvalidSurfaceData,
+    /// The requested rectangle is empty or extends past the edges of the surface.
+    RectOutOfBounds,
     /// A connection to the display server could not be opened.
     ConnectionFailed,
     /// A connection to the window server is required to open a hardware device.
@@ -96,6 +144,25 @@ pub enum Error {
     IncompatibleNativeDevice,
 }
 
+impl Error {
+    // Returns true if this error was caused by the context being lost.
+    #[allow(dead_code)]
+    pub(crate) fn is_context_lost(&self) -> bool {
+        matches!(
+            *self,
+            Error::PixelFormatSelectionFailed(WindowingApiError::ContextLost)
+                | Error::ContextCreationFailed(WindowingApiError::ContextLost)
+                | Error::ContextDestructionFailed(WindowingApiError::ContextLost)
+                | Error::MakeCurrentFailed(WindowingApiError::ContextLost)
+                | Error::SurfaceCreationFailed(WindowingApiError::ContextLost)
+                | Error::SurfaceImportFailed(WindowingApiError::ContextLost)
+                | Error::SurfaceTextureCreationFailed(WindowingApiError::ContextLost)
+                | Error::SurfaceTextureReleaseFailed(WindowingApiError::ContextLost)
+                | Error::PresentFailed(WindowingApiError::ContextLost)
+        )
+    }
+}
+
 /// Abstraction of the errors that EGL, CGL, GLX, CGL, etc. return.
 ///
 /// They all tend to follow similar patterns.
@@ -3,8 +3,129 @@
 //! Various OpenGL utilities used by the different backends.
 
 use crate::gl;
-use crate::gl::types::{GLenum, GLint, GLuint};
-use crate::Gl;
+use crate::gl::types::{GLboolean, GLenum, GLfloat, GLint, GLuint};
+use crate::{Error, Gl};
+
+// Saves the GL state that surfman's internal helpers may touch, restoring it when dropped.
+//
+// Create this after making the relevant context current, so that internal work done on a
+// context the application is using is transparent to it.
+//
+// Dropping the guard also drains every pending GL error, so that errors from surfman's own work
+// don't surface in the application's later `glGetError()` calls. GL can't tell those apart from
+// errors the application raised before the guard was created, so those are cleared too.
+#[allow(dead_code)]
+pub(crate) struct GLStateGuard<'a> {
+    gl: &'a Gl,
+    draw_framebuffer: GLint,
+    read_framebuffer: GLint,
+    texture_2d: GLint,
+    viewport: [GLint; 4],
+    clear_color: [GLfloat; 4],
+    scissor_box: [GLint; 4],
+    scissor_test: GLboolean,
+}
+
+#[allow(dead_code)]
+impl<'a> GLStateGuard<'a> {
+    pub(crate) fn new(gl: &'a Gl) -> GLStateGuard<'a> {
+        unsafe {
+            let mut guard = GLStateGuard {
+                gl,
+                draw_framebuffer: 0,
+                read_framebuffer: 0,
+                texture_2d: 0,
+                viewport: [0; 4],
+                clear_color: [0.0; 4],
+                scissor_box: [0; 4],
+                scissor_test: gl.IsEnabled(gl::SCISSOR_TEST),
+            };
+            gl.GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut guard.draw_framebuffer);
+            gl.GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut guard.read_framebuffer);
+            gl.GetIntegerv(gl::TEXTURE_BINDING_2D, &mut guard.texture_2d);
+            gl.GetIntegerv(gl::VIEWPORT, guard.viewport.as_mut_ptr());
+            gl.GetFloatv(gl::COLOR_CLEAR_VALUE, guard.clear_color.as_mut_ptr());
+            gl.GetIntegerv(gl::SCISSOR_BOX, guard.scissor_box.as_mut_ptr());
+            guard
+        }
+    }
+}
+
+impl<'a> Drop for GLStateGuard<'a> {
+    fn drop(&mut self) {
+        let gl = self.gl;
+        unsafe {
+            gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.draw_framebuffer as GLuint);
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, self.read_framebuffer as GLuint);
+            gl.BindTexture(gl::TEXTURE_2D, self.texture_2d as GLuint);
+            let [x, y, width, height] = self.viewport;
+            gl.Viewport(x, y, width, height);
+            let [r, g, b, a] = self.clear_color;
+            gl.ClearColor(r, g, b, a);
+            let [x, y, width, height] = self.scissor_box;
+            gl.Scissor(x, y, width, height);
+            if self.scissor_test == gl::TRUE {
+                gl.Enable(gl::SCISSOR_TEST);
+            } else {
+                gl.Disable(gl::SCISSOR_TEST);
+            }
+        }
+
+        // Don't leave errors from our own GL work behind for the application to trip over.
+        for error in drain_gl_errors(gl) {
+            debug!("surfman: internal GL work raised error 0x{:x}", error);
+        }
+    }
+}
+
+// Checks that the GL functions surfman calls internally were loaded, reporting the first one
+// that wasn't.
+//
+// A broken GL library may hand back null for core entry points. Calling one of those panics, so
+// this is checked once after loading, when creating the context, instead of mid-frame.
+#[allow(dead_code)]
+pub(crate) fn check_required_gl_functions(gl: &Gl) -> Result<(), Error> {
+    let functions = [
+        ("glBindFramebuffer", &gl.BindFramebuffer),
+        ("glBindTexture", &gl.BindTexture),
+        ("glCheckFramebufferStatus", &gl.CheckFramebufferStatus),
+        ("glClear", &gl.Clear),
+        ("glClearColor", &gl.ClearColor),
+        ("glDeleteFramebuffers", &gl.DeleteFramebuffers),
+        ("glDeleteTextures", &gl.DeleteTextures),
+        ("glFinish", &gl.Finish),
+        ("glFlush", &gl.Flush),
+        ("glFramebufferTexture2D", &gl.FramebufferTexture2D),
+        ("glGenFramebuffers", &gl.GenFramebuffers),
+        ("glGenTextures", &gl.GenTextures),
+        ("glGetError", &gl.GetError),
+        ("glGetIntegerv", &gl.GetIntegerv),
+        ("glGetString", &gl.GetString),
+        ("glReadPixels", &gl.ReadPixels),
+        ("glViewport", &gl.Viewport),
+    ];
+    match functions.iter().find(|(_, function)| !function.is_loaded()) {
+        Some(&(name, _)) => Err(Error::RequiredGLFunctionMissing(name)),
+        None => Ok(()),
+    }
+}
+
+// Clears and returns all pending GL errors on the current context.
+pub(crate) fn drain_gl_errors(gl: &Gl) -> Vec<GLenum> {
+    // `glGetError()` can return `GL_CONTEXT_LOST` forever, so bound the loop.
+    const MAX_ERRORS: usize = 32;
+
+    let mut errors = vec![];
+    unsafe {
+        while errors.len() < MAX_ERRORS {
+            match gl.GetError() {
+                gl::NO_ERROR => break,
+                error => errors.push(error),
+            }
+        }
+    }
+    errors
+}
 
 #[allow(dead_code)]
 pub(crate) fn create_and_bind_framebuffer(
@@ -49,3 +170,29 @@ pub(crate) fn destroy_framebuffer(gl: &Gl, framebuffer_object: GLuint) {
         gl.DeleteFramebuffers(1, &framebuffer_object);
     }
 }
+
+// Blocks until all commands previously issued on the current context have completed on the GPU.
+//
+// This waits on a fence where sync objects are available and falls back to `glFinish()`
+// otherwise.
+#[allow(dead_code)]
+pub(crate) fn wait_for_gpu_completion(gl: &Gl) {
+    unsafe {
+        if !gl.FenceSync.is_loaded() {
+            gl.Finish();
+            return;
+        }
+
+        let sync = gl.FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+        if sync.is_null() {
+            gl.Finish();
+            return;
+        }
+        if gl.ClientWaitSync(sync, gl::SYNC_FLUSH_COMMANDS_BIT, gl::TIMEOUT_IGNORED)
+            == gl::WAIT_FAILED
+        {
+            gl.Finish();
+        }
+        gl.DeleteSync(sync);
+    }
+}
@@ -3,9 +3,12 @@
 //! OpenGL information.
 
 use crate::gl;
+use crate::gl::types::{GLenum, GLuint};
 use crate::Gl;
 
+use std::collections::HashSet;
 use std::ffi::CStr;
+use std::fmt::{self, Display, Formatter};
 use std::os::raw::c_char;
 
 /// The API (OpenGL or OpenGL ES).
@@ -17,11 +20,22 @@ pub enum GLApi {
     GLES,
 }
 
+impl Display for GLApi {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            GLApi::GL => f.write_str("OpenGL"),
+            GLApi::GLES => f.write_str("OpenGL ES"),
+        }
+    }
+}
+
 /// Describes the OpenGL version that is requested when a context is created.
 ///
 /// Since OpenGL and OpenGL ES have different version numbering schemes, the valid values here
 /// depend on the value of `Device::gl_api()`.
-#[derive(Clone, Copy, Debug, PartialEq)]
+///
+/// Versions are ordered by major version, then minor version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct GLVersion {
     /// The major OpenGL version (e.g. 4 in 4.2).
     pub major: u8,
@@ -30,12 +44,82 @@ pub struct GLVersion {
 }
 
 impl GLVersion {
+    /// OpenGL 2.1.
+    pub const GL_2_1: GLVersion = GLVersion { major: 2, minor: 1 };
+    /// OpenGL 3.0.
+    pub const GL_3_0: GLVersion = GLVersion { major: 3, minor: 0 };
+    /// OpenGL 3.2.
+    pub const GL_3_2: GLVersion = GLVersion { major: 3, minor: 2 };
+    /// OpenGL 3.3.
+    pub const GL_3_3: GLVersion = GLVersion { major: 3, minor: 3 };
+    /// OpenGL 4.0.
+    pub const GL_4_0: GLVersion = GLVersion { major: 4, minor: 0 };
+    /// OpenGL 4.1.
+    pub const GL_4_1: GLVersion = GLVersion { major: 4, minor: 1 };
+    /// OpenGL 4.2.
+    pub const GL_4_2: GLVersion = GLVersion { major: 4, minor: 2 };
+    /// OpenGL 4.3.
+    pub const GL_4_3: GLVersion = GLVersion { major: 4, minor: 3 };
+    /// OpenGL 4.4.
+    pub const GL_4_4: GLVersion = GLVersion { major: 4, minor: 4 };
+    /// OpenGL 4.5.
+    pub const GL_4_5: GLVersion = GLVersion { major: 4, minor: 5 };
+    /// OpenGL 4.6.
+    pub const GL_4_6: GLVersion = GLVersion { major: 4, minor: 6 };
+    /// OpenGL ES 2.0.
+    pub const GLES_2_0: GLVersion = GLVersion { major: 2, minor: 0 };
+    /// OpenGL ES 3.0.
+    pub const GLES_3_0: GLVersion = GLVersion { major: 3, minor: 0 };
+    /// OpenGL ES 3.1.
+    pub const GLES_3_1: GLVersion = GLVersion { major: 3, minor: 1 };
+    /// OpenGL ES 3.2.
+    pub const GLES_3_2: GLVersion = GLVersion { major: 3, minor: 2 };
+
     /// Creates a GL version structure with the given major and minor version numbers.
     #[inline]
     pub fn new(major: u8, minor: u8) -> GLVersion {
         GLVersion { major, minor }
     }
 
+    /// Returns true if a context of this version provides everything that the required version
+    /// does.
+    ///
+    /// Both versions must be of the same API, since OpenGL and OpenGL ES version numbers aren't
+    /// comparable.
+    #[inline]
+    pub fn supports(&self, required: GLVersion) -> bool {
+        *self >= required
+    }
+
+    // Returns the versions of the given API to try when probing for the best supported context,
+    // from newest to oldest.
+    #[allow(dead_code)]
+    pub(crate) fn probe_ladder(api: GLApi) -> &'static [GLVersion] {
+        const GL_VERSIONS: [GLVersion; 11] = [
+            GLVersion::GL_4_6,
+            GLVersion::GL_4_5,
+            GLVersion::GL_4_4,
+            GLVersion::GL_4_3,
+            GLVersion::GL_4_2,
+            GLVersion::GL_4_1,
+            GLVersion::GL_4_0,
+            GLVersion::GL_3_3,
+            GLVersion::GL_3_2,
+            GLVersion::GL_3_0,
+            GLVersion::GL_2_1,
+        ];
+        const GLES_VERSIONS: [GLVersion; 4] = [
+            GLVersion::GLES_3_2,
+            GLVersion::GLES_3_1,
+            GLVersion::GLES_3_0,
+            GLVersion::GLES_2_0,
+        ];
+        match api {
+            GLApi::GL => &GL_VERSIONS,
+            GLApi::GLES => &GLES_VERSIONS,
+        }
+    }
+
     #[allow(dead_code)]
     pub(crate) fn current(gl: &Gl) -> GLVersion {
         unsafe {
@@ -63,3 +147,200 @@ impl GLVersion {
         }
     }
 }
+
+impl Display for GLVersion {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// An optional OpenGL feature that `GLInfo::supports()` can check for.
+///
+/// Each capability is resolved against the context's API, version, and extensions, since the
+/// features became core in different versions of OpenGL and OpenGL ES.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GLCapability {
+    /// Compute shaders: OpenGL 4.3, OpenGL ES 3.1, or `GL_ARB_compute_shader`.
+    ComputeShaders,
+    /// Geometry shaders: OpenGL 3.2, OpenGL ES 3.2, or `GL_EXT_geometry_shader` or
+    /// `GL_OES_geometry_shader`.
+    GeometryShaders,
+    /// `GL_TEXTURE_2D_ARRAY` textures: OpenGL 3.0, OpenGL ES 3.0, or `GL_EXT_texture_array`.
+    TextureArrays,
+    /// Rendering to several array texture layers at once, via `GL_OVR_multiview`.
+    Multiview,
+    /// Framebuffers with sRGB encoding: OpenGL 3.0, OpenGL ES 3.0, `GL_ARB_framebuffer_sRGB`,
+    /// `GL_EXT_framebuffer_sRGB`, or `GL_EXT_sRGB`.
+    SRGBFramebuffer,
+}
+
+/// Information about the OpenGL implementation behind a context.
+///
+/// This is gathered when the context is created. It is chiefly useful for logging, for detecting
+/// specific drivers, and for checking which optional features are available.
+#[derive(Clone, Debug)]
+pub struct GLInfo {
+    renderer: String,
+    vendor: String,
+    api: GLApi,
+    version: GLVersion,
+    shading_language_version: GLVersion,
+    extensions: HashSet<String>,
+}
+
+impl GLInfo {
+    // Reads the information from the current context.
+    #[allow(dead_code)]
+    pub(crate) fn populate(gl: &Gl) -> GLInfo {
+        unsafe {
+            let renderer = gl_string(gl, gl::RENDERER);
+            let vendor = gl_string(gl, gl::VENDOR);
+            let version_string = gl_string(gl, gl::VERSION);
+            let api = if version_string.starts_with("OpenGL ES") {
+                GLApi::GLES
+            } else {
+                GLApi::GL
+            };
+            let version =
+                parse_version_string(&version_string).unwrap_or_else(|| GLVersion::new(0, 0));
+            let shading_language_version =
+                parse_version_string(&gl_string(gl, gl::SHADING_LANGUAGE_VERSION))
+                    .unwrap_or_else(|| GLVersion::new(0, 0));
+            GLInfo {
+                renderer,
+                vendor,
+                api,
+                version,
+                shading_language_version,
+                extensions: gl_extensions(gl),
+            }
+        }
+    }
+
+    /// Returns the `GL_RENDERER` string, which names the renderer (e.g. the GPU).
+    #[inline]
+    pub fn renderer(&self) -> &str {
+        &self.renderer
+    }
+
+    /// Returns the `GL_VENDOR` string, which names the company responsible for the
+    /// implementation.
+    #[inline]
+    pub fn vendor(&self) -> &str {
+        &self.vendor
+    }
+
+    /// Returns the version of GLSL (or GLSL ES) that the implementation supports.
+    ///
+    /// The minor version is reported as written, so GLSL 4.60 is major version 4 and minor
+    /// version 60. If the version string couldn't be parsed, this is 0.0.
+    #[inline]
+    pub fn shading_language_version(&self) -> GLVersion {
+        self.shading_language_version
+    }
+
+    /// Returns whether the implementation is OpenGL or OpenGL ES, according to `GL_VERSION`.
+    #[inline]
+    pub fn api(&self) -> GLApi {
+        self.api
+    }
+
+    /// Returns the OpenGL (or OpenGL ES) version that the implementation reports.
+    ///
+    /// This can be newer than the version that was requested. If the version string couldn't be
+    /// parsed, this is 0.0.
+    #[inline]
+    pub fn version(&self) -> GLVersion {
+        self.version
+    }
+
+    /// Returns true if the implementation advertises the given extension, such as
+    /// `GL_OVR_multiview`.
+    #[inline]
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
+
+    /// Returns true if the implementation provides the given capability, either as part of its
+    /// version or through an extension.
+    pub fn supports(&self, capability: GLCapability) -> bool {
+        let (gl_version, gles_version, extensions): (_, _, &[&str]) = match capability {
+            GLCapability::ComputeShaders => (
+                Some(GLVersion::GL_4_3),
+                Some(GLVersion::GLES_3_1),
+                &["GL_ARB_compute_shader"],
+            ),
+            GLCapability::GeometryShaders => (
+                Some(GLVersion::GL_3_2),
+                Some(GLVersion::GLES_3_2),
+                &["GL_EXT_geometry_shader", "GL_OES_geometry_shader"],
+            ),
+            GLCapability::TextureArrays => (
+                Some(GLVersion::GL_3_0),
+                Some(GLVersion::GLES_3_0),
+                &["GL_EXT_texture_array"],
+            ),
+            GLCapability::Multiview => (None, None, &["GL_OVR_multiview"]),
+            GLCapability::SRGBFramebuffer => (
+                Some(GLVersion::GL_3_0),
+                Some(GLVersion::GLES_3_0),
+                &[
+                    "GL_ARB_framebuffer_sRGB",
+                    "GL_EXT_framebuffer_sRGB",
+                    "GL_EXT_sRGB",
+                ],
+            ),
+        };
+        let core_version = match self.api {
+            GLApi::GL => gl_version,
+            GLApi::GLES => gles_version,
+        };
+        if let Some(core_version) = core_version {
+            if self.version.supports(core_version) {
+                return true;
+            }
+        }
+        extensions
+            .iter()
+            .any(|extension| self.has_extension(extension))
+    }
+}
+
+unsafe fn gl_string(gl: &Gl, name: GLenum) -> String {
+    let string = gl.GetString(name) as *const c_char;
+    if string.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(string).to_string_lossy().into_owned()
+}
+
+// Lists the extensions of the current context. OpenGL ES 2.0 has no `glGetStringi()`, so this
+// falls back to splitting `GL_EXTENSIONS`, which core profiles don't support.
+unsafe fn gl_extensions(gl: &Gl) -> HashSet<String> {
+    if gl.GetStringi.is_loaded() {
+        let mut extension_count = 0;
+        gl.GetIntegerv(gl::NUM_EXTENSIONS, &mut extension_count);
+        if gl.GetError() == gl::NO_ERROR {
+            return (0..extension_count as GLuint)
+                .map(|index| gl.GetStringi(gl::EXTENSIONS, index) as *const c_char)
+                .filter(|extension| !extension.is_null())
+                .map(|extension| CStr::from_ptr(extension).to_string_lossy().into_owned())
+                .collect();
+        }
+    }
+    gl_string(gl, gl::EXTENSIONS)
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect()
+}
+
+// Extracts a version number from a string like "OpenGL ES GLSL ES 3.00 (ANGLE 2.1)" or
+// "4.60 NVIDIA".
+pub(crate) fn parse_version_string(string: &str) -> Option<GLVersion> {
+    let start = string.find(|c: char| c.is_ascii_digit())?;
+    let version = string[start..].split_whitespace().next()?;
+    let mut components = version.split('.');
+    let major = components.next()?.parse().ok()?;
+    let minor = components.next()?.parse().ok()?;
+    Some(GLVersion::new(major, minor))
+}
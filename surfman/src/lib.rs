@@ -7,6 +7,34 @@
 //! useful as a lightweight framework for *just* initializing rendering in native applications.
 //! This is in contrast to crates like SDL, GLFW, winit, and Glutin, all of which have a broader
 //! focus in that they manage windowing and the event loop as well.
+//!
+//! Sizes, points, and rectangles in the API are `euclid` types. They are re-exported as
+//! `surfman::Size2D`, `surfman::Point2D`, and `surfman::Rect`, so you don't need to depend on the
+//! same version of `euclid` as `surfman` does:
+//!
+//! ```no_run
+//! use surfman::{Connection, ContextAttributeFlags, ContextAttributes, GLVersion};
+//! use surfman::{Size2D, SurfaceAccess, SurfaceType};
+//!
+//! let connection = Connection::new().unwrap();
+//! let adapter = connection.create_low_power_adapter().unwrap();
+//! let mut device = connection.create_device(&adapter).unwrap();
+//! let attributes = ContextAttributes {
+//!     version: GLVersion::new(3, 0),
+//!     flags: ContextAttributeFlags::empty(),
+//!     ..Default::default()
+//! };
+//! let descriptor = device.create_context_descriptor(&attributes).unwrap();
+//! let mut context = device.create_context(&descriptor, None).unwrap();
+//!
+//! let size = Size2D::new(256, 256);
+//! let mut surface = device
+//!     .create_surface(&context, SurfaceAccess::GPUOnly, SurfaceType::Generic { size })
+//!     .unwrap();
+//!
+//! device.destroy_surface(&mut context, &mut surface).unwrap();
+//! device.destroy_context(&mut context).unwrap();
+//! ```
 
 #![warn(missing_docs)]
 
@@ -22,6 +50,9 @@ extern crate log;
 #[macro_use]
 extern crate objc;
 
+#[macro_use]
+mod trace;
+
 pub mod platform;
 pub use platform::default::connection::{Connection, NativeConnection};
 pub use platform::default::context::{Context, ContextDescriptor, NativeContext};
@@ -43,13 +74,15 @@ pub mod error;
 pub use crate::error::{Error, WindowingApiError};
 
 mod context;
-pub use crate::context::{ContextAttributeFlags, ContextAttributes, ContextID};
+pub use crate::context::{ColorBits, ContextAttributeFlags, ContextAttributes, ContextID};
+pub use crate::context::{ContextPriority, FlushBehavior, ResetNotification};
 
 mod info;
-pub use crate::info::{GLApi, GLVersion};
+pub use crate::info::{GLApi, GLCapability, GLInfo, GLVersion};
 
 mod surface;
-pub use crate::surface::{SurfaceAccess, SurfaceID, SurfaceInfo, SurfaceType, SystemSurfaceInfo};
+pub use crate::surface::{AlphaType, ExternalSurfaceInfo, InvalidateFlags, SystemSurfaceInfo};
+pub use crate::surface::{SurfaceAccess, SurfaceID, SurfaceInfo, SurfaceType, SurfaceUsage};
 
 pub mod macros;
 
@@ -58,9 +91,21 @@ pub(crate) use crate::gl::Gl;
 #[cfg(target_os = "android")]
 pub(crate) use crate::gl::Gles2 as Gl;
 
+#[cfg(angle)]
+mod array_surface;
+#[cfg(angle)]
+pub use crate::array_surface::ArraySurface;
+
 mod gl_utils;
 mod renderbuffers;
 
+#[cfg(angle)]
+mod tiled_surface;
+#[cfg(angle)]
+pub use crate::tiled_surface::{Tile, TiledSurface};
+
+pub use euclid::default::{Point2D, Rect, Size2D};
+
 mod gl {
     include!(concat!(env!("OUT_DIR"), "/gl_bindings.rs"));
 }
@@ -9,10 +9,10 @@ use crate::egl;
 use crate::egl::types::{EGLConfig, EGLContext, EGLSurface, EGLint};
 use crate::platform::generic::egl::context::{self, CurrentContextGuard};
 use crate::platform::generic::egl::device::EGL_FUNCTIONS;
-use crate::platform::generic::egl::error::ToWindowingApiError;
+use crate::platform::generic::egl::error::egl_error;
 use crate::platform::generic::egl::surface::ExternalEGLSurfaces;
 use crate::surface::Framebuffer;
-use crate::{ContextAttributes, Error, Gl, SurfaceInfo};
+use crate::{ContextAttributes, Error, ExternalSurfaceInfo, GLApi, Gl, SurfaceInfo};
 
 use std::mem;
 use std::os::raw::c_void;
@@ -67,9 +67,11 @@ impl Device {
         &self,
         attributes: &ContextAttributes,
     ) -> Result<ContextDescriptor, Error> {
+        device_span!("create_context_descriptor", flags = ?attributes.flags);
         unsafe {
             ContextDescriptor::new(
                 self.egl_display,
+                self.gl_api(),
                 attributes,
                 &[
                     egl::COLOR_BUFFER_TYPE as EGLint,
@@ -92,6 +94,7 @@ impl Device {
         descriptor: &ContextDescriptor,
         share_with: Option<&Context>,
     ) -> Result<Context, Error> {
+        device_span!("create_context", shared = share_with.is_some());
         let mut next_context_id = CREATE_CONTEXT_MUTEX.lock().unwrap();
 
         let egl_display = self.egl_display;
@@ -106,8 +109,14 @@ impl Device {
                 gl_api,
             )?;
 
-            // Create a dummy pbuffer.
-            let pbuffer = context::create_dummy_pbuffer(egl_display, egl_context);
+            // Create a dummy pbuffer, if the config allows one.
+            let pbuffer = match context::create_default_surface(egl_display, egl_context) {
+                Ok(pbuffer) => pbuffer,
+                Err(err) => {
+                    EGL_FUNCTIONS.with(|egl| egl.DestroyContext(egl_display, egl_context));
+                    return Err(err);
+                }
+            };
 
             // Wrap up the EGL context.
             let context = Context {
@@ -131,10 +140,12 @@ impl Device {
         &self,
         native_context: NativeContext,
     ) -> Result<Context, Error> {
+        device_span!("create_context_from_native_context");
         let mut next_context_id = CREATE_CONTEXT_MUTEX.lock().unwrap();
 
-        // Create a dummy pbuffer.
-        let pbuffer = context::create_dummy_pbuffer(self.egl_display, native_context.egl_context);
+        // Create a dummy pbuffer, if the config allows one.
+        let pbuffer =
+            context::create_default_surface(self.egl_display, native_context.egl_context)?;
 
         // Create the context.
         let context = Context {
@@ -156,6 +167,7 @@ impl Device {
     ///
     /// The context must have been created on this device.
     pub fn destroy_context(&self, context: &mut Context) -> Result<(), Error> {
+        device_span!("destroy_context", context = self.context_id(context).0);
         if context.egl_context == egl::NO_CONTEXT {
             return Ok(());
         }
@@ -168,16 +180,22 @@ impl Device {
             }
 
             EGL_FUNCTIONS.with(|egl| {
-                let result = egl.DestroySurface(self.egl_display, context.pbuffer);
-                assert_ne!(result, egl::FALSE);
-                context.pbuffer = egl::NO_SURFACE;
+                if context.pbuffer != egl::NO_SURFACE {
+                    let result = egl.DestroySurface(self.egl_display, context.pbuffer);
+                    assert_ne!(result, egl::FALSE);
+                    context.pbuffer = egl::NO_SURFACE;
+                }
 
-                egl.MakeCurrent(
-                    self.egl_display,
-                    egl::NO_SURFACE,
-                    egl::NO_SURFACE,
-                    egl::NO_CONTEXT,
-                );
+                // Only unbind the context if it's current on this thread, so that destroying one
+                // context doesn't unbind an unrelated one.
+                if egl.GetCurrentContext() == context.egl_context {
+                    egl.MakeCurrent(
+                        self.egl_display,
+                        egl::NO_SURFACE,
+                        egl::NO_SURFACE,
+                        egl::NO_CONTEXT,
+                    );
+                }
 
                 if context.context_is_owned {
                     let result = egl.DestroyContext(self.egl_display, context.egl_context);
@@ -202,6 +220,7 @@ impl Device {
     ///
     /// After calling this function, it is valid to use OpenGL rendering commands.
     pub fn make_context_current(&self, context: &Context) -> Result<(), Error> {
+        device_span!("make_context_current", context = self.context_id(context).0);
         unsafe {
             let egl_display = self.egl_display;
             let egl_context = context.egl_context;
@@ -223,8 +242,7 @@ impl Device {
                 let result =
                     egl.MakeCurrent(egl_display, egl_draw_surface, egl_read_surface, egl_context);
                 if result == egl::FALSE {
-                    let err = egl.GetError().to_windowing_api_error();
-                    return Err(Error::MakeCurrentFailed(err));
+                    return Err(egl_error(egl.GetError(), Error::MakeCurrentFailed));
                 }
                 Ok(())
             })
@@ -236,6 +254,7 @@ impl Device {
     /// After calling this function, OpenGL rendering commands will fail until a new context is
     /// made current.
     pub fn make_no_context_current(&self) -> Result<(), Error> {
+        device_span!("make_no_context_current");
         unsafe { context::make_no_context_current(self.egl_display) }
     }
 
@@ -254,6 +273,11 @@ impl Device {
         context: &mut Context,
         new_surface: Surface,
     ) -> Result<(), (Error, Surface)> {
+        device_span!(
+            "bind_surface_to_context",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(&new_surface).size,
+        );
         if context.id != new_surface.context_id {
             return Err((Error::IncompatibleSurface, new_surface));
         }
@@ -276,6 +300,10 @@ impl Device {
         &self,
         context: &mut Context,
     ) -> Result<Option<Surface>, Error> {
+        device_span!(
+            "unbind_surface_from_context",
+            context = self.context_id(context).0
+        );
         match context.framebuffer {
             Framebuffer::External { .. } => return Err(Error::ExternalRenderTarget),
             Framebuffer::None => return Ok(None),
@@ -296,6 +324,18 @@ impl Device {
         }
     }
 
+    /// Returns the API that the given context was created for, per `EGL_CONTEXT_CLIENT_TYPE`.
+    #[inline]
+    pub fn context_gl_api(&self, context: &Context) -> GLApi {
+        unsafe { context::context_gl_api(self.egl_display, context.egl_context) }
+    }
+
+    /// Returns the `EGL_NATIVE_VISUAL_ID` of the given context's config, or 0 if there is none.
+    #[inline]
+    pub fn context_native_visual_id(&self, context: &Context) -> EGLint {
+        unsafe { context::context_native_visual_id(self.egl_display, context.egl_context) }
+    }
+
     /// Returns the attributes that the context descriptor was created with.
     pub fn context_descriptor_attributes(
         &self,
@@ -352,6 +392,16 @@ impl Device {
         }
     }
 
+    /// Returns what EGL reports about an adopted context's render target, or `None` otherwise.
+    pub fn context_external_surface_info(&self, context: &Context) -> Option<ExternalSurfaceInfo> {
+        match context.framebuffer {
+            Framebuffer::External(ref surfaces) => unsafe {
+                Some(surfaces.info(self.egl_display, context.id))
+            },
+            Framebuffer::None | Framebuffer::Surface(_) => None,
+        }
+    }
+
     /// Given a context, returns its underlying EGL context and attached surfaces.
     pub fn native_context(&self, context: &Context) -> NativeContext {
         let (egl_draw_surface, egl_read_surface) = match context.framebuffer {
@@ -126,6 +126,14 @@ impl Device {
         _: SurfaceAccess,
         surface_type: SurfaceType<NativeWidget>,
     ) -> Result<Surface, Error> {
+        device_span!(
+            "create_surface",
+            context = self.context_id(context).0,
+            size = ?match surface_type {
+                SurfaceType::Generic { size } => Some(size),
+                SurfaceType::Widget { .. } => None,
+            },
+        );
         match surface_type {
             SurfaceType::Generic { size } => self.create_generic_surface(context, &size),
             SurfaceType::Widget { native_widget } => unsafe {
@@ -245,6 +253,11 @@ impl Device {
         context: &mut Context,
         surface: Surface,
     ) -> Result<SurfaceTexture, (Error, Surface)> {
+        device_span!(
+            "create_surface_texture",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(&surface).size,
+        );
         unsafe {
             match surface.objects {
                 SurfaceObjects::Window { .. } => return Err((Error::WidgetAttached, surface)),
@@ -278,6 +291,11 @@ impl Device {
     /// The supplied context must match the context the surface was created with, or an
     /// `IncompatibleSurface` error is returned.
     pub fn present_surface(&self, context: &Context, surface: &mut Surface) -> Result<(), Error> {
+        device_span!(
+            "present_surface",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(surface).size,
+        );
         if context.id != surface.context_id {
             return Err(Error::IncompatibleSurface);
         }
@@ -300,6 +318,7 @@ impl Device {
         surface: &mut Surface,
         size: Size2D<i32>,
     ) -> Result<(), Error> {
+        device_span!("resize_surface", context = self.context_id(_context).0, size = ?size);
         surface.size = size;
         Ok(())
     }
@@ -350,6 +369,11 @@ impl Device {
         context: &mut Context,
         surface: &mut Surface,
     ) -> Result<(), Error> {
+        device_span!(
+            "destroy_surface",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(surface).size,
+        );
         if context.id != surface.context_id {
             return Err(Error::IncompatibleSurface);
         }
@@ -408,6 +432,10 @@ impl Device {
         context: &mut Context,
         mut surface_texture: SurfaceTexture,
     ) -> Result<Surface, (Error, SurfaceTexture)> {
+        device_span!(
+            "destroy_surface_texture",
+            context = self.context_id(context).0
+        );
         let _guard = self.temporarily_make_context_current(context);
         GL_FUNCTIONS.with(|gl| {
             unsafe {
@@ -2,33 +2,51 @@
 //
 //! Functionality common to backends using EGL contexts.
 
-use super::device::EGL_FUNCTIONS;
-use super::error::ToWindowingApiError;
+use super::device::{display_egl_version, display_extension_is_supported};
+use super::device::{supports_surfaceless_context, EGL_FUNCTIONS};
+use super::error::egl_error;
 use super::ffi::EGL_CONTEXT_OPENGL_PROFILE_MASK;
+use super::ffi::EGL_CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY_EXT;
+use super::ffi::EGL_CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY_KHR;
+use super::ffi::EGL_CONTEXT_PRIORITY_LOW_IMG;
 use super::ffi::{EGL_CONTEXT_MINOR_VERSION_KHR, EGL_CONTEXT_OPENGL_COMPATIBILITY_PROFILE_BIT};
+use super::ffi::{EGL_CONTEXT_PRIORITY_HIGH_IMG, EGL_CONTEXT_PRIORITY_LEVEL_IMG};
+use super::ffi::{EGL_CONTEXT_RELEASE_BEHAVIOR_KHR, EGL_CONTEXT_RELEASE_BEHAVIOR_NONE_KHR};
+use super::ffi::{EGL_EXTENSION_FUNCTIONS, EGL_LOSE_CONTEXT_ON_RESET_EXT};
+use super::ffi::{GL_GUILTY_CONTEXT_RESET, GL_INNOCENT_CONTEXT_RESET, GL_UNKNOWN_CONTEXT_RESET};
 use super::surface::{EGLBackedSurface, ExternalEGLSurfaces};
 use crate::context::{self, CREATE_CONTEXT_MUTEX};
 use crate::egl;
-use crate::egl::types::{EGLConfig, EGLContext, EGLDisplay, EGLSurface, EGLint};
+use crate::egl::types::{EGLConfig, EGLContext, EGLDisplay, EGLSurface, EGLenum, EGLint};
+use crate::gl::types::GLenum;
+use crate::gl_utils;
 use crate::surface::Framebuffer;
+use crate::ExternalSurfaceInfo;
+use crate::ResetNotification;
+use crate::{ColorBits, ContextPriority, FlushBehavior, GLInfo, Gl, SurfaceInfo};
 use crate::{ContextAttributeFlags, ContextAttributes, ContextID, Error, GLApi, GLVersion};
-use crate::{Gl, SurfaceInfo};
 
 use std::ffi::CString;
 use std::mem;
 use std::os::raw::{c_char, c_void};
 use std::ptr;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::thread;
+use std::time::Duration;
 
 #[allow(dead_code)]
 const DUMMY_PBUFFER_SIZE: EGLint = 16;
-const RGB_CHANNEL_BIT_DEPTH: EGLint = 8;
+
+// How many times to ask for matching configs before concluding that there are none. Some ANGLE
+// builds spuriously report zero configs for the first query after `eglInitialize()`.
+pub(crate) const CHOOSE_CONFIG_ATTEMPTS: u32 = 3;
 
 pub(crate) struct EGLBackedContext {
     pub(crate) egl_context: EGLContext,
     pub(crate) id: ContextID,
     framebuffer: Framebuffer<EGLBackedSurface, ExternalEGLSurfaces>,
     context_is_owned: bool,
+    gl: Gl,
 }
 
 /// Wrapper for a native `EGLContext`.
@@ -44,12 +62,103 @@ pub struct NativeContext {
 
 /// Information needed to create a context. Some APIs call this a "config" or a "pixel format".
 ///
-/// These are local to a device.
-#[derive(Clone)]
+/// These are local to a device. Two descriptors are equal when they name the same EGL config and
+/// request the same version, profile, and flush behavior, so contexts created from them are
+/// interchangeable.
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct ContextDescriptor {
     pub(crate) egl_config_id: EGLint,
     pub(crate) gl_version: GLVersion,
     pub(crate) compatibility_profile: bool,
+    pub(crate) flush_behavior: FlushBehavior,
+    pub(crate) priority: ContextPriority,
+    pub(crate) reset_notification: ResetNotification,
+}
+
+/// Describes one of the configs that an EGL display supports.
+///
+/// Obtained by enumerating the configs of a device. The raw `EGL_RENDERABLE_TYPE` and
+/// `EGL_SURFACE_TYPE` bitmasks are exposed so that formats `surfman`'s attribute model doesn't
+/// cover can still be selected.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConfigInfo {
+    /// The EGL config ID (`EGL_CONFIG_ID`).
+    pub config_id: EGLint,
+    /// The number of bits of red in the color buffer.
+    pub red_size: EGLint,
+    /// The number of bits of green in the color buffer.
+    pub green_size: EGLint,
+    /// The number of bits of blue in the color buffer.
+    pub blue_size: EGLint,
+    /// The number of bits of alpha in the color buffer.
+    pub alpha_size: EGLint,
+    /// The number of bits in the depth buffer.
+    pub depth_size: EGLint,
+    /// The number of bits in the stencil buffer.
+    pub stencil_size: EGLint,
+    /// The number of samples per pixel, or 0 if multisampling is not supported.
+    pub samples: EGLint,
+    /// A bitmask of the client APIs that contexts created with this config support.
+    pub renderable_type: EGLint,
+    /// A bitmask of the surface types that this config supports.
+    pub surface_type: EGLint,
+}
+
+/// Describes the format of a surface's buffers.
+///
+/// This is enough to create offscreen surfaces that match a surface `surfman` didn't create, such
+/// as the back buffer of an adopted context.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfaceFormat {
+    /// The ID of the EGL config that the surface was created with.
+    pub config_id: EGLint,
+    /// The number of bits in each channel of the color buffer.
+    pub color_bits: ColorBits,
+    /// The number of bits in the depth buffer.
+    pub depth_size: EGLint,
+    /// The number of bits in the stencil buffer.
+    pub stencil_size: EGLint,
+    /// The number of samples per pixel, or 0 if the surface isn't multisampled.
+    pub samples: EGLint,
+}
+
+/// What a context was actually created with, as opposed to what was requested.
+///
+/// The version is the one the implementation reports, which can be newer than the requested one,
+/// and the format is that of the config the context was created with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GrantedAttributes {
+    /// The API that the context implements.
+    pub api: GLApi,
+    /// The OpenGL (or OpenGL ES) version that the context reports.
+    pub version: GLVersion,
+    /// The format of the config that the context was created with.
+    pub format: SurfaceFormat,
+}
+
+/// Describes the ways in which the config chosen for a context exceeds what was requested.
+///
+/// `eglChooseConfig()` treats most sizes as minimums, so a request for no depth buffer can still
+/// be granted one if no matching config lacks it. Each field holds the granted size when it is
+/// larger than the requested one, and `None` otherwise.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConfigUpgrades {
+    /// The number of bits of alpha in the color buffer.
+    pub alpha_size: Option<EGLint>,
+    /// The number of bits in the depth buffer.
+    pub depth_size: Option<EGLint>,
+    /// The number of bits in the stencil buffer.
+    pub stencil_size: Option<EGLint>,
+    /// The number of samples per pixel. `surfman` never requests multisampling.
+    pub samples: Option<EGLint>,
+}
+
+impl ConfigUpgrades {
+    /// Returns true if the config matches the request exactly.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        *self == ConfigUpgrades::default()
+    }
 }
 
 #[must_use]
@@ -107,6 +216,7 @@ impl EGLBackedContext {
             id: *next_context_id,
             framebuffer: Framebuffer::None,
             context_is_owned: true,
+            gl: Gl::load_with(get_proc_address),
         };
         next_context_id.0 += 1;
         Ok(context)
@@ -122,6 +232,7 @@ impl EGLBackedContext {
                 read: native_context.egl_read_surface,
             }),
             context_is_owned: false,
+            gl: Gl::load_with(get_proc_address),
         };
         next_context_id.0 += 1;
         context
@@ -129,12 +240,16 @@ impl EGLBackedContext {
 
     pub(crate) unsafe fn destroy(&mut self, egl_display: EGLDisplay) {
         EGL_FUNCTIONS.with(|egl| {
-            egl.MakeCurrent(
-                egl_display,
-                egl::NO_SURFACE,
-                egl::NO_SURFACE,
-                egl::NO_CONTEXT,
-            );
+            // Only unbind the context if it's current on this thread, so that destroying one
+            // context doesn't unbind an unrelated one.
+            if egl.GetCurrentContext() == self.egl_context {
+                egl.MakeCurrent(
+                    egl_display,
+                    egl::NO_SURFACE,
+                    egl::NO_SURFACE,
+                    egl::NO_CONTEXT,
+                );
+            }
 
             if self.context_is_owned {
                 let result = egl.DestroyContext(egl_display, self.egl_context);
@@ -145,6 +260,14 @@ impl EGLBackedContext {
         });
     }
 
+    // Returns the OpenGL functions for this context, which were loaded when it was created.
+    //
+    // Function pointers aren't guaranteed to be the same across contexts, so each context keeps
+    // its own table rather than sharing a thread-local one.
+    pub(crate) fn gl(&self) -> &Gl {
+        &self.gl
+    }
+
     pub(crate) fn native_context(&self) -> NativeContext {
         let egl_surfaces = match self.framebuffer {
             Framebuffer::Surface(ref surface) => surface.egl_surfaces(),
@@ -174,8 +297,13 @@ impl EGLBackedContext {
                 self.egl_context,
             );
             if result == egl::FALSE {
-                let err = egl.GetError().to_windowing_api_error();
-                return Err(Error::MakeCurrentFailed(err));
+                let err = egl.GetError();
+                return Err(make_current_error(
+                    egl_display,
+                    egl_surfaces.draw,
+                    self.egl_context,
+                    err,
+                ));
             }
             Ok(())
         })
@@ -211,7 +339,6 @@ impl EGLBackedContext {
 
     pub(crate) unsafe fn unbind_surface(
         &mut self,
-        gl: &Gl,
         egl_display: EGLDisplay,
     ) -> Result<Option<EGLBackedSurface>, Error> {
         match self.framebuffer {
@@ -226,7 +353,7 @@ impl EGLBackedContext {
         };
 
         // If we're current, we stay current, but with no surface attached.
-        surface.unbind(gl, egl_display, self.egl_context);
+        surface.unbind(self.gl(), egl_display, self.egl_context);
 
         Ok(Some(surface))
     }
@@ -238,6 +365,16 @@ impl EGLBackedContext {
             Framebuffer::Surface(ref surface) => Ok(Some(surface.info())),
         }
     }
+
+    pub(crate) unsafe fn external_surface_info(
+        &self,
+        egl_display: EGLDisplay,
+    ) -> Option<ExternalSurfaceInfo> {
+        match self.framebuffer {
+            Framebuffer::External(ref surfaces) => Some(surfaces.info(egl_display, self.id)),
+            Framebuffer::None | Framebuffer::Surface(_) => None,
+        }
+    }
 }
 
 impl NativeContext {
@@ -263,26 +400,13 @@ impl NativeContext {
 impl ContextDescriptor {
     pub(crate) unsafe fn new(
         egl_display: EGLDisplay,
+        gl_api: GLApi,
         attributes: &ContextAttributes,
         extra_config_attributes: &[EGLint],
     ) -> Result<ContextDescriptor, Error> {
         let flags = attributes.flags;
-
-        let alpha_size = if flags.contains(ContextAttributeFlags::ALPHA) {
-            8
-        } else {
-            0
-        };
-        let depth_size = if flags.contains(ContextAttributeFlags::DEPTH) {
-            24
-        } else {
-            0
-        };
-        let stencil_size = if flags.contains(ContextAttributeFlags::STENCIL) {
-            8
-        } else {
-            0
-        };
+        let color_bits = attributes.color_bits;
+        let (alpha_size, depth_size, stencil_size) = requested_buffer_sizes(attributes);
 
         let compatibility_profile = flags.contains(ContextAttributeFlags::COMPATIBILITY_PROFILE);
 
@@ -298,14 +422,15 @@ impl ContextDescriptor {
         // Create required config attributes.
         //
         // We check these separately because `eglChooseConfig` on its own might give us 32-bit
-        // color when 24-bit color is requested, and that can break code.
+        // color when 24-bit color is requested, and that can break code. It also treats the sizes
+        // as minimums, so it would never select a 565 config if one is requested.
         let required_config_attributes = [
             egl::RED_SIZE as EGLint,
-            RGB_CHANNEL_BIT_DEPTH,
+            color_bits.red as EGLint,
             egl::GREEN_SIZE as EGLint,
-            RGB_CHANNEL_BIT_DEPTH,
+            color_bits.green as EGLint,
             egl::BLUE_SIZE as EGLint,
-            RGB_CHANNEL_BIT_DEPTH,
+            color_bits.blue as EGLint,
         ];
 
         // Create config attributes.
@@ -323,18 +448,27 @@ impl ContextDescriptor {
 
         EGL_FUNCTIONS.with(|egl| {
             // See how many applicable configs there are.
-            let mut config_count = 0;
-            let result = egl.ChooseConfig(
-                egl_display,
-                requested_config_attributes.as_ptr(),
-                ptr::null_mut(),
-                0,
-                &mut config_count,
-            );
-            if result == egl::FALSE {
-                let err = egl.GetError().to_windowing_api_error();
-                return Err(Error::PixelFormatSelectionFailed(err));
-            }
+            let config_count = count_configs_with_retry(
+                || {
+                    let mut config_count = 0;
+                    let result = egl.ChooseConfig(
+                        egl_display,
+                        requested_config_attributes.as_ptr(),
+                        ptr::null_mut(),
+                        0,
+                        &mut config_count,
+                    );
+                    if result == egl::FALSE {
+                        return Err(egl_error(egl.GetError(), Error::PixelFormatSelectionFailed));
+                    }
+                    Ok(config_count)
+                },
+                || {
+                    // Initializing an already-initialized display is a no-op, but it makes sure
+                    // the display is still usable before trying again.
+                    egl.Initialize(egl_display, ptr::null_mut(), ptr::null_mut());
+                },
+            )?;
             if config_count == 0 {
                 return Err(Error::NoPixelFormatFound);
             }
@@ -350,8 +484,7 @@ impl ContextDescriptor {
                 &mut real_config_count,
             );
             if result == egl::FALSE {
-                let err = egl.GetError().to_windowing_api_error();
-                return Err(Error::PixelFormatSelectionFailed(err));
+                return Err(egl_error(egl.GetError(), Error::PixelFormatSelectionFailed));
             }
 
             // Sanitize configs.
@@ -368,6 +501,20 @@ impl ContextDescriptor {
                 Some(egl_config) => egl_config,
             };
 
+            // Make sure the config can actually render with the requested API. Otherwise, context
+            // creation fails later on with a far less helpful error.
+            let requested_renderable_type = extra_config_attributes
+                .chunks(2)
+                .find(|pair| pair[0] == egl::RENDERABLE_TYPE as EGLint)
+                .map(|pair| pair[1]);
+            if let Some(requested_renderable_type) = requested_renderable_type {
+                let renderable_type =
+                    get_config_attr(egl_display, egl_config, egl::RENDERABLE_TYPE as EGLint);
+                if renderable_type & requested_renderable_type != requested_renderable_type {
+                    return Err(Error::ConfigNotRenderableForApi);
+                }
+            }
+
             // Get the config ID and version.
             let egl_config_id = get_config_attr(egl_display, egl_config, egl::CONFIG_ID as EGLint);
             let gl_version = attributes.version;
@@ -376,6 +523,13 @@ impl ContextDescriptor {
                 egl_config_id,
                 gl_version,
                 compatibility_profile,
+                flush_behavior: supported_flush_behavior(egl_display, attributes.flush_behavior),
+                priority: supported_priority(egl_display, attributes.priority),
+                reset_notification: supported_reset_notification(
+                    egl_display,
+                    gl_api,
+                    attributes.reset_notification,
+                ),
             })
         })
     }
@@ -397,6 +551,9 @@ impl ContextDescriptor {
                 egl_config_id,
                 gl_version,
                 compatibility_profile,
+                flush_behavior: FlushBehavior::Flush,
+                priority: ContextPriority::Medium,
+                reset_notification: ResetNotification::None,
             }
         })
     }
@@ -427,9 +584,37 @@ impl ContextDescriptor {
         })
     }
 
+    // Compares the config of this descriptor against the attributes it was requested with.
+    pub(crate) unsafe fn upgrades(
+        &self,
+        egl_display: EGLDisplay,
+        attributes: &ContextAttributes,
+    ) -> ConfigUpgrades {
+        let egl_config = egl_config_from_id(egl_display, self.egl_config_id);
+        let (alpha_size, depth_size, stencil_size) = requested_buffer_sizes(attributes);
+        let upgrade = |attr: EGLenum, requested: EGLint| {
+            let granted = get_config_attr(egl_display, egl_config, attr as EGLint);
+            if granted > requested {
+                Some(granted)
+            } else {
+                None
+            }
+        };
+
+        ConfigUpgrades {
+            alpha_size: upgrade(egl::ALPHA_SIZE, alpha_size),
+            depth_size: upgrade(egl::DEPTH_SIZE, depth_size),
+            stencil_size: upgrade(egl::STENCIL_SIZE, stencil_size),
+            samples: upgrade(egl::SAMPLES, 0),
+        }
+    }
+
     pub(crate) unsafe fn attributes(&self, egl_display: EGLDisplay) -> ContextAttributes {
         let egl_config = egl_config_from_id(egl_display, self.egl_config_id);
 
+        let red_size = get_config_attr(egl_display, egl_config, egl::RED_SIZE as EGLint);
+        let green_size = get_config_attr(egl_display, egl_config, egl::GREEN_SIZE as EGLint);
+        let blue_size = get_config_attr(egl_display, egl_config, egl::BLUE_SIZE as EGLint);
         let alpha_size = get_config_attr(egl_display, egl_config, egl::ALPHA_SIZE as EGLint);
         let depth_size = get_config_attr(egl_display, egl_config, egl::DEPTH_SIZE as EGLint);
         let stencil_size = get_config_attr(egl_display, egl_config, egl::STENCIL_SIZE as EGLint);
@@ -449,6 +634,15 @@ impl ContextDescriptor {
         ContextAttributes {
             flags: attribute_flags,
             version: self.gl_version,
+            flush_behavior: self.flush_behavior,
+            color_bits: ColorBits {
+                red: red_size as u8,
+                green: green_size as u8,
+                blue: blue_size as u8,
+                alpha: alpha_size as u8,
+            },
+            priority: self.priority,
+            reset_notification: self.reset_notification,
         }
     }
 }
@@ -472,13 +666,7 @@ pub(crate) unsafe fn create_context(
     share_with: EGLContext,
     gl_api: GLApi,
 ) -> Result<EGLContext, Error> {
-    EGL_FUNCTIONS.with(|egl| {
-        let ok = egl.BindAPI(match gl_api {
-            GLApi::GL => egl::OPENGL_API,
-            GLApi::GLES => egl::OPENGL_ES_API,
-        });
-        assert_ne!(ok, egl::FALSE);
-    });
+    bind_api(gl_api);
 
     let egl_config = egl_config_from_id(egl_display, descriptor.egl_config_id);
 
@@ -498,11 +686,64 @@ pub(crate) unsafe fn create_context(
         ]);
     }
 
+    if descriptor.flush_behavior == FlushBehavior::None {
+        egl_context_attributes.extend(&[
+            EGL_CONTEXT_RELEASE_BEHAVIOR_KHR as EGLint,
+            EGL_CONTEXT_RELEASE_BEHAVIOR_NONE_KHR as EGLint,
+        ]);
+    }
+
+    let priority_level = match descriptor.priority {
+        ContextPriority::Low => Some(EGL_CONTEXT_PRIORITY_LOW_IMG),
+        ContextPriority::Medium => None,
+        ContextPriority::High => Some(EGL_CONTEXT_PRIORITY_HIGH_IMG),
+    };
+    if let Some(priority_level) = priority_level {
+        egl_context_attributes.extend(&[
+            EGL_CONTEXT_PRIORITY_LEVEL_IMG as EGLint,
+            priority_level as EGLint,
+        ]);
+    }
+
+    // `EGL_EXT_create_context_robustness` only covers OpenGL ES; OpenGL uses the
+    // `EGL_KHR_create_context` attribute, which is also core in EGL 1.5.
+    if descriptor.reset_notification == ResetNotification::LoseContext {
+        let reset_notification_strategy = match gl_api {
+            GLApi::GL => EGL_CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY_KHR,
+            GLApi::GLES => EGL_CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY_EXT,
+        };
+        egl_context_attributes.extend(&[
+            reset_notification_strategy as EGLint,
+            EGL_LOSE_CONTEXT_ON_RESET_EXT as EGLint,
+        ]);
+    }
+
     // Include some extra zeroes to work around broken implementations.
     //
     // FIXME(pcwalton): Which implementations are those? (This is copied from Gecko.)
     egl_context_attributes.extend(&[egl::NONE as EGLint, 0, 0, 0]);
 
+    create_context_with_attributes(egl_display, egl_config, share_with, &egl_context_attributes)
+}
+
+// Selects the client API that subsequently created contexts will implement.
+pub(crate) fn bind_api(gl_api: GLApi) {
+    EGL_FUNCTIONS.with(|egl| unsafe {
+        let ok = egl.BindAPI(match gl_api {
+            GLApi::GL => egl::OPENGL_API,
+            GLApi::GLES => egl::OPENGL_ES_API,
+        });
+        assert_ne!(ok, egl::FALSE);
+    });
+}
+
+// Creates a context from an already-bound API and an `EGL_NONE`-terminated attribute list.
+pub(crate) unsafe fn create_context_with_attributes(
+    egl_display: EGLDisplay,
+    egl_config: EGLConfig,
+    share_with: EGLContext,
+    egl_context_attributes: &[EGLint],
+) -> Result<EGLContext, Error> {
     EGL_FUNCTIONS.with(|egl| {
         let egl_context = egl.CreateContext(
             egl_display,
@@ -511,15 +752,246 @@ pub(crate) unsafe fn create_context(
             egl_context_attributes.as_ptr(),
         );
         if egl_context == egl::NO_CONTEXT {
-            let err = egl.GetError();
-            let err = err.to_windowing_api_error();
-            return Err(Error::ContextCreationFailed(err));
+            return Err(egl_error(egl.GetError(), Error::ContextCreationFailed));
         }
 
         Ok(egl_context)
     })
 }
 
+// Calls `count_configs` until it reports at least one config or `CHOOSE_CONFIG_ATTEMPTS` attempts
+// have been made, calling `revalidate` between attempts. Errors are returned immediately.
+pub(crate) fn count_configs_with_retry<F, G>(
+    mut count_configs: F,
+    mut revalidate: G,
+) -> Result<EGLint, Error>
+where
+    F: FnMut() -> Result<EGLint, Error>,
+    G: FnMut(),
+{
+    let mut attempt = 1;
+    loop {
+        let config_count = count_configs()?;
+        if config_count > 0 || attempt >= CHOOSE_CONFIG_ATTEMPTS {
+            return Ok(config_count);
+        }
+        warn!("surfman: eglChooseConfig() found no configs; retrying");
+        revalidate();
+        attempt += 1;
+    }
+}
+
+// Returns the alpha, depth, and stencil sizes to request for the given attributes.
+fn requested_buffer_sizes(attributes: &ContextAttributes) -> (EGLint, EGLint, EGLint) {
+    let flags = attributes.flags;
+    let alpha_size = if flags.contains(ContextAttributeFlags::ALPHA) {
+        attributes.color_bits.alpha as EGLint
+    } else {
+        0
+    };
+    let depth_size = if flags.contains(ContextAttributeFlags::DEPTH) {
+        24
+    } else {
+        0
+    };
+    let stencil_size = if flags.contains(ContextAttributeFlags::STENCIL) {
+        8
+    } else {
+        0
+    };
+    (alpha_size, depth_size, stencil_size)
+}
+
+// Returns the requested flush behavior if the display can honor it, or `Flush` otherwise.
+pub(crate) unsafe fn supported_flush_behavior(
+    egl_display: EGLDisplay,
+    requested: FlushBehavior,
+) -> FlushBehavior {
+    match requested {
+        FlushBehavior::None
+            if display_extension_is_supported(egl_display, "EGL_KHR_context_flush_control") =>
+        {
+            FlushBehavior::None
+        }
+        _ => FlushBehavior::Flush,
+    }
+}
+
+// Returns the requested priority if the display supports `EGL_IMG_context_priority`, or `Medium`
+// otherwise.
+pub(crate) unsafe fn supported_priority(
+    egl_display: EGLDisplay,
+    requested: ContextPriority,
+) -> ContextPriority {
+    if display_extension_is_supported(egl_display, "EGL_IMG_context_priority") {
+        requested
+    } else {
+        ContextPriority::Medium
+    }
+}
+
+// Returns the requested reset notification strategy if the display supports the attribute that
+// `create_context()` passes for it with the given API, or `None` otherwise: that's
+// `EGL_EXT_create_context_robustness` for OpenGL ES, and `EGL_KHR_create_context` or EGL 1.5 for
+// OpenGL.
+pub(crate) unsafe fn supported_reset_notification(
+    egl_display: EGLDisplay,
+    gl_api: GLApi,
+    requested: ResetNotification,
+) -> ResetNotification {
+    let supported = match gl_api {
+        GLApi::GL => {
+            display_extension_is_supported(egl_display, "EGL_KHR_create_context")
+                || display_egl_version(egl_display) >= (1, 5)
+        }
+        GLApi::GLES => {
+            display_extension_is_supported(egl_display, "EGL_EXT_create_context_robustness")
+        }
+    };
+    if supported {
+        requested
+    } else {
+        ResetNotification::None
+    }
+}
+
+// Gathers information about the OpenGL implementation behind the given context by temporarily
+// making it current, returning a `MakeCurrentFailed` error if that isn't possible.
+//
+// Once the context is current, this checks that the GL functions surfman calls internally were
+// loaded before calling any of them, returning a `RequiredGLFunctionMissing` error if not.
+pub(crate) unsafe fn gl_info_for_egl_context(
+    gl: &Gl,
+    egl_display: EGLDisplay,
+    egl_context: EGLContext,
+) -> Result<GLInfo, Error> {
+    EGL_FUNCTIONS.with(|egl| {
+        let guard = CurrentContextGuard::new();
+        let result = egl.MakeCurrent(egl_display, egl::NO_SURFACE, egl::NO_SURFACE, egl_context);
+        if result == egl::FALSE {
+            return Err(egl_error(egl.GetError(), Error::MakeCurrentFailed));
+        }
+        let gl_info = gl_utils::check_required_gl_functions(gl).map(|()| GLInfo::populate(gl));
+
+        // The guard can't restore "no context" because it has no display to do it with.
+        if guard.egl_display == egl::NO_DISPLAY {
+            egl.MakeCurrent(
+                egl_display,
+                egl::NO_SURFACE,
+                egl::NO_SURFACE,
+                egl::NO_CONTEXT,
+            );
+        }
+        gl_info
+    })
+}
+
+// Runs the given function on a helper thread, giving up on it after the given timeout.
+//
+// This is for EGL calls that hang on some drivers. A call that times out keeps running on its
+// thread, which is leaked, so the function must not borrow anything.
+pub(crate) fn run_with_timeout<T, F>(timeout: Duration, function: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // The receiver is gone if we timed out, in which case there's no one to tell.
+        let _ = sender.send(function());
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => Ok(result),
+        Err(RecvTimeoutError::Timeout) => Err(Error::MakeCurrentTimedOut),
+        Err(RecvTimeoutError::Disconnected) => Err(Error::Failed),
+    }
+}
+
+// Returns true if the given EGL context has been lost, e.g. due to a GPU reset.
+//
+// If the context is current, this asks GL for its reset status, which is the most reliable
+// answer for robust contexts. Otherwise, it falls back to a lightweight EGL query, which fails
+// with `EGL_CONTEXT_LOST` after a power management event. Either way, it doesn't change which
+// context is current.
+pub(crate) unsafe fn egl_context_is_lost(egl_display: EGLDisplay, egl_context: EGLContext) -> bool {
+    EGL_FUNCTIONS.with(|egl| {
+        if egl.GetCurrentContext() == egl_context {
+            if let Some(get_graphics_reset_status) =
+                EGL_EXTENSION_FUNCTIONS.GetGraphicsResetStatusEXT
+            {
+                if reset_status_means_context_lost(get_graphics_reset_status()) {
+                    return true;
+                }
+            }
+        }
+
+        let mut config_id = 0;
+        let result = egl.QueryContext(
+            egl_display,
+            egl_context,
+            egl::CONFIG_ID as EGLint,
+            &mut config_id,
+        );
+        result == egl::FALSE && egl_error_means_context_lost(egl.GetError())
+    })
+}
+
+// Interprets the result of `glGetGraphicsResetStatus()`.
+pub(crate) fn reset_status_means_context_lost(status: GLenum) -> bool {
+    match status {
+        GL_GUILTY_CONTEXT_RESET | GL_INNOCENT_CONTEXT_RESET | GL_UNKNOWN_CONTEXT_RESET => true,
+        _ => false,
+    }
+}
+
+// Interprets the error from a failed EGL call.
+pub(crate) fn egl_error_means_context_lost(error: EGLint) -> bool {
+    error as EGLenum == egl::CONTEXT_LOST
+}
+
+// Builds the error for a failed `eglMakeCurrent()` with the given draw surface.
+//
+// `EGL_BAD_MATCH` usually means that the surface was created with a config that is incompatible
+// with the context's, so in that case the two config IDs are reported.
+pub(crate) unsafe fn make_current_error(
+    egl_display: EGLDisplay,
+    egl_surface: EGLSurface,
+    egl_context: EGLContext,
+    err: EGLint,
+) -> Error {
+    if err as EGLenum == egl::BAD_MATCH && egl_surface != egl::NO_SURFACE {
+        let configs = EGL_FUNCTIONS.with(|egl| {
+            let (mut surface_config, mut context_config) = (0, 0);
+            let surface_ok = egl.QuerySurface(
+                egl_display,
+                egl_surface,
+                egl::CONFIG_ID as EGLint,
+                &mut surface_config,
+            );
+            let context_ok = egl.QueryContext(
+                egl_display,
+                egl_context,
+                egl::CONFIG_ID as EGLint,
+                &mut context_config,
+            );
+            if surface_ok == egl::FALSE || context_ok == egl::FALSE {
+                None
+            } else {
+                Some((surface_config, context_config))
+            }
+        });
+        if let Some((surface_config, context_config)) = configs {
+            if surface_config != context_config {
+                return Error::SurfaceConfigMismatch {
+                    surface_config,
+                    context_config,
+                };
+            }
+        }
+    }
+    egl_error(err, Error::MakeCurrentFailed)
+}
+
 pub(crate) unsafe fn make_no_context_current(egl_display: EGLDisplay) -> Result<(), Error> {
     EGL_FUNCTIONS.with(|egl| {
         let result = egl.MakeCurrent(
@@ -529,8 +1001,7 @@ pub(crate) unsafe fn make_no_context_current(egl_display: EGLDisplay) -> Result<
             egl::NO_CONTEXT,
         );
         if result == egl::FALSE {
-            let err = egl.GetError().to_windowing_api_error();
-            return Err(Error::MakeCurrentFailed(err));
+            return Err(egl_error(egl.GetError(), Error::MakeCurrentFailed));
         }
         Ok(())
     })
@@ -562,6 +1033,39 @@ pub(crate) unsafe fn get_context_attr(
     })
 }
 
+// Returns the client API that the given context was created for, as `EGL_CONTEXT_CLIENT_TYPE`
+// reports it.
+//
+// This is usually the device's API, but a context adopted with
+// `create_context_from_native_context()` may have been created for the other one. The context
+// attributes have no field for the API, so the answer has to come from EGL.
+pub(crate) unsafe fn context_gl_api(egl_display: EGLDisplay, egl_context: EGLContext) -> GLApi {
+    let client_type =
+        get_context_attr(egl_display, egl_context, egl::CONTEXT_CLIENT_TYPE as EGLint);
+    match client_type as EGLenum {
+        egl::OPENGL_API => GLApi::GL,
+        _ => GLApi::GLES,
+    }
+}
+
+// Returns the `EGL_NATIVE_VISUAL_ID` of the config that the given context was created with.
+//
+// This names the matching visual or pixel format of the native window system, such as an X11
+// visual ID. It is 0 if the window system has no such notion, or if the context has no config, as
+// with `EGL_KHR_no_config_context`.
+pub(crate) unsafe fn context_native_visual_id(
+    egl_display: EGLDisplay,
+    egl_context: EGLContext,
+) -> EGLint {
+    let egl_config_id = get_context_attr(egl_display, egl_context, egl::CONFIG_ID as EGLint);
+    match try_egl_config_from_id(egl_display, egl_config_id) {
+        Some(egl_config) => {
+            get_config_attr(egl_display, egl_config, egl::NATIVE_VISUAL_ID as EGLint)
+        }
+        None => 0,
+    }
+}
+
 pub(crate) unsafe fn egl_config_from_id(
     egl_display: EGLDisplay,
     egl_config_id: EGLint,
@@ -590,6 +1094,154 @@ pub(crate) unsafe fn egl_config_from_id(
     })
 }
 
+// Returns the config with the given ID, or `None` if the display has no such config.
+pub(crate) unsafe fn try_egl_config_from_id(
+    egl_display: EGLDisplay,
+    egl_config_id: EGLint,
+) -> Option<EGLConfig> {
+    let config_attributes = [
+        egl::CONFIG_ID as EGLint,
+        egl_config_id,
+        egl::NONE as EGLint,
+        0,
+        0,
+        0,
+    ];
+
+    EGL_FUNCTIONS.with(|egl| {
+        let (mut config, mut config_count) = (ptr::null(), 0);
+        let result = egl.ChooseConfig(
+            egl_display,
+            config_attributes.as_ptr(),
+            &mut config,
+            1,
+            &mut config_count,
+        );
+        if result == egl::FALSE || config_count == 0 {
+            None
+        } else {
+            Some(config)
+        }
+    })
+}
+
+pub(crate) unsafe fn enumerate_configs(egl_display: EGLDisplay) -> Result<Vec<ConfigInfo>, Error> {
+    Ok(all_configs(egl_display)?
+        .into_iter()
+        .map(|egl_config| {
+            let get = |attr: EGLenum| get_config_attr(egl_display, egl_config, attr as EGLint);
+            ConfigInfo {
+                config_id: get(egl::CONFIG_ID),
+                red_size: get(egl::RED_SIZE),
+                green_size: get(egl::GREEN_SIZE),
+                blue_size: get(egl::BLUE_SIZE),
+                alpha_size: get(egl::ALPHA_SIZE),
+                depth_size: get(egl::DEPTH_SIZE),
+                stencil_size: get(egl::STENCIL_SIZE),
+                samples: get(egl::SAMPLES),
+                renderable_type: get(egl::RENDERABLE_TYPE),
+                surface_type: get(egl::SURFACE_TYPE),
+            }
+        })
+        .collect())
+}
+
+// Returns the formats of the configs that can back pbuffers bound to RGBA textures, which is what
+// generic surfaces need on backends that implement them with pbuffers.
+#[allow(dead_code)]
+pub(crate) unsafe fn texture_pbuffer_surface_formats(
+    egl_display: EGLDisplay,
+) -> Result<Vec<SurfaceFormat>, Error> {
+    Ok(all_configs(egl_display)?
+        .into_iter()
+        .filter_map(|egl_config| {
+            let get = |attr: EGLenum| get_config_attr(egl_display, egl_config, attr as EGLint);
+            if get(egl::SURFACE_TYPE) & egl::PBUFFER_BIT as EGLint == 0
+                || get(egl::BIND_TO_TEXTURE_RGBA) != egl::TRUE as EGLint
+            {
+                return None;
+            }
+            Some(config_surface_format(egl_display, egl_config))
+        })
+        .collect())
+}
+
+// Returns the format of the buffers of surfaces created with the given config.
+pub(crate) unsafe fn config_surface_format(
+    egl_display: EGLDisplay,
+    egl_config: EGLConfig,
+) -> SurfaceFormat {
+    let get = |attr: EGLenum| get_config_attr(egl_display, egl_config, attr as EGLint);
+    SurfaceFormat {
+        config_id: get(egl::CONFIG_ID),
+        color_bits: ColorBits {
+            red: get(egl::RED_SIZE) as u8,
+            green: get(egl::GREEN_SIZE) as u8,
+            blue: get(egl::BLUE_SIZE) as u8,
+            alpha: get(egl::ALPHA_SIZE) as u8,
+        },
+        depth_size: get(egl::DEPTH_SIZE),
+        stencil_size: get(egl::STENCIL_SIZE),
+        samples: get(egl::SAMPLES),
+    }
+}
+
+unsafe fn all_configs(egl_display: EGLDisplay) -> Result<Vec<EGLConfig>, Error> {
+    EGL_FUNCTIONS.with(|egl| {
+        // See how many configs there are.
+        let mut config_count = 0;
+        let result = egl.GetConfigs(egl_display, ptr::null_mut(), 0, &mut config_count);
+        if result == egl::FALSE {
+            return Err(egl_error(egl.GetError(), Error::PixelFormatSelectionFailed));
+        }
+
+        // Fetch all of them.
+        let mut configs = vec![ptr::null(); config_count as usize];
+        let mut real_config_count = config_count;
+        let result = egl.GetConfigs(
+            egl_display,
+            configs.as_mut_ptr(),
+            config_count,
+            &mut real_config_count,
+        );
+        if result == egl::FALSE {
+            return Err(egl_error(egl.GetError(), Error::PixelFormatSelectionFailed));
+        }
+        configs.truncate(real_config_count as usize);
+        Ok(configs)
+    })
+}
+
+// Returns the format of the given EGL surface.
+//
+// This works for surfaces `surfman` didn't create too, since it only relies on the config the
+// surface reports. If the surface is `EGL_NO_SURFACE`, a `NoSurfaceAttached` error is returned.
+#[allow(dead_code)]
+pub(crate) unsafe fn egl_surface_format(
+    egl_display: EGLDisplay,
+    egl_surface: EGLSurface,
+) -> Result<SurfaceFormat, Error> {
+    EGL_FUNCTIONS.with(|egl| {
+        if egl_surface == egl::NO_SURFACE {
+            return Err(Error::NoSurfaceAttached);
+        }
+
+        let mut config_id = 0;
+        let result = egl.QuerySurface(
+            egl_display,
+            egl_surface,
+            egl::CONFIG_ID as EGLint,
+            &mut config_id,
+        );
+        if result == egl::FALSE {
+            return Err(egl_error(egl.GetError(), Error::PixelFormatSelectionFailed));
+        }
+
+        let egl_config = egl_config_from_id(egl_display, config_id);
+        Ok(config_surface_format(egl_display, egl_config))
+    })
+}
+
 pub(crate) fn get_proc_address(symbol_name: &str) -> *const c_void {
     EGL_FUNCTIONS.with(|egl| unsafe {
         let symbol_name: CString = CString::new(symbol_name).unwrap();
@@ -597,6 +1249,28 @@ pub(crate) fn get_proc_address(symbol_name: &str) -> *const c_void {
     })
 }
 
+// Creates the surface that the given context renders to when no surface is bound.
+//
+// This is a dummy pbuffer if the context's config supports pbuffers. Window-only configs can't
+// have one, so for those `EGL_NO_SURFACE` is returned and the context is made current
+// surfacelessly, which requires `EGL_KHR_surfaceless_context`.
+#[allow(dead_code)]
+pub(crate) unsafe fn create_default_surface(
+    egl_display: EGLDisplay,
+    egl_context: EGLContext,
+) -> Result<EGLSurface, Error> {
+    let egl_config_id = get_context_attr(egl_display, egl_context, egl::CONFIG_ID as EGLint);
+    let egl_config = egl_config_from_id(egl_display, egl_config_id);
+    let surface_type = get_config_attr(egl_display, egl_config, egl::SURFACE_TYPE as EGLint);
+    if surface_type & egl::PBUFFER_BIT as EGLint != 0 {
+        return Ok(create_dummy_pbuffer(egl_display, egl_context));
+    }
+    if supports_surfaceless_context(egl_display) {
+        return Ok(egl::NO_SURFACE);
+    }
+    Err(Error::RequiredExtensionUnavailable)
+}
+
 // Creates and returns a dummy pbuffer surface for the given context. This is used as the default
 // framebuffer on some backends.
 #[allow(dead_code)]
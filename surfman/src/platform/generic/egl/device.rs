@@ -2,9 +2,11 @@
 //
 //! Functionality common to backends using EGL displays.
 
-use crate::egl::Egl;
+use crate::egl::types::{EGLDisplay, EGLint};
+use crate::egl::{self, Egl};
 
-use std::ffi::CString;
+use std::collections::HashSet;
+use std::ffi::{CStr, CString};
 use std::mem;
 use std::os::raw::{c_char, c_void};
 
@@ -73,3 +75,54 @@ pub(crate) unsafe fn lookup_egl_extension(name: &'static [u8]) -> *mut c_void {
     EGL_FUNCTIONS
         .with(|egl| mem::transmute(egl.GetProcAddress(&name[0] as *const u8 as *const c_char)))
 }
+
+// Returns the set of EGL extensions available on the given display, or the client extensions
+// (those that don't depend on a display) if `egl_display` is `EGL_NO_DISPLAY`.
+pub(crate) unsafe fn egl_extensions(egl_display: EGLDisplay) -> HashSet<String> {
+    EGL_FUNCTIONS.with(|egl| {
+        let extensions = egl.QueryString(egl_display, egl::EXTENSIONS as EGLint);
+        if extensions.is_null() {
+            return HashSet::new();
+        }
+        CStr::from_ptr(extensions)
+            .to_string_lossy()
+            .split_whitespace()
+            .map(|extension| extension.to_owned())
+            .collect()
+    })
+}
+
+// Returns true if the given EGL client extension (one that doesn't depend on a display) is
+// available.
+pub(crate) unsafe fn client_extension_is_supported(name: &str) -> bool {
+    egl_extensions(egl::NO_DISPLAY).contains(name)
+}
+
+// Returns true if the given EGL extension is available on the given display.
+pub(crate) unsafe fn display_extension_is_supported(egl_display: EGLDisplay, name: &str) -> bool {
+    egl_extensions(egl_display).contains(name)
+}
+
+// Returns the EGL version that the given display implements, or 0.0 if it can't be queried.
+pub(crate) unsafe fn display_egl_version(egl_display: EGLDisplay) -> (u32, u32) {
+    EGL_FUNCTIONS.with(|egl| {
+        let version = egl.QueryString(egl_display, egl::VERSION as EGLint);
+        if version.is_null() {
+            return (0, 0);
+        }
+        // The string starts with `<major>.<minor>`, followed by vendor-specific information.
+        let version = CStr::from_ptr(version).to_string_lossy();
+        let mut numbers = version
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .split('.')
+            .map(|number| number.parse().unwrap_or(0));
+        (numbers.next().unwrap_or(0), numbers.next().unwrap_or(0))
+    })
+}
+
+// Returns true if contexts on the given display can be made current without any surface.
+pub(crate) unsafe fn supports_surfaceless_context(egl_display: EGLDisplay) -> bool {
+    display_extension_is_supported(egl_display, "EGL_KHR_surfaceless_context")
+}
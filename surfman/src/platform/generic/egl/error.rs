@@ -4,7 +4,7 @@
 
 use crate::egl;
 use crate::egl::types::{EGLenum, EGLint};
-use crate::WindowingApiError;
+use crate::{Error, WindowingApiError};
 
 pub(crate) trait ToWindowingApiError {
     fn to_windowing_api_error(self) -> WindowingApiError;
@@ -31,3 +31,25 @@ impl ToWindowingApiError for EGLint {
         }
     }
 }
+
+// Translates the EGL error left behind by a failed call, using `wrap` to report it as a failure
+// of the operation.
+//
+// `EGL_NOT_INITIALIZED` means that the display was terminated, possibly by another library, so
+// it's reported as `DisplayNotInitialized` instead: the operation itself isn't at fault.
+pub(crate) fn egl_error(egl_error: EGLint, wrap: fn(WindowingApiError) -> Error) -> Error {
+    match egl_error as EGLenum {
+        egl::NOT_INITIALIZED => Error::DisplayNotInitialized,
+        _ => wrap(egl_error.to_windowing_api_error()),
+    }
+}
+
+// Translates the EGL error left behind by a failed surface creation call.
+//
+// Allocation failures are reported separately so that callers can free other surfaces and retry.
+pub(crate) fn surface_creation_error(egl_error: EGLint) -> Error {
+    match egl_error as EGLenum {
+        egl::BAD_ALLOC => Error::OutOfSurfaceMemory,
+        _ => self::egl_error(egl_error, Error::SurfaceCreationFailed),
+    }
+}
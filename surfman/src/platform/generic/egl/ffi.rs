@@ -6,6 +6,7 @@
 
 use crate::egl::types::{EGLAttrib, EGLBoolean, EGLContext, EGLDeviceEXT, EGLDisplay, EGLSurface};
 use crate::egl::types::{EGLenum, EGLint};
+use crate::gl::types::GLenum;
 
 use std::os::raw::c_void;
 
@@ -15,12 +16,21 @@ pub type EGLClientBuffer = *mut EGLClientBufferOpaque;
 pub enum EGLImageKHROpaque {}
 pub type EGLImageKHR = *mut EGLImageKHROpaque;
 
+pub const EGL_CONTEXT_RELEASE_BEHAVIOR_NONE_KHR: EGLenum = 0;
+pub const EGL_CONTEXT_RELEASE_BEHAVIOR_KHR: EGLenum = 0x2097;
 pub const EGL_GL_TEXTURE_2D_KHR: EGLenum = 0x30b1;
 pub const EGL_IMAGE_PRESERVED_KHR: EGLenum = 0x30d2;
 pub const EGL_CONTEXT_MINOR_VERSION_KHR: EGLenum = 0x30fb;
 pub const EGL_CONTEXT_OPENGL_PROFILE_MASK: EGLenum = 0x30fd;
+pub const EGL_CONTEXT_PRIORITY_LEVEL_IMG: EGLenum = 0x3100;
+pub const EGL_CONTEXT_PRIORITY_HIGH_IMG: EGLenum = 0x3101;
+pub const EGL_CONTEXT_PRIORITY_MEDIUM_IMG: EGLenum = 0x3102;
+pub const EGL_CONTEXT_PRIORITY_LOW_IMG: EGLenum = 0x3103;
+pub const EGL_CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY_EXT: EGLenum = 0x3138;
 pub const EGL_PLATFORM_DEVICE_EXT: EGLenum = 0x313f;
 pub const EGL_NATIVE_BUFFER_ANDROID: EGLenum = 0x3140;
+pub const EGL_CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY_KHR: EGLenum = 0x31bd;
+pub const EGL_LOSE_CONTEXT_ON_RESET_EXT: EGLenum = 0x31bf;
 pub const EGL_PLATFORM_X11_KHR: EGLenum = 0x31d5;
 pub const EGL_PLATFORM_WAYLAND_KHR: EGLenum = 0x31d8;
 pub const EGL_PLATFORM_SURFACELESS_MESA: EGLenum = 0x31dd;
@@ -30,10 +40,17 @@ pub const EGL_DEVICE_EXT: EGLenum = 0x322c;
 pub const EGL_D3D11_DEVICE_ANGLE: EGLenum = 0x33a1;
 pub const EGL_DXGI_KEYED_MUTEX_ANGLE: EGLenum = 0x33a2;
 pub const EGL_D3D_TEXTURE_ANGLE: EGLenum = 0x33a3;
+pub const EGL_DIRECT_COMPOSITION_ANGLE: EGLenum = 0x33a5;
+pub const EGL_FEATURE_OVERRIDES_ENABLED_ANGLE: EGLenum = 0x3466;
+pub const EGL_FEATURE_OVERRIDES_DISABLED_ANGLE: EGLenum = 0x3467;
 
 pub const EGL_NO_DEVICE_EXT: EGLDeviceEXT = 0 as EGLDeviceEXT;
 pub const EGL_NO_IMAGE_KHR: EGLImageKHR = 0 as EGLImageKHR;
 
+pub const GL_GUILTY_CONTEXT_RESET: GLenum = 0x8253;
+pub const GL_INNOCENT_CONTEXT_RESET: GLenum = 0x8254;
+pub const GL_UNKNOWN_CONTEXT_RESET: GLenum = 0x8255;
+
 pub const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: EGLint = 1;
 pub const EGL_CONTEXT_OPENGL_COMPATIBILITY_PROFILE_BIT: EGLint = 2;
 
@@ -58,6 +75,7 @@ pub(crate) struct EGLExtensionFunctions {
             attrib_list: *const EGLAttrib,
         ) -> EGLDeviceEXT,
     >,
+    pub(crate) GetGraphicsResetStatusEXT: Option<extern "C" fn() -> GLenum>,
     pub(crate) GetNativeClientBufferANDROID:
         Option<extern "C" fn(buffer: *const c_void) -> EGLClientBuffer>,
     pub(crate) QueryDeviceAttribEXT: Option<
@@ -87,6 +105,7 @@ lazy_static! {
                 ImageTargetTexture2DOES: cast(get(b"glEGLImageTargetTexture2DOES\0")),
 
                 CreateDeviceANGLE: cast(get(b"eglCreateDeviceANGLE\0")),
+                GetGraphicsResetStatusEXT: cast(get(b"glGetGraphicsResetStatusEXT\0")),
                 GetNativeClientBufferANDROID: cast(get(b"eglGetNativeClientBufferANDROID\0")),
                 QueryDeviceAttribEXT: cast(get(b"eglQueryDeviceAttribEXT\0")),
                 QueryDisplayAttribEXT: cast(get(b"eglQueryDisplayAttribEXT\0")),
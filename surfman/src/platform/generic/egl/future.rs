@@ -0,0 +1,137 @@
+// surfman/surfman/src/platform/generic/egl/future.rs
+//
+//! Futures that resolve when EGL fences are signaled.
+
+use super::device::EGL_FUNCTIONS;
+use super::error::egl_error;
+use crate::egl;
+use crate::egl::types::{EGLAttrib, EGLDisplay, EGLSync, EGLTime, EGLenum};
+use crate::Error;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+/// A future that resolves once the GPU has finished all commands issued before it was created.
+///
+/// It is backed by an EGL fence, which belongs to the display rather than to any context, so it
+/// can be polled from any thread. Polling never blocks: if the fence hasn't been signaled yet, a
+/// helper thread waits for it and wakes the task once it has.
+pub(crate) struct EGLFenceFuture {
+    shared: Arc<SharedFence>,
+}
+
+// The fence and the result of waiting on it, shared between the future and its helper thread.
+// The fence is destroyed when both are done with it.
+struct SharedFence {
+    fence: Fence,
+    state: Mutex<WaitState>,
+}
+
+struct Fence {
+    egl_display: EGLDisplay,
+    egl_sync: EGLSync,
+}
+
+#[derive(Default)]
+struct WaitState {
+    waiting: bool,
+    result: Option<Result<(), Error>>,
+    waker: Option<Waker>,
+}
+
+// EGL sync objects belong to the display and may be waited on from any thread.
+unsafe impl Send for Fence {}
+unsafe impl Sync for Fence {}
+
+impl EGLFenceFuture {
+    // Inserts a fence into the command stream of the current context. The caller must flush the
+    // context afterward so that the fence is eventually signaled.
+    //
+    // The fence is never waited on with `EGL_SYNC_FLUSH_COMMANDS_BIT`, since by then the future
+    // may have moved to a thread where that context isn't current.
+    pub(crate) unsafe fn new(egl_display: EGLDisplay) -> Result<EGLFenceFuture, Error> {
+        EGL_FUNCTIONS.with(|egl| {
+            let attributes = [egl::NONE as EGLAttrib];
+            let egl_sync = egl.CreateSync(egl_display, egl::SYNC_FENCE, attributes.as_ptr());
+            if egl_sync == egl::NO_SYNC {
+                return Err(egl_error(egl.GetError(), Error::PresentFailed));
+            }
+            Ok(EGLFenceFuture {
+                shared: Arc::new(SharedFence {
+                    fence: Fence {
+                        egl_display,
+                        egl_sync,
+                    },
+                    state: Mutex::new(WaitState::default()),
+                }),
+            })
+        })
+    }
+}
+
+impl Fence {
+    // Waits up to the given number of nanoseconds for the fence to be signaled. Returns `None` if
+    // it wasn't signaled in time.
+    fn wait(&self, timeout: EGLTime) -> Option<Result<(), Error>> {
+        EGL_FUNCTIONS.with(|egl| unsafe {
+            let result = egl.ClientWaitSync(self.egl_display, self.egl_sync, 0, timeout);
+            match result as EGLenum {
+                egl::CONDITION_SATISFIED => Some(Ok(())),
+                egl::TIMEOUT_EXPIRED => None,
+                _ => Some(Err(egl_error(egl.GetError(), Error::PresentFailed))),
+            }
+        })
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        EGL_FUNCTIONS.with(|egl| unsafe {
+            egl.DestroySync(self.egl_display, self.egl_sync);
+        })
+    }
+}
+
+impl Future for EGLFenceFuture {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, task_context: &mut Context) -> Poll<Result<(), Error>> {
+        let mut state = self.shared.state.lock().unwrap();
+        if let Some(result) = state.result.take() {
+            return Poll::Ready(result);
+        }
+        state.waker = Some(task_context.waker().clone());
+        if state.waiting {
+            return Poll::Pending;
+        }
+
+        // The fence has often been signaled by the time it's first polled, so check before
+        // starting a thread.
+        if let Some(result) = self.shared.fence.wait(0) {
+            return Poll::Ready(result);
+        }
+
+        // There's no way to be notified when a fence is signaled, so block on it on a helper
+        // thread instead of asking to be polled again straight away.
+        state.waiting = true;
+        let shared = self.shared.clone();
+        thread::spawn(move || {
+            let result = shared
+                .fence
+                .wait(egl::FOREVER)
+                .unwrap_or(Err(Error::Failed));
+            let waker = {
+                let mut state = shared.state.lock().unwrap();
+                state.result = Some(result);
+                state.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        });
+        Poll::Pending
+    }
+}
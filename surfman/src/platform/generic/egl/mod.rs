@@ -8,4 +8,6 @@ pub(crate) mod context;
 pub(crate) mod device;
 pub(crate) mod error;
 pub(crate) mod ffi;
+#[cfg(feature = "sm-async")]
+pub(crate) mod future;
 pub(crate) mod surface;
@@ -9,7 +9,7 @@ use crate::egl::types::{EGLAttrib, EGLConfig, EGLContext, EGLDisplay, EGLSurface
 use crate::gl;
 use crate::gl::types::{GLint, GLuint};
 use crate::gl_utils;
-use crate::platform::generic::egl::error::ToWindowingApiError;
+use crate::platform::generic::egl::error::egl_error;
 use crate::platform::generic::egl::ffi::EGLClientBuffer;
 use crate::platform::generic::egl::ffi::EGLImageKHR;
 use crate::platform::generic::egl::ffi::EGL_EXTENSION_FUNCTIONS;
@@ -18,7 +18,7 @@ use crate::platform::generic::egl::ffi::EGL_IMAGE_PRESERVED_KHR;
 use crate::platform::generic::egl::ffi::EGL_NO_IMAGE_KHR;
 use crate::renderbuffers::Renderbuffers;
 use crate::Gl;
-use crate::{ContextAttributes, ContextID, Error, SurfaceID, SurfaceInfo};
+use crate::{ContextAttributes, ContextID, Error, ExternalSurfaceInfo, SurfaceID, SurfaceInfo};
 
 use euclid::default::Size2D;
 use std::fmt::{self, Debug, Formatter};
@@ -277,9 +277,7 @@ impl EGLBackedSurface {
                         if ok != egl::FALSE {
                             Ok(())
                         } else {
-                            Err(Error::PresentFailed(
-                                egl.GetError().to_windowing_api_error(),
-                            ))
+                            Err(egl_error(egl.GetError(), Error::PresentFailed))
                         }
                     })
                 }
@@ -359,6 +357,42 @@ impl EGLSurfaceTexture {
     }
 }
 
+impl ExternalEGLSurfaces {
+    // Returns what EGL reports about the draw surface of a context adopted with
+    // `create_context_from_native_context()`.
+    //
+    // `context_surface_info()` returns an `ExternalRenderTarget` error for such contexts, since
+    // surfman didn't create their surfaces, so the size is queried from EGL here instead.
+    // Surfaceless contexts have no size.
+    pub(crate) unsafe fn info(
+        &self,
+        egl_display: EGLDisplay,
+        context_id: ContextID,
+    ) -> ExternalSurfaceInfo {
+        let size = if self.draw == egl::NO_SURFACE {
+            None
+        } else {
+            EGL_FUNCTIONS.with(|egl| {
+                let (mut width, mut height) = (0, 0);
+                if egl.QuerySurface(egl_display, self.draw, egl::WIDTH as EGLint, &mut width)
+                    == egl::FALSE
+                    || egl.QuerySurface(egl_display, self.draw, egl::HEIGHT as EGLint, &mut height)
+                        == egl::FALSE
+                {
+                    egl.GetError();
+                    return None;
+                }
+                Some(Size2D::new(width, height))
+            })
+        };
+        ExternalSurfaceInfo {
+            size,
+            context_id,
+            framebuffer_object: 0,
+        }
+    }
+}
+
 impl Default for ExternalEGLSurfaces {
     #[inline]
     fn default() -> ExternalEGLSurfaces {
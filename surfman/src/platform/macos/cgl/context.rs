@@ -135,6 +135,7 @@ impl Device {
         &self,
         attributes: &ContextAttributes,
     ) -> Result<ContextDescriptor, Error> {
+        device_span!("create_context_descriptor", flags = ?attributes.flags);
         if attributes
             .flags
             .contains(ContextAttributeFlags::COMPATIBILITY_PROFILE)
@@ -217,6 +218,7 @@ impl Device {
         descriptor: &ContextDescriptor,
         share_with: Option<&Context>,
     ) -> Result<Context, Error> {
+        device_span!("create_context", shared = share_with.is_some());
         // Take a lock so that we're only creating one context at a time. `CGLChoosePixelFormat`
         // will fail, returning `kCGLBadConnection`, if multiple threads try to open a display
         // connection simultaneously.
@@ -254,6 +256,7 @@ impl Device {
         &self,
         native_context: NativeContext,
     ) -> Result<Context, Error> {
+        device_span!("create_context_from_native_context");
         let mut next_context_id = CREATE_CONTEXT_MUTEX.lock().unwrap();
         let context = Context {
             cgl_context: native_context.0,
@@ -269,6 +272,7 @@ impl Device {
     ///
     /// The context must have been created on this device.
     pub fn destroy_context(&self, context: &mut Context) -> Result<(), Error> {
+        device_span!("destroy_context", context = self.context_id(context).0);
         if context.cgl_context.is_null() {
             return Ok(());
         }
@@ -302,6 +306,7 @@ impl Device {
     ///
     /// After calling this function, it is valid to use OpenGL rendering commands.
     pub fn make_context_current(&self, context: &Context) -> Result<(), Error> {
+        device_span!("make_context_current", context = self.context_id(context).0);
         unsafe {
             let err = CGLSetCurrentContext(context.cgl_context);
             if err != kCGLNoError {
@@ -316,6 +321,7 @@ impl Device {
     /// After calling this function, OpenGL rendering commands will fail until a new context is
     /// made current.
     pub fn make_no_context_current(&self) -> Result<(), Error> {
+        device_span!("make_no_context_current");
         unsafe {
             let err = CGLSetCurrentContext(ptr::null_mut());
             if err != kCGLNoError {
@@ -349,6 +355,11 @@ impl Device {
         context: &mut Context,
         new_surface: Surface,
     ) -> Result<(), (Error, Surface)> {
+        device_span!(
+            "bind_surface_to_context",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(&new_surface).size,
+        );
         match context.framebuffer {
             Framebuffer::External(_) => return Err((Error::ExternalRenderTarget, new_surface)),
             Framebuffer::Surface(_) => return Err((Error::SurfaceAlreadyBound, new_surface)),
@@ -371,6 +382,10 @@ impl Device {
         &self,
         context: &mut Context,
     ) -> Result<Option<Surface>, Error> {
+        device_span!(
+            "unbind_surface_from_context",
+            context = self.context_id(context).0
+        );
         match context.framebuffer {
             Framebuffer::External(_) => return Err(Error::ExternalRenderTarget),
             Framebuffer::None | Framebuffer::Surface(_) => {}
@@ -426,6 +441,7 @@ impl Device {
             return ContextAttributes {
                 flags: attribute_flags,
                 version,
+                ..Default::default()
             };
         }
 
@@ -85,6 +85,14 @@ impl Device {
         access: SurfaceAccess,
         surface_type: SurfaceType<NativeWidget>,
     ) -> Result<Surface, Error> {
+        device_span!(
+            "create_surface",
+            context = self.context_id(context).0,
+            size = ?match surface_type {
+                SurfaceType::Generic { size } => Some(size),
+                SurfaceType::Widget { .. } => None,
+            },
+        );
         let mut system_surface = self.0.create_surface(access, surface_type)?;
         self.0.set_surface_flipped(&mut system_surface, true);
 
@@ -157,6 +165,11 @@ impl Device {
         context: &mut Context,
         surface: Surface,
     ) -> Result<SurfaceTexture, (Error, Surface)> {
+        device_span!(
+            "create_surface_texture",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(&surface).size,
+        );
         if surface.system_surface.view_info.is_some() {
             return Err((Error::WidgetAttached, surface));
         }
@@ -224,6 +237,11 @@ impl Device {
         context: &mut Context,
         surface: &mut Surface,
     ) -> Result<(), Error> {
+        device_span!(
+            "destroy_surface",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(surface).size,
+        );
         GL_FUNCTIONS.with(|gl| {
             if context.id != surface.context_id {
                 return Err(Error::IncompatibleSurface);
@@ -251,9 +269,13 @@ impl Device {
     /// occur.
     pub fn destroy_surface_texture(
         &self,
-        _: &mut Context,
+        _context: &mut Context,
         mut surface_texture: SurfaceTexture,
     ) -> Result<Surface, (Error, SurfaceTexture)> {
+        device_span!(
+            "destroy_surface_texture",
+            context = self.context_id(_context).0
+        );
         GL_FUNCTIONS.with(|gl| {
             unsafe {
                 gl.DeleteTextures(1, &surface_texture.texture_object);
@@ -287,7 +309,12 @@ impl Device {
     ///
     /// The supplied context must match the context the surface was created with, or an
     /// `IncompatibleSurface` error is returned.
-    pub fn present_surface(&self, _: &Context, surface: &mut Surface) -> Result<(), Error> {
+    pub fn present_surface(&self, _context: &Context, surface: &mut Surface) -> Result<(), Error> {
+        device_span!(
+            "present_surface",
+            context = self.context_id(_context).0,
+            size = ?self.surface_info(surface).size,
+        );
         self.0.present_surface(&mut surface.system_surface)?;
 
         GL_FUNCTIONS.with(|gl| {
@@ -312,6 +339,7 @@ impl Device {
         surface: &mut Surface,
         size: Size2D<i32>,
     ) -> Result<(), Error> {
+        device_span!("resize_surface", context = self.context_id(context).0, size = ?size);
         if context.id != surface.context_id {
             return Err(Error::IncompatibleSurface);
         }
@@ -8,17 +8,12 @@ use crate::context::ContextID;
 use crate::egl;
 use crate::egl::types::EGLint;
 use crate::platform::generic::egl::context::{self, CurrentContextGuard, EGLBackedContext};
-use crate::{ContextAttributes, Error, Gl, SurfaceInfo};
+use crate::{ContextAttributes, Error, ExternalSurfaceInfo, GLApi, SurfaceInfo};
 
 use std::os::raw::c_void;
 
 pub use crate::platform::generic::egl::context::{ContextDescriptor, NativeContext};
 
-thread_local! {
-    #[doc(hidden)]
-    pub static GL_FUNCTIONS: Gl = Gl::load_with(context::get_proc_address);
-}
-
 /// Represents an OpenGL rendering context.
 ///
 /// A context allows you to issue rendering commands to a surface. When initially created, a
@@ -46,12 +41,14 @@ impl Device {
         &self,
         attributes: &ContextAttributes,
     ) -> Result<ContextDescriptor, Error> {
+        device_span!("create_context_descriptor", flags = ?attributes.flags);
         // Set environment variables as appropriate.
         self.adapter.set_environment_variables();
 
         unsafe {
             ContextDescriptor::new(
                 self.native_connection.egl_display,
+                self.gl_api(),
                 attributes,
                 &[
                     egl::SURFACE_TYPE as EGLint,
@@ -75,6 +72,7 @@ impl Device {
         descriptor: &ContextDescriptor,
         share_with: Option<&Context>,
     ) -> Result<Context, Error> {
+        device_span!("create_context", shared = share_with.is_some());
         unsafe {
             EGLBackedContext::new(
                 self.native_connection.egl_display,
@@ -96,6 +94,7 @@ impl Device {
         &self,
         native_context: NativeContext,
     ) -> Result<Context, Error> {
+        device_span!("create_context_from_native_context");
         Ok(Context(EGLBackedContext::from_native_context(
             native_context,
         )))
@@ -105,6 +104,7 @@ impl Device {
     ///
     /// The context must have been created on this device.
     pub fn destroy_context(&self, context: &mut Context) -> Result<(), Error> {
+        device_span!("destroy_context", context = self.context_id(context).0);
         if let Ok(Some(mut surface)) = self.unbind_surface_from_context(context) {
             self.destroy_surface(context, &mut surface)?;
         }
@@ -124,13 +124,13 @@ impl Device {
     /// Returns the descriptor that this context was created with.
     #[inline]
     pub fn context_descriptor(&self, context: &Context) -> ContextDescriptor {
-        GL_FUNCTIONS.with(|gl| unsafe {
+        unsafe {
             ContextDescriptor::from_egl_context(
-                gl,
+                context.0.gl(),
                 self.native_connection.egl_display,
                 context.0.egl_context,
             )
-        })
+        }
     }
 
     /// Makes the context the current OpenGL context for this thread.
@@ -138,6 +138,7 @@ impl Device {
     /// After calling this function, it is valid to use OpenGL rendering commands.
     #[inline]
     pub fn make_context_current(&self, context: &Context) -> Result<(), Error> {
+        device_span!("make_context_current", context = self.context_id(context).0);
         unsafe { context.0.make_current(self.native_connection.egl_display) }
     }
 
@@ -147,6 +148,7 @@ impl Device {
     /// made current.
     #[inline]
     pub fn make_no_context_current(&self) -> Result<(), Error> {
+        device_span!("make_no_context_current");
         unsafe { context::make_no_context_current(self.native_connection.egl_display) }
     }
 
@@ -160,6 +162,25 @@ impl Device {
         Ok(guard)
     }
 
+    /// Returns the API that the given context was created for, per `EGL_CONTEXT_CLIENT_TYPE`.
+    #[inline]
+    pub fn context_gl_api(&self, context: &Context) -> GLApi {
+        unsafe {
+            context::context_gl_api(self.native_connection.egl_display, context.0.egl_context)
+        }
+    }
+
+    /// Returns the `EGL_NATIVE_VISUAL_ID` of the given context's config, or 0 if there is none.
+    #[inline]
+    pub fn context_native_visual_id(&self, context: &Context) -> EGLint {
+        unsafe {
+            context::context_native_visual_id(
+                self.native_connection.egl_display,
+                context.0.egl_context,
+            )
+        }
+    }
+
     /// Returns the attributes that the context descriptor was created with.
     #[inline]
     pub fn context_descriptor_attributes(
@@ -197,6 +218,11 @@ impl Device {
         context: &mut Context,
         surface: Surface,
     ) -> Result<(), (Error, Surface)> {
+        device_span!(
+            "bind_surface_to_context",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(&surface).size,
+        );
         unsafe {
             context
                 .0
@@ -213,12 +239,16 @@ impl Device {
         &self,
         context: &mut Context,
     ) -> Result<Option<Surface>, Error> {
-        GL_FUNCTIONS.with(|gl| unsafe {
+        device_span!(
+            "unbind_surface_from_context",
+            context = self.context_id(context).0
+        );
+        unsafe {
             context
                 .0
-                .unbind_surface(gl, self.native_connection.egl_display)
+                .unbind_surface(self.native_connection.egl_display)
                 .map(|maybe_surface| maybe_surface.map(Surface))
-        })
+        }
     }
 
     /// Returns a unique ID representing a context.
@@ -237,4 +267,14 @@ impl Device {
     pub fn context_surface_info(&self, context: &Context) -> Result<Option<SurfaceInfo>, Error> {
         context.0.surface_info()
     }
+
+    /// Returns what EGL reports about an adopted context's render target, or `None` otherwise.
+    #[inline]
+    pub fn context_external_surface_info(&self, context: &Context) -> Option<ExternalSurfaceInfo> {
+        unsafe {
+            context
+                .0
+                .external_surface_info(self.native_connection.egl_display)
+        }
+    }
 }
@@ -2,7 +2,7 @@
 //
 //! A surface implementation using X11 surfaces backed by TextureImage.
 
-use super::context::{Context, GL_FUNCTIONS};
+use super::context::Context;
 use super::device::Device;
 use crate::egl;
 use crate::egl::types::EGLint;
@@ -70,6 +70,14 @@ impl Device {
         _: SurfaceAccess,
         surface_type: SurfaceType<NativeWidget>,
     ) -> Result<Surface, Error> {
+        device_span!(
+            "create_surface",
+            context = self.context_id(context).0,
+            size = ?match surface_type {
+                SurfaceType::Generic { size } => Some(size),
+                SurfaceType::Widget { .. } => None,
+            },
+        );
         match surface_type {
             SurfaceType::Generic { size } => self.create_generic_surface(context, &size),
             SurfaceType::Widget { native_widget } => unsafe {
@@ -86,16 +94,14 @@ impl Device {
         let _guard = self.temporarily_make_context_current(context)?;
         let context_descriptor = self.context_descriptor(context);
         let context_attributes = self.context_descriptor_attributes(&context_descriptor);
-        GL_FUNCTIONS.with(|gl| {
-            Ok(Surface(EGLBackedSurface::new_generic(
-                gl,
-                self.native_connection.egl_display,
-                context.0.egl_context,
-                context.0.id,
-                &context_attributes,
-                size,
-            )))
-        })
+        Ok(Surface(EGLBackedSurface::new_generic(
+            context.0.gl(),
+            self.native_connection.egl_display,
+            context.0.egl_context,
+            context.0.id,
+            &context_attributes,
+            size,
+        )))
     }
 
     unsafe fn create_window_surface(
@@ -151,15 +157,20 @@ impl Device {
         context: &mut Context,
         surface: Surface,
     ) -> Result<SurfaceTexture, (Error, Surface)> {
+        device_span!(
+            "create_surface_texture",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(&surface).size,
+        );
         let _guard = match self.temporarily_make_context_current(context) {
             Ok(guard) => guard,
             Err(err) => return Err((err, surface)),
         };
 
-        GL_FUNCTIONS.with(|gl| match surface.0.to_surface_texture(gl) {
+        match surface.0.to_surface_texture(context.0.gl()) {
             Ok(surface_texture) => Ok(SurfaceTexture(surface_texture)),
             Err((err, surface)) => Err((err, Surface(surface))),
-        })
+        }
     }
 
     /// Destroys a surface.
@@ -174,11 +185,16 @@ impl Device {
         context: &mut Context,
         surface: &mut Surface,
     ) -> Result<(), Error> {
-        GL_FUNCTIONS.with(|gl| {
-            let egl_display = self.native_connection.egl_display;
-            surface.0.destroy(gl, egl_display, context.0.id)?;
-            Ok(())
-        })
+        device_span!(
+            "destroy_surface",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(surface).size,
+        );
+        let egl_display = self.native_connection.egl_display;
+        surface
+            .0
+            .destroy(context.0.gl(), egl_display, context.0.id)?;
+        Ok(())
     }
 
     /// Destroys a surface texture and returns the underlying surface.
@@ -193,8 +209,12 @@ impl Device {
         context: &mut Context,
         surface_texture: SurfaceTexture,
     ) -> Result<Surface, (Error, SurfaceTexture)> {
+        device_span!(
+            "destroy_surface_texture",
+            context = self.context_id(context).0
+        );
         match self.temporarily_make_context_current(context) {
-            Ok(_guard) => GL_FUNCTIONS.with(|gl| Ok(Surface(surface_texture.0.destroy(gl)))),
+            Ok(_guard) => Ok(Surface(surface_texture.0.destroy(context.0.gl()))),
             Err(err) => Err((err, surface_texture)),
         }
     }
@@ -207,6 +227,11 @@ impl Device {
     /// The supplied context must match the context the surface was created with, or an
     /// `IncompatibleSurface` error is returned.
     pub fn present_surface(&self, context: &Context, surface: &mut Surface) -> Result<(), Error> {
+        device_span!(
+            "present_surface",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(surface).size,
+        );
         surface
             .0
             .present(self.native_connection.egl_display, context.0.egl_context)
@@ -219,6 +244,7 @@ impl Device {
         surface: &mut Surface,
         size: Size2D<i32>,
     ) -> Result<(), Error> {
+        device_span!("resize_surface", context = self.context_id(_context).0, size = ?size);
         surface.0.size = size;
         Ok(())
     }
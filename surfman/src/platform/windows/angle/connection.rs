@@ -130,7 +130,8 @@ impl Connection {
         &self,
         egl_display: EGLDisplay,
     ) -> Result<Device, Error> {
-        Device::from_egl_display(egl_display)
+        let egl_device = Device::egl_device_from_egl_display(egl_display)?;
+        Device::from_egl_display(egl_display, egl_device)
     }
 
     /// Opens the display connection corresponding to the given `winit` window.
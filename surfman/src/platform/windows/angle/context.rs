@@ -6,26 +6,35 @@ use super::device::Device;
 use super::surface::{Surface, Synchronization, Win32Objects};
 use crate::context::{ContextID, CREATE_CONTEXT_MUTEX};
 use crate::egl;
-use crate::egl::types::{EGLConfig, EGLContext, EGLint};
+use crate::egl::types::{EGLConfig, EGLContext, EGLDisplay, EGLint};
+use crate::gl;
+use crate::gl::types::{GLchar, GLenum, GLint, GLuint};
+use crate::gl_utils;
 use crate::platform::generic::egl::context::{self, CurrentContextGuard};
 use crate::platform::generic::egl::device::EGL_FUNCTIONS;
 use crate::platform::generic::egl::error::ToWindowingApiError;
 use crate::platform::generic::egl::surface::ExternalEGLSurfaces;
 use crate::surface::Framebuffer;
-use crate::{ContextAttributes, Error, Gl, SurfaceInfo};
+use crate::{ContextAttributeFlags, ContextAttributes, Error, GLInfo, GLVersion, Gl};
+use crate::{ContextPriority, FlushBehavior, GLApi, SurfaceAccess, SurfaceInfo, SurfaceType};
+use crate::{ExternalSurfaceInfo, GLCapability, ResetNotification, SurfaceUsage};
 
+use euclid::default::Size2D;
+use std::any::Any;
 use std::mem;
 use std::os::raw::c_void;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
 use std::thread;
 use winapi::shared::winerror::S_OK;
 use winapi::um::winbase::INFINITE;
+use winapi::um::winnt::HANDLE;
 
-pub use crate::platform::generic::egl::context::{ContextDescriptor, NativeContext};
-
-thread_local! {
-    #[doc(hidden)]
-    pub static GL_FUNCTIONS: Gl = Gl::load_with(context::get_proc_address);
-}
+pub use crate::platform::generic::egl::context::GrantedAttributes;
+pub use crate::platform::generic::egl::context::NativeContext;
+pub use crate::platform::generic::egl::context::SurfaceFormat;
+pub use crate::platform::generic::egl::context::{ConfigInfo, ConfigUpgrades, ContextDescriptor};
 
 /// Represents an OpenGL rendering context.
 ///
@@ -46,8 +55,12 @@ thread_local! {
 pub struct Context {
     pub(crate) egl_context: EGLContext,
     pub(crate) id: ContextID,
-    framebuffer: Framebuffer<Surface, ExternalEGLSurfaces>,
+    pub(crate) framebuffer: Framebuffer<Surface, ExternalEGLSurfaces>,
+    pub(crate) gl_info: GLInfo,
+    pub(crate) descriptor: ContextDescriptor,
     context_is_owned: bool,
+    user_data: Option<Box<dyn Any + Send>>,
+    gl: Gl,
 }
 
 impl Drop for Context {
@@ -59,6 +72,75 @@ impl Drop for Context {
     }
 }
 
+impl Context {
+    // Returns the OpenGL functions for this context, which were loaded when it was created.
+    //
+    // Function pointers aren't guaranteed to be the same across contexts, so each context keeps
+    // its own table rather than sharing a thread-local one.
+    pub(crate) fn gl(&self) -> &Gl {
+        &self.gl
+    }
+}
+
+/// The context and surfaces that were current on this thread before
+/// `make_context_current_saving()` was called.
+///
+/// Pass this to `restore_binding()` to make them current again. Dropping it restores them too.
+/// If no context was current, restoring leaves none current.
+pub struct PreviousBinding {
+    binding: SavedBinding,
+}
+
+// `CurrentContextGuard` can't restore "no context current", since it has no display to unbind
+// from, so that case is handled separately.
+enum SavedBinding {
+    Context(CurrentContextGuard),
+    Nothing(EGLDisplay),
+}
+
+impl PreviousBinding {
+    pub(crate) fn new(egl_display: EGLDisplay) -> PreviousBinding {
+        let no_context_is_current =
+            EGL_FUNCTIONS.with(|egl| unsafe { egl.GetCurrentContext() == egl::NO_CONTEXT });
+        let binding = if no_context_is_current {
+            SavedBinding::Nothing(egl_display)
+        } else {
+            SavedBinding::Context(CurrentContextGuard::new())
+        };
+        PreviousBinding { binding }
+    }
+}
+
+impl Drop for PreviousBinding {
+    fn drop(&mut self) {
+        if let SavedBinding::Nothing(egl_display) = self.binding {
+            unsafe {
+                drop(context::make_no_context_current(egl_display));
+            }
+        }
+    }
+}
+
+// Returns the extra EGL config attributes that ANGLE contexts require.
+pub(crate) fn config_attributes(needs_texture_binding: bool) -> Vec<EGLint> {
+    let mut attributes = vec![];
+    if needs_texture_binding {
+        attributes.extend_from_slice(&[
+            egl::BIND_TO_TEXTURE_RGBA as EGLint,
+            1 as EGLint,
+            egl::SURFACE_TYPE as EGLint,
+            egl::PBUFFER_BIT as EGLint,
+        ]);
+    } else {
+        attributes.extend_from_slice(&[egl::SURFACE_TYPE as EGLint, egl::WINDOW_BIT as EGLint]);
+    }
+    attributes.extend_from_slice(&[
+        egl::RENDERABLE_TYPE as EGLint,
+        egl::OPENGL_ES2_BIT as EGLint,
+    ]);
+    attributes
+}
+
 impl Device {
     /// Creates a context descriptor with the given attributes.
     ///
@@ -68,20 +150,41 @@ impl Device {
         &self,
         attributes: &ContextAttributes,
     ) -> Result<ContextDescriptor, Error> {
-        unsafe {
+        device_span!("create_context_descriptor", flags = ?attributes.flags);
+        self.create_context_descriptor_with_texture_binding(attributes, true)
+    }
+
+    /// Creates a context descriptor, specifying whether texture-backed surfaces will be needed.
+    ///
+    /// Generic surfaces and surface textures require a config that can be bound to a texture,
+    /// which some window-capable configs can't be. If `needs_texture_binding` is false, that
+    /// requirement is dropped and configs are chosen for widget surfaces instead, so more configs
+    /// qualify. Contexts created from such a descriptor can only render to widget surfaces.
+    pub fn create_context_descriptor_with_texture_binding(
+        &self,
+        attributes: &ContextAttributes,
+        needs_texture_binding: bool,
+    ) -> Result<ContextDescriptor, Error> {
+        device_span!("create_context_descriptor_with_texture_binding");
+        // Choosing a config is slow, and the display's configs never change, so reuse the result
+        // for identical requests.
+        let key = (*attributes, needs_texture_binding);
+        if let Some(descriptor) = self.context_descriptor_cache.borrow().get(&key) {
+            return Ok((*descriptor).clone());
+        }
+
+        let descriptor = unsafe {
             ContextDescriptor::new(
                 self.egl_display,
+                self.gl_api(),
                 attributes,
-                &[
-                    egl::BIND_TO_TEXTURE_RGBA as EGLint,
-                    1 as EGLint,
-                    egl::SURFACE_TYPE as EGLint,
-                    egl::PBUFFER_BIT as EGLint,
-                    egl::RENDERABLE_TYPE as EGLint,
-                    egl::OPENGL_ES2_BIT as EGLint,
-                ],
-            )
-        }
+                &config_attributes(needs_texture_binding),
+            )?
+        };
+        self.context_descriptor_cache
+            .borrow_mut()
+            .insert(key, descriptor.clone());
+        Ok(descriptor)
     }
 
     /// Creates a new OpenGL context.
@@ -92,6 +195,15 @@ impl Device {
         &mut self,
         descriptor: &ContextDescriptor,
         share_with: Option<&Context>,
+    ) -> Result<Context, Error> {
+        device_span!("create_context", shared = share_with.is_some());
+        self.create_context_from_descriptor(descriptor, share_with)
+    }
+
+    fn create_context_from_descriptor(
+        &self,
+        descriptor: &ContextDescriptor,
+        share_with: Option<&Context>,
     ) -> Result<Context, Error> {
         let mut next_context_id = CREATE_CONTEXT_MUTEX.lock().unwrap();
         unsafe {
@@ -101,30 +213,442 @@ impl Device {
                 share_with.map_or(egl::NO_CONTEXT, |ctx| ctx.egl_context),
                 self.gl_api(),
             )?;
+            let gl = Gl::load_with(context::get_proc_address);
+            self.wrap_new_egl_context(egl_context, gl, descriptor.clone(), &mut next_context_id)
+        }
+    }
+
+    // Gathers information about a freshly created EGL context and wraps it in an owned
+    // `Context`, taking the next context ID. The context keeps `gl` as its function table. The
+    // descriptor is recorded so that surfaces can be created for the context without querying
+    // EGL again.
+    unsafe fn wrap_new_egl_context(
+        &self,
+        egl_context: EGLContext,
+        gl: Gl,
+        descriptor: ContextDescriptor,
+        next_context_id: &mut ContextID,
+    ) -> Result<Context, Error> {
+        let gl_info = match self.make_current_timeout {
+            None => context::gl_info_for_egl_context(&gl, self.egl_display, egl_context),
+            Some(timeout) => {
+                // EGL handles are just pointers, so they can be sent to the helper thread.
+                // If the thread gets stuck, the context is current there and can't be
+                // destroyed safely, so it's leaked. `Gl` can't be sent, so the thread loads its
+                // own function table.
+                let (display, context) = (self.egl_display as usize, egl_context as usize);
+                context::run_with_timeout(timeout, move || {
+                    let gl = Gl::load_with(context::get_proc_address);
+                    context::gl_info_for_egl_context(
+                        &gl,
+                        display as EGLDisplay,
+                        context as EGLContext,
+                    )
+                })?
+            }
+        };
+
+        // A broken ANGLE install can fail to export core entry points. That's caught while the
+        // GL info is gathered, before any of them is called, rather than the first time surfman
+        // calls one later.
+        let gl_info = match gl_info {
+            Ok(gl_info) => gl_info,
+            Err(err) => {
+                EGL_FUNCTIONS.with(|egl| egl.DestroyContext(self.egl_display, egl_context));
+                return Err(err);
+            }
+        };
+
+        let context = Context {
+            egl_context,
+            id: *next_context_id,
+            framebuffer: Framebuffer::None,
+            gl_info,
+            descriptor,
+            context_is_owned: true,
+            user_data: None,
+            gl,
+        };
+        next_context_id.0 += 1;
+        self.live_context_count.fetch_add(1, Ordering::SeqCst);
+        Ok(context)
+    }
+
+    /// Creates a new OpenGL ES context from the EGL config with the given ID, passing the given
+    /// attribute list to `eglCreateContext()` unmodified.
+    ///
+    /// This is an escape hatch for vendor-specific context attributes that `ContextAttributes`
+    /// can't express. The context is otherwise like one from `create_context()`, and its
+    /// `GLInfo` is gathered as usual. If the display has no config with that ID, a
+    /// `NoPixelFormatFound` error is returned.
+    ///
+    /// # Safety
+    ///
+    /// `raw_attributes` must be a valid EGL context attribute list, terminated by `EGL_NONE`.
+    pub unsafe fn create_context_with_raw_attributes(
+        &self,
+        config_id: EGLint,
+        raw_attributes: &[EGLint],
+    ) -> Result<Context, Error> {
+        device_span!("create_context_with_raw_attributes");
+        let egl_config = match context::try_egl_config_from_id(self.egl_display, config_id) {
+            Some(egl_config) => egl_config,
+            None => return Err(Error::NoPixelFormatFound),
+        };
+
+        let mut next_context_id = CREATE_CONTEXT_MUTEX.lock().unwrap();
+        context::bind_api(self.gl_api());
+        let egl_context = context::create_context_with_attributes(
+            self.egl_display,
+            egl_config,
+            egl::NO_CONTEXT,
+            raw_attributes,
+        )?;
+        let gl = Gl::load_with(context::get_proc_address);
+        let descriptor = ContextDescriptor::from_egl_context(&gl, self.egl_display, egl_context);
+        self.wrap_new_egl_context(egl_context, gl, descriptor, &mut next_context_id)
+    }
+
+    /// Returns information about every config that the EGL display supports.
+    ///
+    /// This is an escape hatch for applications that need a format that `ContextAttributes`
+    /// can't express. Pass the `config_id` of the chosen config to
+    /// `create_context_with_config_id()`.
+    pub fn enumerate_configs(&self) -> Result<Vec<ConfigInfo>, Error> {
+        unsafe { context::enumerate_configs(self.egl_display) }
+    }
+
+    /// Returns the formats that generic surfaces can have on this device.
+    ///
+    /// There is one entry for each EGL config that can back a pbuffer bound to an RGBA texture,
+    /// which generic surfaces require, so the same channel sizes may appear more than once with
+    /// different depth, stencil, or sample counts. An RGBA8 format is always available. The list
+    /// is in the order the display reports its configs.
+    pub fn supported_surface_formats(&self) -> Result<Vec<SurfaceFormat>, Error> {
+        unsafe { context::texture_pbuffer_surface_formats(self.egl_display) }
+    }
+
+    /// Creates a new OpenGL ES context from the EGL config with the given ID.
+    ///
+    /// The config ID is typically obtained from `enumerate_configs()`. If the display has no
+    /// config with that ID, a `NoPixelFormatFound` error is returned.
+    pub fn create_context_with_config_id(
+        &self,
+        config_id: EGLint,
+        version: GLVersion,
+    ) -> Result<Context, Error> {
+        device_span!("create_context_with_config_id");
+        unsafe {
+            if context::try_egl_config_from_id(self.egl_display, config_id).is_none() {
+                return Err(Error::NoPixelFormatFound);
+            }
+        }
+
+        let descriptor = ContextDescriptor {
+            egl_config_id: config_id,
+            gl_version: version,
+            compatibility_profile: false,
+            flush_behavior: FlushBehavior::Flush,
+            priority: ContextPriority::Medium,
+            reset_notification: ResetNotification::None,
+        };
+        self.create_context_from_descriptor(&descriptor, None)
+    }
+
+    /// Creates a new OpenGL ES context from the EGL config that scores highest under the given
+    /// chooser.
+    ///
+    /// Every config the display supports is passed to `chooser`, which returns a score for it, or
+    /// `None` to reject it outright. This covers requirements that `ContextAttributes` can't
+    /// express, such as preferring a particular sample pattern. The chooser is responsible for
+    /// rejecting configs that can't render the requested API. Only the version and the
+    /// compatibility profile flag of `attributes` are used. If every config is rejected, a
+    /// `NoPixelFormatFound` error is returned.
+    pub fn create_context_with_chooser<F>(
+        &self,
+        attributes: &ContextAttributes,
+        chooser: F,
+    ) -> Result<Context, Error>
+    where
+        F: Fn(&ConfigInfo) -> Option<i64>,
+    {
+        device_span!("create_context_with_chooser");
+        let configs = self.enumerate_configs()?;
+        let config_id = configs
+            .iter()
+            .filter_map(|config| chooser(config).map(|score| (score, config.config_id)))
+            .max_by_key(|&(score, _)| score)
+            .map(|(_, config_id)| config_id)
+            .ok_or(Error::NoPixelFormatFound)?;
+
+        let descriptor = ContextDescriptor {
+            egl_config_id: config_id,
+            gl_version: attributes.version,
+            compatibility_profile: attributes
+                .flags
+                .contains(ContextAttributeFlags::COMPATIBILITY_PROFILE),
+            flush_behavior: unsafe {
+                context::supported_flush_behavior(self.egl_display, attributes.flush_behavior)
+            },
+            priority: unsafe { context::supported_priority(self.egl_display, attributes.priority) },
+            reset_notification: unsafe {
+                context::supported_reset_notification(
+                    self.egl_display,
+                    self.gl_api(),
+                    attributes.reset_notification,
+                )
+            },
+        };
+        self.create_context_from_descriptor(&descriptor, None)
+    }
+
+    /// Creates a context with the given attributes, reporting any buffers that its config
+    /// provides beyond what was requested.
+    ///
+    /// EGL may grant a larger config than requested, e.g. one with a depth buffer when none was
+    /// asked for, which wastes memory. The returned `ConfigUpgrades` lists each such attribute,
+    /// and a warning is logged if there are any.
+    pub fn create_context_reporting_upgrades(
+        &mut self,
+        attributes: &ContextAttributes,
+        share_with: Option<&Context>,
+    ) -> Result<(Context, ConfigUpgrades), Error> {
+        device_span!("create_context_reporting_upgrades");
+        let descriptor = self.create_context_descriptor(attributes)?;
+        let upgrades = unsafe { descriptor.upgrades(self.egl_display, attributes) };
+        if !upgrades.is_empty() {
+            warn!(
+                "surfman: The chosen config exceeds the requested attributes: {:?}",
+                upgrades
+            );
+        }
+        let context = self.create_context(&descriptor, share_with)?;
+        Ok((context, upgrades))
+    }
+
+    /// Creates a context, then passes what it was actually created with to the given reporter.
+    ///
+    /// The reporter is called once, after the information in `context_gl_info()` has been
+    /// gathered, with the context's API, the version it reports, and the format of its config.
+    /// This is the place to log what was granted. It isn't called if creation fails.
+    pub fn create_context_with_reporter<F>(
+        &mut self,
+        descriptor: &ContextDescriptor,
+        share_with: Option<&Context>,
+        reporter: F,
+    ) -> Result<Context, Error>
+    where
+        F: FnOnce(&GrantedAttributes),
+    {
+        device_span!("create_context_with_reporter");
+        let context = self.create_context(descriptor, share_with)?;
+        let egl_config = self.context_descriptor_to_egl_config(&context.descriptor);
+        reporter(&GrantedAttributes {
+            api: self.context_gl_api(&context),
+            version: context.gl_info.version(),
+            format: unsafe { context::config_surface_format(self.egl_display, egl_config) },
+        });
+        Ok(context)
+    }
+
+    /// Creates a context with the given attributes, creates a generic surface of the given size,
+    /// binds it to the context, and makes the context current.
+    ///
+    /// This is a shortcut for the common offscreen rendering case. The surface is owned by the
+    /// returned context; retrieve it with `unbind_surface_from_context()` if needed, and
+    /// `context_surface_info()` returns its framebuffer object. If any step fails, everything
+    /// created so far is destroyed.
+    pub fn create_context_with_surface(
+        &mut self,
+        attributes: &ContextAttributes,
+        size: Size2D<i32>,
+    ) -> Result<Context, Error> {
+        device_span!("create_context_with_surface");
+        let descriptor = self.create_context_descriptor(attributes)?;
+        let mut context = self.create_context(&descriptor, None)?;
+
+        let surface = match self.create_surface(
+            &context,
+            SurfaceAccess::GPUOnly,
+            SurfaceType::Generic { size },
+        ) {
+            Ok(surface) => surface,
+            Err(err) => {
+                let _ = self.destroy_context(&mut context);
+                return Err(err);
+            }
+        };
+        if let Err((err, mut surface)) = self.bind_surface_to_context(&mut context, surface) {
+            let _ = self.destroy_surface(&mut context, &mut surface);
+            let _ = self.destroy_context(&mut context);
+            return Err(err);
+        }
+        if let Err(err) = self.make_context_current(&context) {
+            let _ = self.destroy_context(&mut context);
+            return Err(err);
+        }
+        Ok(context)
+    }
+
+    /// Creates a context with a generic surface of the given size bound to it, makes the context
+    /// current, and returns it along with the framebuffer object to render into.
+    ///
+    /// This is `create_context_with_surface()` for offscreen renderers that want to bind the
+    /// framebuffer object right away. The surface is owned by the returned context, so it isn't
+    /// returned separately; retrieve it with `unbind_surface_from_context()` if needed.
+    pub fn create_offscreen_context(
+        &mut self,
+        attributes: &ContextAttributes,
+        size: Size2D<i32>,
+    ) -> Result<(Context, GLuint), Error> {
+        device_span!("create_offscreen_context");
+        let context = self.create_context_with_surface(attributes, size)?;
+        let framebuffer_object = match context.framebuffer {
+            Framebuffer::Surface(ref surface) => self.surface_info(surface).framebuffer_object,
+            // `create_context_with_surface()` always returns the context with its surface bound.
+            Framebuffer::None | Framebuffer::External(_) => unreachable!(),
+        };
+        Ok((context, framebuffer_object))
+    }
+
+    /// Creates a minimal context for loading OpenGL function pointers and makes it current.
+    ///
+    /// The context requests OpenGL ES 2.0 with no depth, stencil, or alpha, which any device that
+    /// can create a context at all supports, and has a 1x1 surface bound so that it's usable
+    /// immediately. Once this returns, pass `get_proc_address()` with the returned context to a
+    /// function like `gl::load_with()`. Destroy the context with `destroy_context()` as usual.
+    pub fn create_loader_context(&mut self) -> Result<Context, Error> {
+        device_span!("create_loader_context");
+        let attributes = ContextAttributes {
+            version: GLVersion::GLES_2_0,
+            flags: ContextAttributeFlags::empty(),
+            ..Default::default()
+        };
+        self.create_context_with_surface(&attributes, Size2D::new(1, 1))
+    }
+
+    /// Creates a context with the newest OpenGL ES version that the device supports.
+    ///
+    /// Versions are tried from OpenGL ES 3.2 down to 2.0, and the first context that can be
+    /// created is returned along with the attributes that were used to create it.
+    pub fn probe_best_context(&self) -> Result<(Context, ContextAttributes), Error> {
+        device_span!("probe_best_context");
+        let attributes = ContextAttributes {
+            version: GLVersion::GLES_3_2,
+            flags: ContextAttributeFlags::empty(),
+            ..Default::default()
+        };
+        let (context, version) = self.create_context_with_version_downgrade(&attributes, None)?;
+        Ok((
+            context,
+            ContextAttributes {
+                version,
+                ..attributes
+            },
+        ))
+    }
 
-            let context = Context {
-                egl_context,
-                id: *next_context_id,
-                framebuffer: Framebuffer::None,
-                context_is_owned: true,
+    /// Creates a new OpenGL ES context, falling back to older versions if the requested one is
+    /// unavailable.
+    ///
+    /// ANGLE's OpenGL ES support depends on the Direct3D feature level of the hardware, so a
+    /// request for OpenGL ES 3.2 may fail where 3.0 would succeed. This tries the requested
+    /// version first, then each of 3.2, 3.1, 3.0, and 2.0 that is older than it, and returns the
+    /// first context that can be created along with the version that was granted. The other
+    /// attributes are never relaxed. If no version works, the error from the requested version is
+    /// returned.
+    pub fn create_context_with_version_downgrade(
+        &self,
+        attributes: &ContextAttributes,
+        share_with: Option<&Context>,
+    ) -> Result<(Context, GLVersion), Error> {
+        device_span!("create_context_with_version_downgrade");
+        let requested = attributes.version;
+        let older_versions = GLVersion::probe_ladder(self.gl_api())
+            .iter()
+            .cloned()
+            .filter(|&version| version < requested);
+
+        let mut first_error = None;
+        for version in Some(requested).into_iter().chain(older_versions) {
+            let attributes = ContextAttributes {
+                version,
+                ..*attributes
             };
-            next_context_id.0 += 1;
-            Ok(context)
+            let result = self
+                .create_context_descriptor(&attributes)
+                .and_then(|descriptor| {
+                    self.create_context_from_descriptor(&descriptor, share_with)
+                });
+            match result {
+                Ok(context) => {
+                    if version != requested {
+                        warn!(
+                            "surfman: OpenGL ES {} unavailable; created an OpenGL ES {} context",
+                            requested, version
+                        );
+                    }
+                    return Ok((context, version));
+                }
+                Err(err) => {
+                    if first_error.is_none() {
+                        first_error = Some(err);
+                    }
+                }
+            }
+        }
+        Err(first_error.unwrap_or(Error::UnsupportedGLVersion))
+    }
+
+    /// Creates a group of contexts that all share one namespace of OpenGL objects.
+    ///
+    /// The first context is created without sharing, and each of the rest shares with it, so a
+    /// texture or buffer created in any of them is visible to all of them. This is useful for a
+    /// pool of worker threads that upload resources while another renders. The shared objects
+    /// live until the last context in the group is destroyed, so the contexts may be destroyed in
+    /// any order. If any context can't be created, the ones created so far are destroyed and the
+    /// error is returned.
+    pub fn create_context_group(
+        &self,
+        attributes: &ContextAttributes,
+        count: usize,
+    ) -> Result<Vec<Context>, Error> {
+        device_span!("create_context_group");
+        let descriptor = self.create_context_descriptor(attributes)?;
+        let mut contexts: Vec<Context> = Vec::with_capacity(count);
+        for _ in 0..count {
+            match self.create_context_from_descriptor(&descriptor, contexts.first()) {
+                Ok(context) => contexts.push(context),
+                Err(err) => {
+                    for mut context in contexts {
+                        let _ = self.destroy_context(&mut context);
+                    }
+                    return Err(err);
+                }
+            }
         }
+        Ok(contexts)
     }
 
     /// Wraps a native `EGLContext` in a context object.
     ///
     /// The underlying `EGLContext` is not retained, as there is no way to do this in the EGL API.
     /// Therefore, it is the caller's responsibility to keep it alive as long as this `Context`
-    /// remains alive.
+    /// remains alive. If a GL function that surfman needs can't be loaded for the context, a
+    /// `RequiredGLFunctionMissing` error is returned.
     pub unsafe fn create_context_from_native_context(
         &self,
         native_context: NativeContext,
     ) -> Result<Context, Error> {
+        device_span!("create_context_from_native_context");
         let mut next_context_id = CREATE_CONTEXT_MUTEX.lock().unwrap();
 
         // Create the context.
+        let gl = Gl::load_with(context::get_proc_address);
+        let gl_info =
+            context::gl_info_for_egl_context(&gl, self.egl_display, native_context.egl_context)?;
+        let descriptor =
+            ContextDescriptor::from_egl_context(&gl, self.egl_display, native_context.egl_context);
         let context = Context {
             egl_context: native_context.egl_context,
             id: *next_context_id,
@@ -132,9 +656,14 @@ impl Device {
                 draw: native_context.egl_draw_surface,
                 read: native_context.egl_read_surface,
             }),
+            gl_info,
+            descriptor,
             context_is_owned: false,
+            user_data: None,
+            gl,
         };
         next_context_id.0 += 1;
+        self.live_context_count.fetch_add(1, Ordering::SeqCst);
 
         Ok(context)
     }
@@ -143,6 +672,7 @@ impl Device {
     ///
     /// The context must have been created on this device.
     pub fn destroy_context(&self, context: &mut Context) -> Result<(), Error> {
+        device_span!("destroy_context", context = self.context_id(context).0);
         if context.egl_context == egl::NO_CONTEXT {
             return Ok(());
         }
@@ -152,39 +682,65 @@ impl Device {
         }
 
         EGL_FUNCTIONS.with(|egl| unsafe {
-            egl.MakeCurrent(
-                self.egl_display,
-                egl::NO_SURFACE,
-                egl::NO_SURFACE,
-                egl::NO_CONTEXT,
-            );
+            // Only unbind the context if it's current on this thread, so that destroying one
+            // context doesn't unbind an unrelated one.
+            if egl.GetCurrentContext() == context.egl_context {
+                egl.MakeCurrent(
+                    self.egl_display,
+                    egl::NO_SURFACE,
+                    egl::NO_SURFACE,
+                    egl::NO_CONTEXT,
+                );
+            }
 
             if context.context_is_owned {
                 let result = egl.DestroyContext(self.egl_display, context.egl_context);
-                egl.DestroyContext(self.egl_display, context.egl_context);
                 assert_ne!(result, egl::FALSE);
             }
 
             context.egl_context = egl::NO_CONTEXT;
         });
+        self.context_lost_callbacks.borrow_mut().remove(&context.id);
+        context.user_data = None;
+        // A context from a device on another display was never counted here, so don't let it
+        // wrap the count around.
+        let decremented =
+            self.live_context_count
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                    count.checked_sub(1)
+                });
+        if decremented.is_err() {
+            error!("surfman: destroyed a context that no device on this display created");
+        }
 
         Ok(())
     }
 
+    /// Returns the number of contexts that haven't been destroyed yet.
+    ///
+    /// This is meant for leak checks in tests. Contexts created by this device and contexts it
+    /// adopted with `create_context_from_native_context()` both count. So do contexts of devices
+    /// opened from this one with `shared_display()` or `create_thread_local_clone()`, which share
+    /// the count, so a context may be destroyed with any of them.
+    #[inline]
+    pub fn live_context_count(&self) -> usize {
+        self.live_context_count.load(Ordering::SeqCst)
+    }
+
     /// Returns the descriptor that this context was created with.
+    ///
+    /// The descriptor is recorded when the context is created, so this doesn't query EGL.
+    #[inline]
     pub fn context_descriptor(&self, context: &Context) -> ContextDescriptor {
-        unsafe {
-            GL_FUNCTIONS.with(|gl| {
-                ContextDescriptor::from_egl_context(gl, self.egl_display, context.egl_context)
-            })
-        }
+        context.descriptor.clone()
     }
 
     /// Makes the context the current OpenGL context for this thread.
     ///
     /// After calling this function, it is valid to use OpenGL rendering commands.
     pub fn make_context_current(&self, context: &Context) -> Result<(), Error> {
-        unsafe {
+        device_span!("make_context_current", context = self.context_id(context).0);
+        let result = unsafe {
             let (egl_draw_surface, egl_read_surface) = match context.framebuffer {
                 Framebuffer::Surface(ref surface) => (surface.egl_surface, surface.egl_surface),
                 Framebuffer::None => (egl::NO_SURFACE, egl::NO_SURFACE),
@@ -199,12 +755,65 @@ impl Device {
                     context.egl_context,
                 );
                 if result == egl::FALSE {
-                    let err = egl.GetError().to_windowing_api_error();
-                    return Err(Error::MakeCurrentFailed(err));
+                    let err = egl.GetError();
+                    return Err(context::make_current_error(
+                        self.egl_display,
+                        egl_draw_surface,
+                        context.egl_context,
+                        err,
+                    ));
                 }
                 Ok(())
             })
+        };
+        self.check_context_lost(context, result)
+    }
+
+    /// Makes the context current on this thread, drawing to one surface and reading from another.
+    ///
+    /// This binds `draw` and `read` as the draw and read surfaces of the default framebuffer, so
+    /// pixels can be copied between them with `glBlitFramebuffer()` or `glCopyTexSubImage2D()`
+    /// without creating framebuffer objects. Both surfaces must have been created for the
+    /// context, or an `IncompatibleSurface` error is returned, and neither may be texture-only.
+    ///
+    /// The context's bound surface is unaffected; calling `make_context_current()` afterward
+    /// binds it again.
+    pub fn make_context_current_with_surfaces(
+        &self,
+        context: &Context,
+        draw: &Surface,
+        read: &Surface,
+    ) -> Result<(), Error> {
+        device_span!(
+            "make_context_current_with_surfaces",
+            context = self.context_id(context).0
+        );
+        if draw.context_id != context.id || read.context_id != context.id {
+            return Err(Error::IncompatibleSurface);
+        }
+        if draw.usage == SurfaceUsage::TextureOnly || read.usage == SurfaceUsage::TextureOnly {
+            return Err(Error::SurfaceNotRenderable);
         }
+
+        let result = EGL_FUNCTIONS.with(|egl| unsafe {
+            let result = egl.MakeCurrent(
+                self.egl_display,
+                draw.egl_surface,
+                read.egl_surface,
+                context.egl_context,
+            );
+            if result == egl::FALSE {
+                let err = egl.GetError();
+                return Err(context::make_current_error(
+                    self.egl_display,
+                    draw.egl_surface,
+                    context.egl_context,
+                    err,
+                ));
+            }
+            Ok(())
+        });
+        self.check_context_lost(context, result)
     }
 
     /// Removes the current OpenGL context from this thread.
@@ -212,6 +821,7 @@ impl Device {
     /// After calling this function, OpenGL rendering commands will fail until a new context is
     /// made current.
     pub fn make_no_context_current(&self) -> Result<(), Error> {
+        device_span!("make_no_context_current");
         unsafe { context::make_no_context_current(self.egl_display) }
     }
 
@@ -224,10 +834,62 @@ impl Device {
         Ok(guard)
     }
 
+    /// Runs a closure with the given context current, then restores whatever binding the thread
+    /// had before, and returns the closure's result.
+    ///
+    /// The previous binding is restored even if the closure panics. If no context was current
+    /// beforehand, none is afterward. If the context can't be made current, the closure isn't
+    /// run and the error is returned.
+    pub fn with_context_current<R>(
+        &self,
+        context: &Context,
+        f: impl FnOnce() -> R,
+    ) -> Result<R, Error> {
+        let _previous_binding = PreviousBinding::new(self.egl_display);
+        self.make_context_current(context)?;
+        Ok(f())
+    }
+
+    /// Makes the given context current on this thread, returning the binding it replaced.
+    ///
+    /// This is the manual counterpart of `with_context_current()`, for when the previous binding
+    /// must outlive a single scope. The returned value captures the previous context and its
+    /// draw and read surfaces; pass it to `restore_binding()` to make them current again. If the
+    /// context can't be made current, the previous binding is left in place and the error is
+    /// returned.
+    pub fn make_context_current_saving(&self, context: &Context) -> Result<PreviousBinding, Error> {
+        device_span!(
+            "make_context_current_saving",
+            context = self.context_id(context).0
+        );
+        let previous_binding = PreviousBinding::new(self.egl_display);
+        self.make_context_current(context)?;
+        Ok(previous_binding)
+    }
+
+    /// Makes the binding saved by `make_context_current_saving()` current again.
+    #[inline]
+    pub fn restore_binding(&self, previous_binding: PreviousBinding) {
+        device_span!("restore_binding");
+        drop(previous_binding)
+    }
+
     pub(crate) fn context_is_current(&self, context: &Context) -> bool {
         EGL_FUNCTIONS.with(|egl| unsafe { egl.GetCurrentContext() == context.egl_context })
     }
 
+    /// Returns the API that the given context was created for, per `EGL_CONTEXT_CLIENT_TYPE`.
+    #[inline]
+    pub fn context_gl_api(&self, context: &Context) -> GLApi {
+        unsafe { context::context_gl_api(self.egl_display, context.egl_context) }
+    }
+
+    /// Returns the `EGL_NATIVE_VISUAL_ID` of the given context's config, or 0 if there is none.
+    #[inline]
+    pub fn context_native_visual_id(&self, context: &Context) -> EGLint {
+        unsafe { context::context_native_visual_id(self.egl_display, context.egl_context) }
+    }
+
     /// Returns the attributes that the context descriptor was created with.
     #[inline]
     pub fn context_descriptor_attributes(
@@ -272,9 +934,17 @@ impl Device {
         context: &mut Context,
         surface: Surface,
     ) -> Result<(), (Error, Surface)> {
+        device_span!(
+            "bind_surface_to_context",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(&surface).size,
+        );
         if context.id != surface.context_id {
             return Err((Error::IncompatibleSurface, surface));
         }
+        if surface.usage == SurfaceUsage::TextureOnly {
+            return Err((Error::SurfaceNotRenderable, surface));
+        }
 
         match context.framebuffer {
             Framebuffer::None => {}
@@ -287,7 +957,7 @@ impl Device {
         if surface.uses_gl_finish() {
             if let Ok(_guard) = self.temporarily_make_context_current(context) {
                 unsafe {
-                    GL_FUNCTIONS.with(|gl| gl.Finish());
+                    context.gl().Finish();
                 }
             }
         }
@@ -305,6 +975,9 @@ impl Device {
             _ => {}
         }
 
+        self.surface_bindings
+            .borrow_mut()
+            .push((context.id, surface.id()));
         context.framebuffer = Framebuffer::Surface(surface);
 
         if is_current {
@@ -323,6 +996,10 @@ impl Device {
         &self,
         context: &mut Context,
     ) -> Result<Option<Surface>, Error> {
+        device_span!(
+            "unbind_surface_from_context",
+            context = self.context_id(context).0
+        );
         match context.framebuffer {
             Framebuffer::None => return Ok(None),
             Framebuffer::External(_) => return Err(Error::ExternalRenderTarget),
@@ -333,6 +1010,9 @@ impl Device {
             Framebuffer::Surface(surface) => surface,
             Framebuffer::None | Framebuffer::External(_) => unreachable!(),
         };
+        self.surface_bindings
+            .borrow_mut()
+            .retain(|&(context_id, _)| context_id != context.id);
 
         match surface.win32_objects {
             Win32Objects::Pbuffer {
@@ -348,6 +1028,242 @@ impl Device {
         Ok(Some(surface))
     }
 
+    /// Replaces the surface attached to a context, returning the old one, if any.
+    ///
+    /// This is equivalent to `unbind_surface_from_context()` followed by
+    /// `bind_surface_to_context()`, and like them it leaves the thread's current context alone:
+    /// the context is made current again with the new surface only if it was current before.
+    /// Other contexts that are current on this thread are unaffected.
+    ///
+    /// The new surface must have been created with this context, or an `IncompatibleSurface`
+    /// error is returned. If an error is returned, the new surface is returned alongside it and
+    /// the old surface stays attached.
+    pub fn replace_context_surface(
+        &self,
+        context: &mut Context,
+        new_surface: Surface,
+    ) -> Result<Option<Surface>, (Error, Surface)> {
+        device_span!(
+            "replace_context_surface",
+            context = self.context_id(context).0
+        );
+        if context.id != new_surface.context_id {
+            return Err((Error::IncompatibleSurface, new_surface));
+        }
+        if new_surface.usage == SurfaceUsage::TextureOnly {
+            return Err((Error::SurfaceNotRenderable, new_surface));
+        }
+
+        let old_surface = match self.unbind_surface_from_context(context) {
+            Ok(old_surface) => old_surface,
+            Err(err) => return Err((err, new_surface)),
+        };
+        match self.bind_surface_to_context(context, new_surface) {
+            Ok(()) => Ok(old_surface),
+            Err((err, new_surface)) => {
+                if let Some(old_surface) = old_surface {
+                    let _ = self.bind_surface_to_context(context, old_surface);
+                }
+                Err((err, new_surface))
+            }
+        }
+    }
+
+    /// Returns information about the OpenGL implementation behind the given context, such as
+    /// the renderer and vendor strings.
+    #[inline]
+    pub fn context_gl_info<'c>(&self, context: &'c Context) -> &'c GLInfo {
+        &context.gl_info
+    }
+
+    /// Returns true if the given context provides the given optional OpenGL feature.
+    ///
+    /// This is resolved against the version and extensions in `context_gl_info()`, so it doesn't
+    /// touch the context.
+    #[inline]
+    pub fn context_supports(&self, context: &Context, capability: GLCapability) -> bool {
+        context.gl_info.supports(capability)
+    }
+
+    /// Gathers the information returned by `context_gl_info()` again, replacing what was
+    /// gathered when the context was created.
+    ///
+    /// The context is made current for the duration of the call, and the previous binding is
+    /// restored afterward. If the context can't be made current, the existing information is
+    /// kept and the error is returned.
+    pub fn repopulate_context_gl_info(&self, context: &mut Context) -> Result<(), Error> {
+        device_span!(
+            "repopulate_context_gl_info",
+            context = self.context_id(context).0
+        );
+        let gl_info = {
+            let _previous_binding = PreviousBinding::new(self.egl_display);
+            self.make_context_current(context)?;
+            GLInfo::populate(context.gl())
+        };
+        context.gl_info = gl_info;
+        Ok(())
+    }
+
+    /// Returns true if the given context has been lost, e.g. due to a GPU reset or driver update.
+    ///
+    /// This is cheap enough to call once per frame. A lost context can't be recovered; destroy
+    /// it and recreate it along with its resources.
+    pub fn poll_context_lost(&self, context: &Context) -> bool {
+        device_span!("poll_context_lost", context = self.context_id(context).0);
+        let lost = unsafe { context::egl_context_is_lost(self.egl_display, context.egl_context) };
+        if lost {
+            self.notify_context_lost(context);
+        }
+        lost
+    }
+
+    /// Sets a function to be called whenever surfman detects that the given context has been
+    /// lost.
+    ///
+    /// Loss is detected when an EGL call made on the context's behalf fails with
+    /// `EGL_CONTEXT_LOST`, or when `poll_context_lost()` returns true. Each context has at most
+    /// one callback; setting another replaces it. The callback is dropped when the context is
+    /// destroyed.
+    pub fn set_context_lost_callback(&self, context: &Context, callback: Box<dyn Fn()>) {
+        self.context_lost_callbacks
+            .borrow_mut()
+            .insert(context.id, Rc::from(callback));
+    }
+
+    /// Attaches arbitrary application data to the given context, replacing any data attached
+    /// before.
+    ///
+    /// This saves keeping a side table keyed by context ID. The data is dropped when it's
+    /// replaced or when the context is destroyed.
+    #[inline]
+    pub fn set_context_user_data(&self, context: &mut Context, data: Box<dyn Any + Send>) {
+        context.user_data = Some(data);
+    }
+
+    /// Returns the application data attached to the given context with
+    /// `set_context_user_data()`, if any.
+    ///
+    /// Use `downcast_ref()` to recover the concrete type.
+    #[inline]
+    pub fn context_user_data<'c>(&self, context: &'c Context) -> Option<&'c (dyn Any + Send)> {
+        context.user_data.as_deref()
+    }
+
+    // Runs the context's loss callback if the given result shows that the context was lost.
+    pub(crate) fn check_context_lost<T>(
+        &self,
+        context: &Context,
+        result: Result<T, Error>,
+    ) -> Result<T, Error> {
+        if let Err(ref err) = result {
+            if err.is_context_lost() {
+                self.notify_context_lost(context);
+            }
+        }
+        result
+    }
+
+    fn notify_context_lost(&self, context: &Context) {
+        // Release the borrow first, in case the callback sets a new callback.
+        let callback = self
+            .context_lost_callbacks
+            .borrow()
+            .get(&context.id)
+            .cloned();
+        if let Some(callback) = callback {
+            callback();
+        }
+    }
+
+    /// Clears and returns all pending OpenGL errors on the given context.
+    ///
+    /// Device methods that do GL work on the application's behalf, like `clear_surface()` or
+    /// `read_surface_pixels()`, clear every pending error when they finish, so that the
+    /// application doesn't see errors it never caused. That includes errors the application
+    /// raised before calling them. Call this first to collect those. This is mainly useful for
+    /// logging.
+    pub fn drain_gl_errors(&self, context: &Context) -> Result<Vec<GLenum>, Error> {
+        device_span!("drain_gl_errors", context = self.context_id(context).0);
+        let _guard = self.temporarily_make_context_current(context)?;
+        Ok(gl_utils::drain_gl_errors(context.gl()))
+    }
+
+    /// Compiles and links a trivial shader program on the given context, then deletes it.
+    ///
+    /// ANGLE initializes the Direct3D HLSL compiler on the first shader compile, which can take
+    /// hundreds of milliseconds. Calling this during a loading screen moves that hitch out of the
+    /// first frame. It's harmless to call more than once, and it leaves no GL objects or state
+    /// behind. If the program fails to link, a `Failed` error is returned.
+    pub fn prewarm(&self, context: &Context) -> Result<(), Error> {
+        device_span!("prewarm", context = self.context_id(context).0);
+        let _guard = self.temporarily_make_context_current(context)?;
+        let gl = context.gl();
+        unsafe {
+            let vertex_shader = compile_shader(gl, gl::VERTEX_SHADER, PREWARM_VERTEX_SHADER);
+            let fragment_shader = compile_shader(gl, gl::FRAGMENT_SHADER, PREWARM_FRAGMENT_SHADER);
+            let program = gl.CreateProgram();
+            gl.AttachShader(program, vertex_shader);
+            gl.AttachShader(program, fragment_shader);
+            gl.LinkProgram(program);
+            let mut link_status = 0;
+            gl.GetProgramiv(program, gl::LINK_STATUS, &mut link_status);
+            gl.DeleteProgram(program);
+            gl.DeleteShader(vertex_shader);
+            gl.DeleteShader(fragment_shader);
+            gl_utils::drain_gl_errors(gl);
+            if link_status != gl::TRUE as GLint {
+                return Err(Error::Failed);
+            }
+            Ok(())
+        }
+    }
+
+    /// Imports a Win32 semaphore handle and signals it once all GL commands previously issued on
+    /// the given context have executed, then flushes the context.
+    ///
+    /// `GL_EXT_semaphore` cannot consume a `VkSemaphore` directly, so the semaphore must first be
+    /// exported from Vulkan as an opaque Win32 handle (`VK_KHR_external_semaphore_win32`). GL does
+    /// not take ownership of the handle. Returns a `RequiredExtensionUnavailable` error if the
+    /// context doesn't support both `GL_EXT_semaphore` and `GL_EXT_semaphore_win32`.
+    pub unsafe fn signal_semaphore_on_flush(
+        &self,
+        context: &Context,
+        semaphore_handle: HANDLE,
+    ) -> Result<(), Error> {
+        device_span!(
+            "signal_semaphore_on_flush",
+            context = self.context_id(context).0
+        );
+        let _guard = self.temporarily_make_context_current(context)?;
+        if !context.gl_info.has_extension("GL_EXT_semaphore")
+            || !context.gl_info.has_extension("GL_EXT_semaphore_win32")
+        {
+            return Err(Error::RequiredExtensionUnavailable);
+        }
+
+        let gl = context.gl();
+
+        let mut semaphore = 0;
+        gl.GenSemaphoresEXT(1, &mut semaphore);
+        gl.ImportSemaphoreWin32HandleEXT(
+            semaphore,
+            gl::HANDLE_TYPE_OPAQUE_WIN32_EXT,
+            semaphore_handle as *mut c_void,
+        );
+        gl.SignalSemaphoreEXT(semaphore, 0, ptr::null(), 0, ptr::null(), ptr::null());
+        gl.Flush();
+        gl.DeleteSemaphoresEXT(1, &semaphore);
+
+        match gl.GetError() {
+            gl::NO_ERROR => Ok(()),
+            _ => {
+                gl_utils::drain_gl_errors(gl);
+                Err(Error::Failed)
+            }
+        }
+    }
+
     /// Returns a unique ID representing a context.
     ///
     /// This ID is unique to all currently-allocated contexts. If you destroy a context and create
@@ -368,6 +1284,31 @@ impl Device {
         }
     }
 
+    /// Returns what EGL reports about an adopted context's render target, or `None` otherwise.
+    pub fn context_external_surface_info(&self, context: &Context) -> Option<ExternalSurfaceInfo> {
+        match context.framebuffer {
+            Framebuffer::External(ref surfaces) => unsafe {
+                Some(surfaces.info(self.egl_display, context.id))
+            },
+            Framebuffer::None | Framebuffer::Surface(_) => None,
+        }
+    }
+
+    /// Returns the format of the surface that the given context draws to.
+    ///
+    /// Unlike `context_surface_info()`, this works for external render targets, such as the
+    /// back buffer of a context adopted with `create_context_from_native_context()`, so offscreen
+    /// surfaces can be created to match it. The context doesn't need to be current. If it has no
+    /// draw surface, a `NoSurfaceAttached` error is returned.
+    pub fn external_surface_format(&self, context: &Context) -> Result<SurfaceFormat, Error> {
+        let egl_surface = match context.framebuffer {
+            Framebuffer::Surface(ref surface) => surface.egl_surface,
+            Framebuffer::External(ExternalEGLSurfaces { draw, .. }) => draw,
+            Framebuffer::None => egl::NO_SURFACE,
+        };
+        unsafe { context::egl_surface_format(self.egl_display, egl_surface) }
+    }
+
     /// Given a context, returns its underlying EGL context and attached surfaces.
     pub fn native_context(&self, context: &Context) -> NativeContext {
         let (egl_draw_surface, egl_read_surface) = match context.framebuffer {
@@ -383,3 +1324,27 @@ impl Device {
         }
     }
 }
+
+// GLSL ES 1.00, so that the shaders compile on every OpenGL ES version.
+const PREWARM_VERTEX_SHADER: &[u8] = b"attribute vec2 aPosition;
+void main() {
+    gl_Position = vec4(aPosition, 0.0, 1.0);
+}
+";
+const PREWARM_FRAGMENT_SHADER: &[u8] = b"precision mediump float;
+void main() {
+    gl_FragColor = vec4(1.0);
+}
+";
+
+unsafe fn compile_shader(gl: &Gl, shader_type: GLenum, source: &[u8]) -> GLuint {
+    let shader = gl.CreateShader(shader_type);
+    gl.ShaderSource(
+        shader,
+        1,
+        &(source.as_ptr() as *const GLchar),
+        &(source.len() as GLint),
+    );
+    gl.CompileShader(shader);
+    shader
+}
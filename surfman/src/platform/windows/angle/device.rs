@@ -3,23 +3,39 @@
 //! A thread-local handle to the device.
 
 use super::connection::Connection;
+use super::context::ContextDescriptor;
+use super::surface::PresentMode;
 use crate::egl;
 use crate::egl::types::{EGLAttrib, EGLDisplay, EGLint, EGLDeviceEXT};
-use crate::platform::generic::egl::device::EGL_FUNCTIONS;
+use crate::platform::generic::egl::device::{egl_extensions, EGL_FUNCTIONS};
 use crate::platform::generic::egl::ffi::{EGL_D3D11_DEVICE_ANGLE, EGL_EXTENSION_FUNCTIONS};
+use crate::platform::generic::egl::ffi::EGL_FEATURE_OVERRIDES_DISABLED_ANGLE;
+use crate::platform::generic::egl::ffi::EGL_FEATURE_OVERRIDES_ENABLED_ANGLE;
 use crate::platform::generic::egl::ffi::{EGL_NO_DEVICE_EXT, EGL_PLATFORM_DEVICE_EXT};
 use crate::platform::generic::egl::ffi::{EGL_DEVICE_EXT};
-use crate::{Error, GLApi};
+use crate::{ContextAttributes, ContextID, Error, GLApi, GLVersion, SurfaceID};
 
-use std::cell::{RefCell, RefMut};
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
 use std::mem;
-use std::os::raw::c_void;
+use std::os::raw::{c_char, c_void};
 use std::ptr;
-use winapi::shared::dxgi::{self, IDXGIAdapter, IDXGIDevice, IDXGIFactory1};
+use std::rc::Rc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+use std::time::Duration;
+use winapi::shared::dxgi::{self, IDXGIAdapter, IDXGIDevice, IDXGIFactory1, IDXGIOutput};
 use winapi::shared::minwindef::UINT;
+use winapi::shared::ntdef::LUID;
+use winapi::shared::windef::HMONITOR;
 use winapi::shared::winerror::{self, S_OK};
 use winapi::um::d3d11::{D3D11CreateDevice, ID3D11Device, D3D11_SDK_VERSION};
-use winapi::um::d3dcommon::{D3D_DRIVER_TYPE, D3D_DRIVER_TYPE_UNKNOWN, D3D_FEATURE_LEVEL_9_3};
+use winapi::um::d3dcommon::D3D_DRIVER_TYPE_WARP;
+use winapi::um::d3dcommon::D3D_FEATURE_LEVEL_11_0;
+use winapi::um::d3dcommon::D3D_FEATURE_LEVEL_9_3;
+use winapi::um::d3dcommon::{D3D_DRIVER_TYPE, D3D_DRIVER_TYPE_UNKNOWN, D3D_FEATURE_LEVEL};
+use winapi::um::d3dcommon::{D3D_FEATURE_LEVEL_10_0, D3D_FEATURE_LEVEL_11_1};
 use winapi::Interface;
 use wio::com::ComPtr;
 
@@ -38,6 +54,30 @@ pub struct Adapter {
 
 unsafe impl Send for Adapter {}
 
+/// The EGL display and Direct3D 11 device behind a device, in a form that can be sent to another
+/// thread.
+///
+/// Devices opened from the same shared display can use each other's surfaces without copying.
+/// If `surfman` initialized the display, it is terminated once the last of these devices and
+/// shared displays is dropped.
+#[derive(Clone)]
+pub struct SharedDisplay {
+    egl_display: EGLDisplay,
+    d3d11_device: ComPtr<ID3D11Device>,
+    d3d_driver_type: D3D_DRIVER_TYPE,
+    owned_display: Option<Arc<OwnedDisplay>>,
+    egl_display_extensions: HashSet<String>,
+    live_context_count: Arc<AtomicUsize>,
+}
+
+unsafe impl Send for SharedDisplay {}
+
+// Terminates an EGL display that `surfman` initialized when dropped.
+pub(crate) struct OwnedDisplay(EGLDisplay);
+
+unsafe impl Send for OwnedDisplay {}
+unsafe impl Sync for OwnedDisplay {}
+
 /// A thread-local handle to a device.
 ///
 /// Devices contain most of the relevant surface management methods.
@@ -45,9 +85,51 @@ pub struct Device {
     pub(crate) egl_display: EGLDisplay,
     pub(crate) d3d11_device: ComPtr<ID3D11Device>,
     pub(crate) d3d_driver_type: D3D_DRIVER_TYPE,
-    pub(crate) display_is_owned: bool,
+    // Shared with this device's thread-local clones. `None` if surfman didn't initialize the
+    // display and so must not terminate it.
+    pub(crate) owned_display: Option<Arc<OwnedDisplay>>,
+    pub(crate) surface_creation_retry_count: u32,
+    pub(crate) surface_bindings: RefCell<Vec<(ContextID, SurfaceID)>>,
+    // Keyed by the attributes and whether texture binding is needed.
+    pub(crate) context_descriptor_cache:
+        RefCell<HashMap<(ContextAttributes, bool), ContextDescriptor>>,
+    pub(crate) make_current_timeout: Option<Duration>,
+    pub(crate) present_mode: Cell<PresentMode>,
+    pub(crate) egl_display_extensions: HashSet<String>,
+    pub(crate) context_lost_callbacks: RefCell<HashMap<ContextID, Rc<dyn Fn()>>>,
+    // Shared with every device opened through `shared_display()`, so that a context can be
+    // destroyed with any of them.
+    pub(crate) live_context_count: Arc<AtomicUsize>,
+}
+
+// A null-terminated list of C strings, as `EGL_ANGLE_feature_control` expects.
+struct FeatureList {
+    _names: Vec<CString>,
+    pointers: Vec<*const c_char>,
+}
+
+impl FeatureList {
+    fn new(names: &[&str]) -> Result<FeatureList, Error> {
+        let names = names
+            .iter()
+            .map(|name| CString::new(*name).map_err(|_| Error::Failed))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut pointers: Vec<_> = names.iter().map(|name| name.as_ptr()).collect();
+        pointers.push(ptr::null());
+        Ok(FeatureList {
+            _names: names,
+            pointers,
+        })
+    }
+
+    fn as_ptr(&self) -> *const *const c_char {
+        self.pointers.as_ptr()
+    }
 }
 
+// The ANGLE feature that stops compiled programs from being stored in the program cache.
+const DISABLE_PROGRAM_CACHE_FEATURE: &str = "disableProgramCaching";
+
 pub(crate) enum VendorPreference {
     None,
     Prefer(UINT),
@@ -71,23 +153,7 @@ impl Adapter {
         vendor_preference: VendorPreference,
     ) -> Result<Adapter, Error> {
         unsafe {
-            let dxgi_factory = DXGI_FACTORY.with(|dxgi_factory_slot| {
-                let mut dxgi_factory_slot: RefMut<Option<ComPtr<IDXGIFactory1>>> =
-                    dxgi_factory_slot.borrow_mut();
-                if dxgi_factory_slot.is_none() {
-                    let mut dxgi_factory: *mut IDXGIFactory1 = ptr::null_mut();
-                    let result = dxgi::CreateDXGIFactory1(
-                        &IDXGIFactory1::uuidof(),
-                        &mut dxgi_factory as *mut *mut IDXGIFactory1 as *mut *mut c_void,
-                    );
-                    if !winerror::SUCCEEDED(result) {
-                        return Err(Error::Failed);
-                    }
-                    assert!(!dxgi_factory.is_null());
-                    *dxgi_factory_slot = Some(ComPtr::from_raw(dxgi_factory));
-                }
-                Ok((*dxgi_factory_slot).clone().unwrap())
-            })?;
+            let dxgi_factory = dxgi_factory()?;
 
             // Find the first adapter that matches the vendor preference.
             let mut adapter_index = 0;
@@ -158,18 +224,195 @@ impl Adapter {
             d3d_driver_type: D3D_DRIVER_TYPE_UNKNOWN,
         }
     }
+
+    // Finds the adapter with the given locally unique identifier.
+    pub(crate) fn from_luid(luid: LUID) -> Result<Adapter, Error> {
+        unsafe {
+            let dxgi_factory = dxgi_factory()?;
+            let mut adapter_index = 0;
+            loop {
+                let mut dxgi_adapter_1 = ptr::null_mut();
+                let result = (*dxgi_factory).EnumAdapters1(adapter_index, &mut dxgi_adapter_1);
+                if !winerror::SUCCEEDED(result) {
+                    return Err(Error::NoAdapterFound);
+                }
+                assert!(!dxgi_adapter_1.is_null());
+                let dxgi_adapter_1 = ComPtr::from_raw(dxgi_adapter_1);
+
+                let mut adapter_desc = mem::zeroed();
+                let result = (*dxgi_adapter_1).GetDesc1(&mut adapter_desc);
+                assert_eq!(result, S_OK);
+
+                if adapter_desc.AdapterLuid.LowPart == luid.LowPart
+                    && adapter_desc.AdapterLuid.HighPart == luid.HighPart
+                {
+                    let mut dxgi_adapter: *mut IDXGIAdapter = ptr::null_mut();
+                    let result = (*dxgi_adapter_1).QueryInterface(
+                        &IDXGIAdapter::uuidof(),
+                        &mut dxgi_adapter as *mut *mut IDXGIAdapter as *mut *mut c_void,
+                    );
+                    assert_eq!(result, S_OK);
+                    return Ok(Adapter::from_dxgi_adapter(ComPtr::from_raw(dxgi_adapter)));
+                }
+
+                adapter_index += 1;
+            }
+        }
+    }
+
+    // Finds the adapter that drives the given monitor.
+    pub(crate) fn from_monitor(hmonitor: HMONITOR) -> Result<Adapter, Error> {
+        unsafe {
+            let dxgi_factory = dxgi_factory()?;
+            let mut adapter_index = 0;
+            loop {
+                let mut dxgi_adapter_1 = ptr::null_mut();
+                let result = (*dxgi_factory).EnumAdapters1(adapter_index, &mut dxgi_adapter_1);
+                if !winerror::SUCCEEDED(result) {
+                    return Err(Error::NoAdapterFound);
+                }
+                assert!(!dxgi_adapter_1.is_null());
+                let dxgi_adapter_1 = ComPtr::from_raw(dxgi_adapter_1);
+
+                let mut output_index = 0;
+                loop {
+                    let mut dxgi_output: *mut IDXGIOutput = ptr::null_mut();
+                    let result = (*dxgi_adapter_1).EnumOutputs(output_index, &mut dxgi_output);
+                    if !winerror::SUCCEEDED(result) {
+                        break;
+                    }
+                    assert!(!dxgi_output.is_null());
+                    let dxgi_output = ComPtr::from_raw(dxgi_output);
+
+                    let mut output_desc = mem::zeroed();
+                    let result = (*dxgi_output).GetDesc(&mut output_desc);
+                    if winerror::SUCCEEDED(result) && output_desc.Monitor == hmonitor {
+                        let mut dxgi_adapter: *mut IDXGIAdapter = ptr::null_mut();
+                        let result = (*dxgi_adapter_1).QueryInterface(
+                            &IDXGIAdapter::uuidof(),
+                            &mut dxgi_adapter as *mut *mut IDXGIAdapter as *mut *mut c_void,
+                        );
+                        assert_eq!(result, S_OK);
+                        return Ok(Adapter::from_dxgi_adapter(ComPtr::from_raw(dxgi_adapter)));
+                    }
+
+                    output_index += 1;
+                }
+
+                adapter_index += 1;
+            }
+        }
+    }
+
+    /// Returns the locally unique identifier of this adapter.
+    ///
+    /// Pass this to `Device::new_on_adapter_luid()` to open a device on this adapter later.
+    pub fn luid(&self) -> LUID {
+        unsafe {
+            let mut adapter_desc = mem::zeroed();
+            let result = (*self.dxgi_adapter).GetDesc(&mut adapter_desc);
+            assert_eq!(result, S_OK);
+            adapter_desc.AdapterLuid
+        }
+    }
+}
+
+// Returns this thread's DXGI factory, creating it if necessary.
+unsafe fn dxgi_factory() -> Result<ComPtr<IDXGIFactory1>, Error> {
+    DXGI_FACTORY.with(|dxgi_factory_slot| {
+        let mut dxgi_factory_slot: RefMut<Option<ComPtr<IDXGIFactory1>>> =
+            dxgi_factory_slot.borrow_mut();
+        if dxgi_factory_slot.is_none() {
+            let mut dxgi_factory: *mut IDXGIFactory1 = ptr::null_mut();
+            let result = dxgi::CreateDXGIFactory1(
+                &IDXGIFactory1::uuidof(),
+                &mut dxgi_factory as *mut *mut IDXGIFactory1 as *mut *mut c_void,
+            );
+            if !winerror::SUCCEEDED(result) {
+                return Err(Error::Failed);
+            }
+            assert!(!dxgi_factory.is_null());
+            *dxgi_factory_slot = Some(ComPtr::from_raw(dxgi_factory));
+        }
+        Ok((*dxgi_factory_slot).clone().unwrap())
+    })
 }
 
 impl Device {
-    #[allow(non_snake_case)]
     pub(crate) fn new(adapter: &Adapter) -> Result<Device, Error> {
+        Device::new_with_features(adapter, &[], &[])
+    }
+
+    /// Opens a device on the adapter with the given locally unique identifier.
+    ///
+    /// On systems with hybrid graphics, this guarantees that rendering happens on a specific
+    /// GPU. The LUID can be obtained from `Adapter::luid()` or from DXGI directly. If no adapter
+    /// has that LUID, a `NoAdapterFound` error is returned.
+    pub fn new_on_adapter_luid(luid: LUID) -> Result<Device, Error> {
+        device_span!("new_on_adapter_luid");
+        Device::new(&Adapter::from_luid(luid)?)
+    }
+
+    /// Opens a device on the adapter that drives the given monitor.
+    ///
+    /// On systems with several GPUs, rendering on the adapter that scans out the window's monitor
+    /// avoids copying every presented frame between adapters. The monitor can be found with
+    /// `MonitorFromWindow()`. If no adapter has an output on that monitor, a `NoAdapterFound`
+    /// error is returned.
+    pub fn new_for_output(hmonitor: HMONITOR) -> Result<Device, Error> {
+        device_span!("new_for_output");
+        Device::new(&Adapter::from_monitor(hmonitor)?)
+    }
+
+    /// Opens the device for the given adapter, falling back to the WARP software rasterizer if
+    /// that fails.
+    ///
+    /// This is useful on virtual machines and remote desktop sessions, where no compatible
+    /// hardware adapter may exist. The fallback is logged as a warning. If the adapter is already
+    /// a software adapter, or WARP fails too, the error is returned.
+    pub fn new_with_fallback(adapter: &Adapter) -> Result<Device, Error> {
+        device_span!("new_with_fallback");
+        match Device::new(adapter) {
+            Ok(device) => Ok(device),
+            Err(err) if adapter.d3d_driver_type == D3D_DRIVER_TYPE_WARP => Err(err),
+            Err(err) => {
+                warn!(
+                    "surfman: Failed to open a hardware device ({:?}); falling back to WARP.",
+                    err
+                );
+                Device::new(&Adapter::new(D3D_DRIVER_TYPE_WARP, VendorPreference::None)?)
+            }
+        }
+    }
+
+    /// Opens the device for the given adapter, enabling and disabling the named ANGLE features.
+    ///
+    /// The feature names (e.g. `"disableProgramCaching"`) are passed to ANGLE via the
+    /// `EGL_ANGLE_feature_control` extension when the display is created. This is useful for
+    /// working around driver-specific bugs. If the extension is unavailable, the lists are
+    /// ignored with a warning.
+    #[allow(non_snake_case)]
+    pub fn new_with_features(
+        adapter: &Adapter,
+        enable: &[&str],
+        disable: &[&str],
+    ) -> Result<Device, Error> {
+        device_span!("new_with_features");
         let d3d_driver_type = adapter.d3d_driver_type;
         unsafe {
+            // Direct3D only accepts an explicit adapter with `D3D_DRIVER_TYPE_UNKNOWN`. Other
+            // driver types, such as WARP, choose their own.
+            let dxgi_adapter = if d3d_driver_type == D3D_DRIVER_TYPE_UNKNOWN {
+                adapter.dxgi_adapter.as_raw()
+            } else {
+                ptr::null_mut()
+            };
+
             let mut d3d11_device = ptr::null_mut();
             let mut d3d11_feature_level = 0;
             let mut d3d11_device_context = ptr::null_mut();
             let result = D3D11CreateDevice(
-                adapter.dxgi_adapter.as_raw(),
+                dxgi_adapter,
                 d3d_driver_type,
                 ptr::null_mut(),
                 0,
@@ -196,8 +439,29 @@ impl Device {
             );
             assert_ne!(egl_device, EGL_NO_DEVICE_EXT);
 
+            // These must stay alive until the display has been created.
+            let enabled_features = FeatureList::new(enable)?;
+            let disabled_features = FeatureList::new(disable)?;
+
+            let mut attribs = vec![];
+            if !enable.is_empty() || !disable.is_empty() {
+                if Device::egl_client_extensions().contains("EGL_ANGLE_feature_control") {
+                    attribs.extend_from_slice(&[
+                        EGL_FEATURE_OVERRIDES_ENABLED_ANGLE as EGLAttrib,
+                        enabled_features.as_ptr() as EGLAttrib,
+                        EGL_FEATURE_OVERRIDES_DISABLED_ANGLE as EGLAttrib,
+                        disabled_features.as_ptr() as EGLAttrib,
+                    ]);
+                } else {
+                    warn!(
+                        "surfman: `EGL_ANGLE_feature_control` is unavailable, so ANGLE feature \
+                         overrides will be ignored."
+                    );
+                }
+            }
+            attribs.extend_from_slice(&[egl::NONE as EGLAttrib, egl::NONE as EGLAttrib, 0, 0]);
+
             EGL_FUNCTIONS.with(|egl| {
-                let attribs = [egl::NONE as EGLAttrib, egl::NONE as EGLAttrib, 0, 0];
                 let egl_display = egl.GetPlatformDisplay(
                     EGL_PLATFORM_DEVICE_EXT,
                     egl_device as *mut c_void,
@@ -210,66 +474,162 @@ impl Device {
                 let result = egl.Initialize(egl_display, &mut major_version, &mut minor_version);
                 assert_ne!(result, egl::FALSE);
 
-                Ok(Device {
+                Ok(Device::from_display(
                     egl_display,
                     d3d11_device,
                     d3d_driver_type,
-                    display_is_owned: true,
-                })
+                    Some(Arc::new(OwnedDisplay(egl_display))),
+                    egl_extensions(egl_display),
+                    Arc::new(AtomicUsize::new(0)),
+                ))
             })
         }
     }
 
+    /// Opens the device for the given adapter with ANGLE's program binary cache turned off.
+    ///
+    /// ANGLE normally caches compiled programs and reuses them across runs, which can hide
+    /// shader changes during development. This enables the `disableProgramCaching` feature, so
+    /// every program is compiled from source. It is a development aid; `Device::new()` leaves
+    /// the cache on. Like `new_with_features()`, it has no effect without the
+    /// `EGL_ANGLE_feature_control` extension.
+    pub fn new_without_program_cache(adapter: &Adapter) -> Result<Device, Error> {
+        device_span!("new_without_program_cache");
+        Device::new_with_features(adapter, &[DISABLE_PROGRAM_CACHE_FEATURE], &[])
+    }
+
     pub(crate) fn from_native_device(native_device: NativeDevice) -> Result<Device, Error> {
         unsafe {
             (*native_device.d3d11_device).AddRef();
-            Ok(Device {
-                egl_display: native_device.egl_display,
-                d3d11_device: ComPtr::from_raw(native_device.d3d11_device),
-                d3d_driver_type: native_device.d3d_driver_type,
-                display_is_owned: false,
-            })
+            Ok(Device::from_display(
+                native_device.egl_display,
+                ComPtr::from_raw(native_device.d3d11_device),
+                native_device.d3d_driver_type,
+                None,
+                egl_extensions(native_device.egl_display),
+                Arc::new(AtomicUsize::new(0)),
+            ))
         }
     }
 
-    #[allow(non_snake_case)]
-    pub(crate) fn from_egl_display(egl_display: EGLDisplay) -> Result<Device, Error> {
-
-        let eglQueryDisplayAttribEXT = EGL_EXTENSION_FUNCTIONS.QueryDisplayAttribEXT.expect(
-            "Where's the `EGL_EXT_device_query` extension?"
-        );
-        let eglQueryDeviceAttribEXT = EGL_EXTENSION_FUNCTIONS.QueryDeviceAttribEXT.expect(
-            "Where's the `EGL_EXT_device_query` extension?"
-        );
-        let mut angle_device : EGLAttrib = 0;
-        let result = eglQueryDisplayAttribEXT(
+    // Wraps an initialized display and its Direct3D 11 device in a device with default settings.
+    // If `owned_display` is `None`, the display is never terminated by surfman. Devices opened on
+    // the same display through `shared_display()` share `live_context_count`.
+    fn from_display(
+        egl_display: EGLDisplay,
+        d3d11_device: ComPtr<ID3D11Device>,
+        d3d_driver_type: D3D_DRIVER_TYPE,
+        owned_display: Option<Arc<OwnedDisplay>>,
+        egl_display_extensions: HashSet<String>,
+        live_context_count: Arc<AtomicUsize>,
+    ) -> Device {
+        Device {
             egl_display,
-            EGL_DEVICE_EXT as EGLint,
-            &mut angle_device as *mut EGLAttrib
-        );
-        if result == egl::FALSE {
-            return Err(Error::DeviceOpenFailed);
+            d3d11_device,
+            d3d_driver_type,
+            owned_display,
+            surface_creation_retry_count: 0,
+            surface_bindings: RefCell::new(vec![]),
+            context_descriptor_cache: RefCell::new(HashMap::new()),
+            make_current_timeout: None,
+            present_mode: Cell::new(PresentMode::Copy),
+            egl_display_extensions,
+            context_lost_callbacks: RefCell::new(HashMap::new()),
+            live_context_count,
+        }
+    }
+
+    /// Returns a handle to this device's display that can be sent to another thread and opened
+    /// there with `SharedDisplay::create_device()`.
+    pub fn shared_display(&self) -> SharedDisplay {
+        SharedDisplay {
+            egl_display: self.egl_display,
+            d3d11_device: self.d3d11_device.clone(),
+            d3d_driver_type: self.d3d_driver_type,
+            owned_display: self.owned_display.clone(),
+            egl_display_extensions: self.egl_display_extensions.clone(),
+            live_context_count: self.live_context_count.clone(),
         }
-        let mut device : EGLAttrib = 0;
+    }
+
+    /// Opens another device on the same EGL display and Direct3D 11 device as this one.
+    ///
+    /// The display is not initialized again, and surfaces from either device can be used with the
+    /// other. The clone has the same settings as this device but none of its contexts or cached
+    /// state. Use `shared_display()` instead to open the clone on another thread.
+    pub fn create_thread_local_clone(&self) -> Device {
+        device_span!("create_thread_local_clone");
+        let mut device = self.shared_display().create_device();
+        device.surface_creation_retry_count = self.surface_creation_retry_count;
+        device.make_current_timeout = self.make_current_timeout;
+        device.present_mode.set(self.present_mode.get());
+        device
+    }
+
+    /// Wraps an existing, already-initialized ANGLE `EGLDisplay` and its `EGLDeviceEXT` in a
+    /// device.
+    ///
+    /// `eglInitialize()` is not called again, and the device does not take ownership of the
+    /// display, so dropping it won't terminate the display. It is the caller's responsibility to
+    /// keep the display alive as long as this `Device` remains alive. This function does,
+    /// however, call `AddRef` on the underlying Direct3D device.
+    #[allow(non_snake_case)]
+    pub unsafe fn from_egl_display(
+        egl_display: EGLDisplay,
+        egl_device: EGLDeviceEXT,
+    ) -> Result<Device, Error> {
+        device_span!("from_egl_display");
+        let eglQueryDeviceAttribEXT = EGL_EXTENSION_FUNCTIONS
+            .QueryDeviceAttribEXT
+            .expect("Where's the `EGL_EXT_device_query` extension?");
+        let mut device: EGLAttrib = 0;
         let result = eglQueryDeviceAttribEXT(
-            angle_device as EGLDeviceEXT,
+            egl_device,
             EGL_D3D11_DEVICE_ANGLE as EGLint,
-            &mut device as *mut EGLAttrib
+            &mut device as *mut EGLAttrib,
         );
         if result == egl::FALSE {
             return Err(Error::DeviceOpenFailed);
         }
         let d3d11_device = device as *mut ID3D11Device;
 
-        unsafe {
-            (*d3d11_device).AddRef();
-            Ok(Device {
-                egl_display: egl_display,
-                d3d11_device: ComPtr::from_raw(d3d11_device),
-                d3d_driver_type: D3D_DRIVER_TYPE_UNKNOWN,
-                display_is_owned: false,
-            })
+        (*d3d11_device).AddRef();
+        Ok(Device::from_display(
+            egl_display,
+            ComPtr::from_raw(d3d11_device),
+            D3D_DRIVER_TYPE_UNKNOWN,
+            None,
+            egl_extensions(egl_display),
+            Arc::new(AtomicUsize::new(0)),
+        ))
+    }
+
+    // Returns the `EGLDeviceEXT` that the given ANGLE display was created with.
+    #[allow(non_snake_case)]
+    pub(crate) fn egl_device_from_egl_display(
+        egl_display: EGLDisplay,
+    ) -> Result<EGLDeviceEXT, Error> {
+        let eglQueryDisplayAttribEXT = EGL_EXTENSION_FUNCTIONS
+            .QueryDisplayAttribEXT
+            .expect("Where's the `EGL_EXT_device_query` extension?");
+        let mut angle_device: EGLAttrib = 0;
+        let result = eglQueryDisplayAttribEXT(
+            egl_display,
+            EGL_DEVICE_EXT as EGLint,
+            &mut angle_device as *mut EGLAttrib,
+        );
+        if result == egl::FALSE {
+            return Err(Error::DeviceOpenFailed);
         }
+        Ok(angle_device as EGLDeviceEXT)
+    }
+
+    /// Returns which surfaces are bound to which contexts created with this device.
+    ///
+    /// This is purely for debugging, e.g. to track down leaked surfaces or `SurfaceAlreadyBound`
+    /// errors.
+    pub fn surface_bindings(&self) -> Vec<(ContextID, SurfaceID)> {
+        self.surface_bindings.borrow().clone()
     }
 
     /// Returns the display server connection that this device was created with.
@@ -314,22 +674,165 @@ impl Device {
     }
 
     /// Returns the OpenGL API flavor that this device supports (OpenGL or OpenGL ES).
+    ///
+    /// ANGLE only implements OpenGL ES, so every context created on this device is an OpenGL ES
+    /// context and `ContextAttributes::version` is always an OpenGL ES version. There is no
+    /// desktop OpenGL path to fall back from.
     #[inline]
     pub fn gl_api(&self) -> GLApi {
         GLApi::GLES
     }
+
+    /// Sets how many times surface creation is retried when the driver runs out of memory.
+    ///
+    /// Before each retry, pending GL work is finished and the device backs off briefly, giving
+    /// the driver a chance to reclaim memory from retired resources. If every attempt fails, an
+    /// `OutOfSurfaceMemory` error is returned. The default is 0, meaning no retries.
+    #[inline]
+    pub fn set_surface_creation_retry_count(&mut self, retry_count: u32) {
+        self.surface_creation_retry_count = retry_count;
+    }
+
+    /// Drops the context descriptors that this device has cached, releasing their memory.
+    ///
+    /// This is useful for long-lived applications that want to give memory back, for instance
+    /// when they move to the background. It can be called at any time: existing contexts,
+    /// surfaces, and descriptors are unaffected, and later descriptor requests simply choose
+    /// their configs again.
+    pub fn purge_caches(&self) {
+        device_span!("purge_caches");
+        *self.context_descriptor_cache.borrow_mut() = HashMap::new();
+    }
+
+    /// Returns the EGL extensions that this device's display supports.
+    ///
+    /// Check this before calling methods that depend on an extension, such as
+    /// `create_surface_from_client_buffer()`, which needs `EGL_ANGLE_d3d_texture_client_buffer`.
+    #[inline]
+    pub fn egl_display_extensions(&self) -> &HashSet<String> {
+        &self.egl_display_extensions
+    }
+
+    /// Returns true if contexts on this device can be made current without any surface bound.
+    ///
+    /// This is the case when the display supports `EGL_KHR_surfaceless_context`. Without it,
+    /// surfman binds a dummy pbuffer to contexts that have no surface, which window-only configs
+    /// can't provide.
+    #[inline]
+    pub fn supports_surfaceless_context(&self) -> bool {
+        self.egl_display_extensions
+            .contains("EGL_KHR_surfaceless_context")
+    }
+
+    /// Returns the EGL client extensions, which are independent of any display.
+    pub fn egl_client_extensions() -> HashSet<String> {
+        unsafe { egl_extensions(egl::NO_DISPLAY) }
+    }
+
+    /// Sets a deadline for the `eglMakeCurrent()` call that context creation makes to gather
+    /// information about the new context.
+    ///
+    /// Some drivers occasionally hang in `eglMakeCurrent()`. With a timeout set, that call runs
+    /// on a helper thread, and if it doesn't finish in time, context creation returns a
+    /// `MakeCurrentTimedOut` error so that the application can fall back to another device
+    /// instead of freezing. The stuck thread and the new context are leaked.
+    /// `make_context_current()` isn't covered, because a context can only be made current on the
+    /// calling thread itself. The default is `None`, meaning no timeout.
+    #[inline]
+    pub fn set_make_current_timeout(&mut self, timeout: Option<Duration>) {
+        self.make_current_timeout = timeout;
+    }
+
+    /// Chooses how window surfaces created from now on present their contents.
+    ///
+    /// `Copy` is the default and keeps ANGLE's usual behavior, where `eglSwapBuffers()` blits the
+    /// back buffer into the window. The flip modes create the swap chain for DirectComposition,
+    /// which makes ANGLE use a flip-model swap chain; `FlipDiscard` additionally lets the back
+    /// buffer contents be discarded after each present. The flip modes require the
+    /// `EGL_ANGLE_direct_composition` extension, or a `RequiredExtensionUnavailable` error is
+    /// returned. Existing surfaces are unaffected.
+    pub fn set_present_mode(&self, mode: PresentMode) -> Result<(), Error> {
+        if mode != PresentMode::Copy
+            && !self
+                .egl_display_extensions
+                .contains("EGL_ANGLE_direct_composition")
+        {
+            return Err(Error::RequiredExtensionUnavailable);
+        }
+        self.present_mode.set(mode);
+        Ok(())
+    }
+
+    /// Returns the present mode that new window surfaces will be created with.
+    #[inline]
+    pub fn present_mode(&self) -> PresentMode {
+        self.present_mode.get()
+    }
+
+    /// Returns the newest OpenGL ES version that contexts on this device can be expected to
+    /// support.
+    ///
+    /// ANGLE's OpenGL ES support is limited by the Direct3D 11 feature level of the adapter, so
+    /// requesting anything newer than this will likely fail. The estimate is conservative.
+    pub fn max_gles_version(&self) -> GLVersion {
+        unsafe { max_gles_version_for_feature_level(self.d3d11_device.GetFeatureLevel()) }
+    }
+
+    /// Returns the largest width or height that a generic surface can have on this device.
+    ///
+    /// This is the maximum Direct3D 11 texture dimension for the device's feature level. Creating
+    /// a larger surface fails with a `SurfaceTooLarge` error.
+    pub fn max_surface_dimension(&self) -> i32 {
+        unsafe { max_texture_dimension_for_feature_level(self.d3d11_device.GetFeatureLevel()) }
+    }
 }
 
-impl Drop for Device {
+// Maps a Direct3D 11 feature level to the newest OpenGL ES version that ANGLE can reliably
+// provide on it.
+pub(crate) fn max_gles_version_for_feature_level(feature_level: D3D_FEATURE_LEVEL) -> GLVersion {
+    if feature_level >= D3D_FEATURE_LEVEL_11_1 {
+        GLVersion::GLES_3_1
+    } else if feature_level >= D3D_FEATURE_LEVEL_10_0 {
+        GLVersion::GLES_3_0
+    } else {
+        GLVersion::GLES_2_0
+    }
+}
+
+// Maps a Direct3D 11 feature level to its maximum 2D texture width and height.
+pub(crate) fn max_texture_dimension_for_feature_level(feature_level: D3D_FEATURE_LEVEL) -> i32 {
+    if feature_level >= D3D_FEATURE_LEVEL_11_0 {
+        16384
+    } else if feature_level >= D3D_FEATURE_LEVEL_10_0 {
+        8192
+    } else if feature_level >= D3D_FEATURE_LEVEL_9_3 {
+        4096
+    } else {
+        2048
+    }
+}
+
+impl SharedDisplay {
+    /// Opens a device on this display for the current thread.
+    pub fn create_device(self) -> Device {
+        Device::from_display(
+            self.egl_display,
+            self.d3d11_device,
+            self.d3d_driver_type,
+            self.owned_display,
+            self.egl_display_extensions,
+            self.live_context_count,
+        )
+    }
+}
+
+impl Drop for OwnedDisplay {
     fn drop(&mut self) {
         unsafe {
-            if self.display_is_owned {
-                EGL_FUNCTIONS.with(|egl| {
-                    let result = egl.Terminate(self.egl_display);
-                    assert_ne!(result, egl::FALSE);
-                    self.egl_display = egl::NO_DISPLAY;
-                })
-            }
+            EGL_FUNCTIONS.with(|egl| {
+                let result = egl.Terminate(self.0);
+                assert_ne!(result, egl::FALSE);
+            })
         }
     }
 }
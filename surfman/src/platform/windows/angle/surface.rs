@@ -2,38 +2,71 @@
 //
 //! Surface management for Direct3D 11 on Windows using the ANGLE library as a frontend.
 
-use super::context::{Context, ContextDescriptor, GL_FUNCTIONS};
+use super::context::{Context, ContextDescriptor, PreviousBinding};
 use super::device::Device;
+use crate::array_surface::ArraySurface;
 use crate::context::ContextID;
 use crate::egl::types::EGLNativeWindowType;
-use crate::egl::types::EGLSurface;
+use crate::egl::types::{EGLAttrib, EGLClientBuffer, EGLSurface, EGLenum};
 use crate::egl::{self, EGLint};
 use crate::gl;
-use crate::gl::types::{GLenum, GLint, GLuint};
+use crate::gl::types::{GLenum, GLint, GLsizei, GLuint};
+use crate::gl_utils::{self, GLStateGuard};
+use crate::platform::generic::egl::context::{self, CurrentContextGuard};
 use crate::platform::generic::egl::device::EGL_FUNCTIONS;
-use crate::platform::generic::egl::error::ToWindowingApiError;
+use crate::platform::generic::egl::error::{egl_error, surface_creation_error};
 use crate::platform::generic::egl::ffi::EGL_D3D_TEXTURE_2D_SHARE_HANDLE_ANGLE;
 use crate::platform::generic::egl::ffi::EGL_D3D_TEXTURE_ANGLE;
+use crate::platform::generic::egl::ffi::EGL_DIRECT_COMPOSITION_ANGLE;
 use crate::platform::generic::egl::ffi::EGL_DXGI_KEYED_MUTEX_ANGLE;
 use crate::platform::generic::egl::ffi::EGL_EXTENSION_FUNCTIONS;
-use crate::{Error, SurfaceAccess, SurfaceID, SurfaceInfo, SurfaceType};
+#[cfg(feature = "sm-async")]
+use crate::platform::generic::egl::future::EGLFenceFuture;
+use crate::surface::Framebuffer;
+use crate::tiled_surface::TiledSurface;
+use crate::WindowingApiError;
+use crate::{AlphaType, InvalidateFlags, SurfaceUsage};
+use crate::{ContextAttributeFlags, Error, Gl, SurfaceAccess, SurfaceID, SurfaceInfo, SurfaceType};
 
-use euclid::default::Size2D;
+use euclid::default::{Point2D, Rect, Size2D};
 use std::fmt::{self, Debug, Formatter};
+#[cfg(feature = "sm-async")]
+use std::future::Future;
 use std::marker::PhantomData;
+use std::mem;
 use std::os::raw::c_void;
 use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
-use winapi::shared::dxgi::IDXGIKeyedMutex;
-use winapi::shared::winerror::S_OK;
+use std::time::{Duration, Instant};
+use winapi::shared::dxgi::{IDXGIDevice, IDXGIKeyedMutex, DXGI_SWAP_EFFECT_FLIP_DISCARD};
+use winapi::shared::dxgi1_2::{IDXGIFactory2, IDXGISwapChain1, DXGI_SWAP_CHAIN_DESC1};
+use winapi::shared::dxgi1_2::{DXGI_ALPHA_MODE_IGNORE, DXGI_SCALING_STRETCH};
+use winapi::shared::dxgiformat::{DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM};
+use winapi::shared::dxgitype::{DXGI_SAMPLE_DESC, DXGI_USAGE_RENDER_TARGET_OUTPUT};
+use winapi::shared::minwindef::FALSE;
+use winapi::shared::windef::HWND;
+use winapi::shared::winerror::{self, DXGI_STATUS_OCCLUDED, S_OK};
 use winapi::um::d3d11;
+use winapi::um::d3d11_1;
 use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+use winapi::um::unknwnbase::IUnknown;
 use winapi::um::winbase::INFINITE;
-use winapi::um::winnt::HANDLE;
+use winapi::um::winnt::{HANDLE, HRESULT};
+use winapi::Interface;
 use wio::com::ComPtr;
 
 const SURFACE_GL_TEXTURE_TARGET: GLenum = gl::TEXTURE_2D;
 
+// The source of surface IDs. These are never reused, so they remain unique even after the
+// surfaces they name are destroyed.
+static NEXT_SURFACE_ID: AtomicUsize = AtomicUsize::new(1);
+
+// The initial delay before retrying a surface allocation that failed with `EGL_BAD_ALLOC`. This
+// doubles with each subsequent attempt.
+const SURFACE_CREATION_RETRY_BACKOFF_MS: u64 = 4;
+
 /// Represents a hardware buffer of pixels that can be rendered to via the CPU or GPU and either
 /// displayed in a native widget or bound to a texture for reading.
 ///
@@ -52,9 +85,14 @@ const SURFACE_GL_TEXTURE_TARGET: GLenum = gl::TEXTURE_2D;
 /// Surfaces must be destroyed with the `destroy_surface()` method, or a panic will occur.
 pub struct Surface {
     pub(crate) egl_surface: EGLSurface,
+    pub(crate) id: SurfaceID,
     pub(crate) size: Size2D<i32>,
     pub(crate) context_id: ContextID,
     pub(crate) context_descriptor: ContextDescriptor,
+    pub(crate) usage: SurfaceUsage,
+    pub(crate) access: SurfaceAccess,
+    pub(crate) alpha_type: AlphaType,
+    pub(crate) mipmapped: bool,
     pub(crate) win32_objects: Win32Objects,
 }
 
@@ -72,6 +110,8 @@ pub struct SurfaceTexture {
     pub(crate) local_egl_surface: EGLSurface,
     pub(crate) local_keyed_mutex: Option<ComPtr<IDXGIKeyedMutex>>,
     pub(crate) gl_texture: GLuint,
+    pub(crate) context_id: ContextID,
+    pub(crate) region: Rect<i32>,
     pub(crate) phantom: PhantomData<*const ()>,
 }
 
@@ -105,6 +145,11 @@ pub(crate) enum Win32Objects {
         // We keep a reference to the ComPtr in order to keep its refcount from becoming zero
         texture: Option<ComPtr<d3d11::ID3D11Texture2D>>,
     },
+    SwapChain {
+        swap_chain: ComPtr<IDXGISwapChain1>,
+        // The back buffer that the EGL surface wraps, kept alive for as long as the surface is.
+        back_buffer: ComPtr<d3d11::ID3D11Texture2D>,
+    },
 }
 
 pub(crate) enum Synchronization {
@@ -113,6 +158,127 @@ pub(crate) enum Synchronization {
     None,
 }
 
+/// Timing information about a single present.
+///
+/// More fields may be added in the future as better timing sources become available.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct PresentStats {
+    /// The wall-clock time spent in `eglSwapBuffers`.
+    pub swap_duration: Duration,
+}
+
+/// How a window surface's back buffer reaches the screen. See `Device::set_present_mode()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentMode {
+    /// The back buffer is copied into the window on each present. This is the default.
+    Copy,
+    /// The back buffer is flipped to the screen, and its contents are preserved across presents.
+    Flip,
+    /// The back buffer is flipped to the screen, and its contents are undefined after each
+    /// present.
+    FlipDiscard,
+}
+
+/// Whether a present reached the screen. See `Device::present_surface_reporting_occlusion()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentResult {
+    /// The frame was presented.
+    Presented,
+    /// The window is occluded, so the frame was discarded.
+    Occluded,
+}
+
+/// How `Device::blit_surface_to_window()` filters the source surface when it's scaled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlitFilter {
+    /// Each destination pixel takes the value of the nearest source pixel.
+    Nearest,
+    /// Destination pixels are linearly interpolated from the nearest source pixels.
+    Linear,
+}
+
+/// An image owned by another graphics API that `Device::import_external_surface()` can wrap in
+/// a surface.
+#[derive(Clone, Copy, Debug)]
+pub enum ExternalSurfaceHandle {
+    /// A raw `ID3D11Texture2D` pointer to a texture created on this device's Direct3D device.
+    D3D11Texture(*mut c_void),
+    /// An NT handle to a shared Direct3D 11 texture, as returned by
+    /// `IDXGIResource1::CreateSharedHandle()`.
+    SharedNTHandle(HANDLE),
+}
+
+/// Settings for `Device::create_surface_with_options()` beyond those that every backend supports.
+///
+/// The defaults produce the same surface as `Device::create_surface()`, so only the settings that
+/// differ need to be given, with `..Default::default()` filling in the rest.
+#[derive(Clone, Copy, Debug)]
+pub struct AngleSurfaceOptions<'a> {
+    /// How a generic surface will be used.
+    ///
+    /// Texture-only surfaces are allocated without depth or stencil buffers and can't be
+    /// attached to a context. Widget surfaces are always render targets, so this is ignored for
+    /// them. The default is `SurfaceUsage::RenderTarget`.
+    pub usage: SurfaceUsage,
+
+    /// An RGBA color to clear the surface to before it is returned.
+    ///
+    /// The contents of a newly created surface are otherwise undefined, which can show up as
+    /// garbage on the first present, or when a surface whose real contents are produced later is
+    /// sampled early. The surface is cleared with `Device::clear_surface()`. This is ignored if
+    /// `data` is given. The default is `None`.
+    pub clear_color: Option<[f32; 4]>,
+
+    /// Whether a generic surface has a full chain of mipmap levels.
+    ///
+    /// If true, the surface is allocated with `EGL_MIPMAP_TEXTURE`, and surface textures wrapping
+    /// it use a mipmapping minification filter. The levels below the base are undefined until
+    /// `Device::generate_surface_mipmaps()` is called after rendering. Widget surfaces can't have
+    /// mipmaps, so this is ignored for them. The default is false.
+    pub generate_mipmaps: bool,
+
+    /// Whether to accept a smaller generic surface than requested rather than failing.
+    ///
+    /// If true, a size beyond `Device::max_surface_dimension()` is clamped to it, and the pbuffer
+    /// is allocated with `EGL_LARGEST_PBUFFER` so that EGL returns the largest surface it can
+    /// instead of running out of memory. The size actually allocated is queried with
+    /// `eglQuerySurface()` and reported by `Device::surface_info()`. Widget surfaces are sized by
+    /// their widget, so this is ignored for them. The default is false.
+    pub largest_pbuffer: bool,
+
+    /// Whether the surface's contents will have premultiplied or straight alpha.
+    ///
+    /// The alpha type is reported by `Device::surface_alpha_type()`, and
+    /// `Device::read_surface_pixels()` converts from it as needed. The default is
+    /// `AlphaType::Premultiplied`.
+    pub alpha_type: AlphaType,
+
+    /// RGBA pixels to fill a generic surface with.
+    ///
+    /// The pixels are 8 bits per channel, with rows bottom to top, as
+    /// `Device::read_surface_pixels()` returns them. They are uploaded with `glTexSubImage2D()`
+    /// while the surface is bound to a texture in the given context. If the data isn't exactly
+    /// `width * height * 4` bytes long for the size allocated, an `InvalidSurfaceData` error is
+    /// returned; giving data for a widget surface returns a `WidgetAttached` error. The default
+    /// is `None`.
+    pub data: Option<&'a [u8]>,
+}
+
+impl<'a> Default for AngleSurfaceOptions<'a> {
+    #[inline]
+    fn default() -> AngleSurfaceOptions<'a> {
+        AngleSurfaceOptions {
+            usage: SurfaceUsage::RenderTarget,
+            clear_color: None,
+            generate_mipmaps: false,
+            largest_pbuffer: false,
+            alpha_type: AlphaType::Premultiplied,
+            data: None,
+        }
+    }
+}
+
 /// Wraps an `EGLNativeWindowType`
 #[repr(C)]
 pub struct NativeWidget {
@@ -122,6 +288,9 @@ pub struct NativeWidget {
     pub egl_native_window: EGLNativeWindowType,
 }
 
+// The most buffers that a DXGI swap chain can have (`DXGI_MAX_SWAP_CHAIN_BUFFERS`).
+const MAX_SWAP_CHAIN_BUFFER_COUNT: u32 = 16;
+
 impl Device {
     /// Creates either a generic or a widget surface, depending on the supplied surface type.
     ///
@@ -130,14 +299,128 @@ impl Device {
     pub fn create_surface(
         &mut self,
         context: &Context,
-        _: SurfaceAccess,
+        surface_access: SurfaceAccess,
+        surface_type: SurfaceType<NativeWidget>,
+    ) -> Result<Surface, Error> {
+        device_span!(
+            "create_surface",
+            context = self.context_id(context).0,
+            size = ?match surface_type {
+                SurfaceType::Generic { size } => Some(size),
+                SurfaceType::Widget { .. } => None,
+            },
+        );
+        self.create_surface_with_options(
+            context,
+            surface_access,
+            surface_type,
+            &AngleSurfaceOptions::default(),
+        )
+    }
+
+    /// Creates either a generic or a widget surface with the given options.
+    ///
+    /// With `AngleSurfaceOptions::default()`, this is equivalent to `create_surface()`. If the
+    /// surface can't be cleared or filled as requested, it is destroyed and the error is
+    /// returned.
+    pub fn create_surface_with_options(
+        &mut self,
+        context: &Context,
+        surface_access: SurfaceAccess,
         surface_type: SurfaceType<NativeWidget>,
+        options: &AngleSurfaceOptions,
     ) -> Result<Surface, Error> {
-        match surface_type {
-            SurfaceType::Generic { ref size } => self.create_pbuffer_surface(context, size, None),
+        device_span!(
+            "create_surface_with_options",
+            context = self.context_id(context).0
+        );
+        let mut surface = match surface_type {
+            SurfaceType::Generic { mut size } => {
+                if let Some(data) = options.data {
+                    if size.width <= 0 || size.height <= 0 || data.len() != pixel_data_len(size) {
+                        return Err(Error::InvalidSurfaceData);
+                    }
+                }
+                if options.largest_pbuffer {
+                    let max = self.max_surface_dimension();
+                    size = size.min(Size2D::new(max, max));
+                }
+                let mut surface = self.create_pbuffer_surface(
+                    context,
+                    &size,
+                    None,
+                    options.usage,
+                    options.generate_mipmaps,
+                    options.largest_pbuffer,
+                )?;
+                surface.access = surface_access;
+                surface
+            }
             SurfaceType::Widget { ref native_widget } => {
-                self.create_window_surface(context, native_widget)
+                if options.data.is_some() {
+                    return Err(Error::WidgetAttached);
+                }
+                self.create_window_surface(context, native_widget)?
             }
+        };
+        surface.alpha_type = options.alpha_type;
+
+        let result = match (options.data, options.clear_color) {
+            // A surface shrunk by `largest_pbuffer` no longer fits the data.
+            (Some(data), _) if data.len() != pixel_data_len(surface.size) => {
+                Err(Error::InvalidSurfaceData)
+            }
+            (Some(data), _) => self.upload_surface_pixels(context, &surface, data),
+            (None, Some(color)) => self.clear_surface(context, &surface, color),
+            (None, None) => Ok(()),
+        };
+        if let Err(err) = result {
+            // The surface was never bound, so it can't be current.
+            EGL_FUNCTIONS.with(|egl| unsafe {
+                egl.DestroySurface(self.egl_display, surface.egl_surface);
+            });
+            surface.egl_surface = egl::NO_SURFACE;
+            return Err(err);
+        }
+        Ok(surface)
+    }
+
+    // Uploads RGBA pixels covering the whole of a pbuffer surface that isn't bound to a context.
+    fn upload_surface_pixels(
+        &self,
+        context: &Context,
+        surface: &Surface,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let synchronization = match surface.win32_objects {
+            Win32Objects::Pbuffer {
+                ref synchronization,
+                ..
+            } => synchronization,
+            Win32Objects::Window | Win32Objects::SwapChain { .. } => {
+                return Err(Error::WidgetAttached)
+            }
+        };
+
+        let _guard = self.temporarily_make_context_current(context)?;
+        unsafe {
+            self.modify_surface_texture(context, surface, synchronization, |gl| {
+                let mut old_unpack_alignment = 0;
+                gl.GetIntegerv(gl::UNPACK_ALIGNMENT, &mut old_unpack_alignment);
+                gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+                gl.TexSubImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    0,
+                    0,
+                    surface.size.width,
+                    surface.size.height,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    data.as_ptr() as *const c_void,
+                );
+                gl.PixelStorei(gl::UNPACK_ALIGNMENT, old_unpack_alignment);
+            })
         }
     }
 
@@ -147,12 +430,36 @@ impl Device {
         context: &Context,
         size: &Size2D<i32>,
         texture: Option<ComPtr<d3d11::ID3D11Texture2D>>,
+        usage: SurfaceUsage,
+        mipmapped: bool,
+        largest: bool,
     ) -> Result<Surface, Error> {
+        // Catch this up front, since EGL only reports a generic allocation failure.
+        let max = self.max_surface_dimension();
+        if size.width > max || size.height > max {
+            return Err(Error::SurfaceTooLarge {
+                requested: *size,
+                max,
+            });
+        }
+
         let context_descriptor = self.context_descriptor(context);
+        let context_descriptor = match usage {
+            SurfaceUsage::RenderTarget => context_descriptor,
+            SurfaceUsage::TextureOnly => {
+                // Nothing will ever render to this surface, so pick a config without depth or
+                // stencil buffers.
+                let mut attributes = self.context_descriptor_attributes(&context_descriptor);
+                attributes
+                    .flags
+                    .remove(ContextAttributeFlags::DEPTH | ContextAttributeFlags::STENCIL);
+                self.create_context_descriptor(&attributes)?
+            }
+        };
         let egl_config = self.context_descriptor_to_egl_config(&context_descriptor);
 
         unsafe {
-            let attributes = [
+            let mut attributes = [
                 egl::WIDTH as EGLint,
                 size.width as EGLint,
                 egl::HEIGHT as EGLint,
@@ -161,29 +468,80 @@ impl Device {
                 egl::TEXTURE_RGBA as EGLint,
                 egl::TEXTURE_TARGET as EGLint,
                 egl::TEXTURE_2D as EGLint,
+                egl::MIPMAP_TEXTURE as EGLint,
+                mipmapped as EGLint,
                 egl::NONE as EGLint,
                 0,
                 0,
                 0,
             ];
+            // `EGL_LARGEST_PBUFFER` is only valid for surfaces that EGL allocates itself.
+            if largest && texture.is_none() {
+                attributes[10] = egl::LARGEST_PBUFFER as EGLint;
+                attributes[11] = egl::TRUE as EGLint;
+                attributes[12] = egl::NONE as EGLint;
+            }
 
             EGL_FUNCTIONS.with(|egl| {
-                let egl_surface = if let Some(ref texture) = texture {
-                    let surface = egl.CreatePbufferFromClientBuffer(
+                let mut attempt = 0;
+                let egl_surface = loop {
+                    let egl_surface = if let Some(ref texture) = texture {
+                        egl.CreatePbufferFromClientBuffer(
+                            self.egl_display,
+                            EGL_D3D_TEXTURE_ANGLE,
+                            texture.as_raw() as *const _,
+                            egl_config,
+                            attributes.as_ptr(),
+                        )
+                    } else {
+                        egl.CreatePbufferSurface(self.egl_display, egl_config, attributes.as_ptr())
+                    };
+                    if egl_surface != egl::NO_SURFACE {
+                        break egl_surface;
+                    }
+
+                    let err = egl.GetError();
+                    if err as EGLenum != egl::BAD_ALLOC
+                        || attempt >= self.surface_creation_retry_count
+                    {
+                        return Err(surface_creation_error(err));
+                    }
+
+                    // Let the driver reclaim memory from retired resources, then back off.
+                    if let Ok(_guard) = self.temporarily_make_context_current(context) {
+                        context.gl().Finish();
+                    }
+                    thread::sleep(Duration::from_millis(
+                        SURFACE_CREATION_RETRY_BACKOFF_MS << attempt.min(6),
+                    ));
+                    attempt += 1;
+                };
+
+                // With `EGL_LARGEST_PBUFFER`, the surface may be smaller than requested.
+                let mut size = *size;
+                if largest {
+                    egl.QuerySurface(
                         self.egl_display,
-                        EGL_D3D_TEXTURE_ANGLE,
-                        texture.as_raw() as *const _,
-                        egl_config,
-                        attributes.as_ptr(),
+                        egl_surface,
+                        egl::WIDTH as EGLint,
+                        &mut size.width,
                     );
-                    assert_ne!(surface, egl::NO_SURFACE);
-                    surface
-                } else {
-                    let surface =
-                        egl.CreatePbufferSurface(self.egl_display, egl_config, attributes.as_ptr());
-                    assert_ne!(surface, egl::NO_SURFACE);
-                    surface
-                };
+                    egl.QuerySurface(
+                        self.egl_display,
+                        egl_surface,
+                        egl::HEIGHT as EGLint,
+                        &mut size.height,
+                    );
+                }
+
+                if usage == SurfaceUsage::RenderTarget {
+                    if let Err(err) =
+                        self.check_pbuffer_framebuffer(context, &context_descriptor, egl_surface)
+                    {
+                        egl.DestroySurface(self.egl_display, egl_surface);
+                        return Err(err);
+                    }
+                }
 
                 let eglQuerySurfacePointerANGLE =
                     EGL_EXTENSION_FUNCTIONS.QuerySurfacePointerANGLE.expect(
@@ -222,9 +580,14 @@ impl Device {
 
                 Ok(Surface {
                     egl_surface,
-                    size: *size,
+                    id: next_surface_id(),
+                    size,
                     context_id: context.id,
                     context_descriptor,
+                    usage,
+                    access: SurfaceAccess::GPUOnly,
+                    alpha_type: AlphaType::Premultiplied,
+                    mipmapped,
                     win32_objects: Win32Objects::Pbuffer {
                         share_handle,
                         synchronization,
@@ -235,6 +598,75 @@ impl Device {
         }
     }
 
+    // Some ANGLE configs can't give pbuffers the depth or stencil buffers they advertise. Check
+    // that the surface's framebuffer is complete and has every buffer the descriptor asked for.
+    unsafe fn check_pbuffer_framebuffer(
+        &self,
+        context: &Context,
+        context_descriptor: &ContextDescriptor,
+        egl_surface: EGLSurface,
+    ) -> Result<(), Error> {
+        let flags = self.context_descriptor_attributes(context_descriptor).flags;
+        EGL_FUNCTIONS.with(|egl| {
+            let _guard = CurrentContextGuard::new();
+            let result = egl.MakeCurrent(
+                self.egl_display,
+                egl_surface,
+                egl_surface,
+                context.egl_context,
+            );
+            if result == egl::FALSE {
+                let err = egl.GetError();
+                return Err(context::make_current_error(
+                    self.egl_display,
+                    egl_surface,
+                    context.egl_context,
+                    err,
+                ));
+            }
+
+            let gl = context.gl();
+            let _state_guard = GLStateGuard::new(gl);
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+            let mut status = gl.CheckFramebufferStatus(gl::FRAMEBUFFER);
+            if status == gl::FRAMEBUFFER_COMPLETE {
+                let requested = [
+                    (
+                        ContextAttributeFlags::DEPTH,
+                        gl::DEPTH,
+                        gl::FRAMEBUFFER_ATTACHMENT_DEPTH_SIZE,
+                    ),
+                    (
+                        ContextAttributeFlags::STENCIL,
+                        gl::STENCIL,
+                        gl::FRAMEBUFFER_ATTACHMENT_STENCIL_SIZE,
+                    ),
+                ];
+                for &(flag, attachment, pname) in &requested {
+                    if !flags.contains(flag) {
+                        continue;
+                    }
+                    let mut size = 0;
+                    gl.GetFramebufferAttachmentParameteriv(
+                        gl::FRAMEBUFFER,
+                        attachment,
+                        pname,
+                        &mut size,
+                    );
+                    if size == 0 {
+                        status = gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT;
+                    }
+                }
+            }
+
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(Error::SurfaceFramebufferIncomplete(status));
+            }
+            Ok(())
+        })
+    }
+
     /// Given a D3D11 texture, create a surface that wraps that texture. This method is unsafe
     /// in that the resulting surface is only valid on the current thread.
     pub unsafe fn create_surface_from_texture(
@@ -243,7 +675,133 @@ impl Device {
         size: &Size2D<i32>,
         texture: ComPtr<d3d11::ID3D11Texture2D>,
     ) -> Result<Surface, Error> {
-        self.create_pbuffer_surface(context, size, Some(texture))
+        device_span!(
+            "create_surface_from_texture",
+            context = self.context_id(context).0
+        );
+        self.create_pbuffer_surface(
+            context,
+            size,
+            Some(texture),
+            SurfaceUsage::RenderTarget,
+            false,
+            false,
+        )
+    }
+
+    /// Wraps a live Direct3D 11 texture, given as a raw `ID3D11Texture2D` pointer, in a surface
+    /// via `eglCreatePbufferFromClientBuffer()` with `EGL_D3D_TEXTURE_ANGLE`.
+    ///
+    /// This is the zero-copy path for Direct3D interop. Unlike surfaces imported from share
+    /// handles, it renders directly into the given texture. The texture must be a single-sampled,
+    /// single-mip 2D texture in a format compatible with the context's config (e.g.
+    /// `DXGI_FORMAT_R8G8B8A8_UNORM` or `DXGI_FORMAT_B8G8R8A8_UNORM` for an RGBA8 config) and must
+    /// have the given size. If `config_matches` is false, surfman checks this against the
+    /// texture's description first and returns an `IncompatibleSurface` error on mismatch; pass
+    /// true to skip the check when the caller already guarantees it.
+    ///
+    /// This method is unsafe because `buffer` must point to a valid `ID3D11Texture2D` created on
+    /// this device's Direct3D device. The surface holds a reference to the texture, and it is
+    /// only valid on the current thread.
+    pub unsafe fn create_surface_from_client_buffer(
+        &mut self,
+        context: &Context,
+        buffer: *mut c_void,
+        config_matches: bool,
+        size: Size2D<i32>,
+    ) -> Result<Surface, Error> {
+        device_span!(
+            "create_surface_from_client_buffer",
+            context = self.context_id(context).0
+        );
+        if !self
+            .egl_display_extensions
+            .contains("EGL_ANGLE_d3d_texture_client_buffer")
+        {
+            return Err(Error::RequiredExtensionUnavailable);
+        }
+        if buffer.is_null() {
+            return Err(Error::IncompatibleSurface);
+        }
+        let texture = buffer as *mut d3d11::ID3D11Texture2D;
+        (*texture).AddRef();
+        let texture = ComPtr::from_raw(texture);
+
+        if !config_matches {
+            let mut desc = mem::zeroed::<d3d11::D3D11_TEXTURE2D_DESC>();
+            texture.GetDesc(&mut desc);
+            let format_is_compatible = match desc.Format {
+                DXGI_FORMAT_R8G8B8A8_UNORM | DXGI_FORMAT_B8G8R8A8_UNORM => true,
+                _ => false,
+            };
+            if !format_is_compatible
+                || desc.Width != size.width as u32
+                || desc.Height != size.height as u32
+                || desc.MipLevels != 1
+                || desc.ArraySize != 1
+                || desc.SampleDesc.Count != 1
+            {
+                return Err(Error::IncompatibleSurface);
+            }
+        }
+
+        self.create_pbuffer_surface(
+            context,
+            &size,
+            Some(texture),
+            SurfaceUsage::RenderTarget,
+            false,
+            false,
+        )
+    }
+
+    /// Wraps an image owned by another graphics API in a surface.
+    ///
+    /// This is a single entry point for the interop paths: Direct3D 11 textures are wrapped
+    /// directly as with `create_surface_from_client_buffer()`, and shared NT handles are first
+    /// opened on this device's Direct3D device with `ID3D11Device1::OpenSharedResource1()`. In
+    /// both cases the texture's description is checked against the given size and the context's
+    /// config, and an `IncompatibleSurface` error is returned on mismatch.
+    ///
+    /// This method is unsafe because the handle must be valid. The surface holds a reference to
+    /// the underlying texture, and it is only valid on the current thread.
+    pub unsafe fn import_external_surface(
+        &mut self,
+        context: &Context,
+        handle: ExternalSurfaceHandle,
+        size: Size2D<i32>,
+    ) -> Result<Surface, Error> {
+        device_span!(
+            "import_external_surface",
+            context = self.context_id(context).0
+        );
+        match handle {
+            ExternalSurfaceHandle::D3D11Texture(texture) => {
+                self.create_surface_from_client_buffer(context, texture, false, size)
+            }
+            ExternalSurfaceHandle::SharedNTHandle(share_handle) => {
+                let d3d11_device = match self.d3d11_device.cast::<d3d11_1::ID3D11Device1>() {
+                    Ok(d3d11_device) => d3d11_device,
+                    Err(_) => return Err(Error::RequiredExtensionUnavailable),
+                };
+                let mut texture: *mut d3d11::ID3D11Texture2D = ptr::null_mut();
+                let result = d3d11_device.OpenSharedResource1(
+                    share_handle,
+                    &d3d11::ID3D11Texture2D::uuidof(),
+                    &mut texture as *mut *mut d3d11::ID3D11Texture2D as *mut *mut c_void,
+                );
+                if !winerror::SUCCEEDED(result) || texture.is_null() {
+                    return Err(Error::IncompatibleSurface);
+                }
+                let texture = ComPtr::from_raw(texture);
+                self.create_surface_from_client_buffer(
+                    context,
+                    texture.as_raw() as *mut c_void,
+                    false,
+                    size,
+                )
+            }
+        }
     }
 
     fn create_window_surface(
@@ -254,9 +812,16 @@ impl Device {
         let context_descriptor = self.context_descriptor(context);
         let egl_config = self.context_descriptor_to_egl_config(&context_descriptor);
 
+        let present_mode = self.present_mode();
+        let mut attributes = vec![];
+        if present_mode != PresentMode::Copy {
+            attributes
+                .extend_from_slice(&[EGL_DIRECT_COMPOSITION_ANGLE as EGLint, egl::TRUE as EGLint]);
+        }
+        attributes.push(egl::NONE as EGLint);
+
         unsafe {
             EGL_FUNCTIONS.with(|egl| {
-                let attributes = [egl::NONE as EGLint];
                 let egl_surface = egl.CreateWindowSurface(
                     self.egl_display,
                     egl_config,
@@ -265,6 +830,21 @@ impl Device {
                 );
                 assert_ne!(egl_surface, egl::NO_SURFACE);
 
+                let swap_behavior = match present_mode {
+                    PresentMode::Copy => None,
+                    PresentMode::Flip => Some(egl::BUFFER_PRESERVED),
+                    PresentMode::FlipDiscard => Some(egl::BUFFER_DESTROYED),
+                };
+                if let Some(swap_behavior) = swap_behavior {
+                    // Not every config can preserve the back buffer, so this is only a hint.
+                    egl.SurfaceAttrib(
+                        self.egl_display,
+                        egl_surface,
+                        egl::SWAP_BEHAVIOR as EGLint,
+                        swap_behavior as EGLint,
+                    );
+                }
+
                 let mut width = 0;
                 let mut height = 0;
                 egl.QuerySurface(
@@ -284,15 +864,163 @@ impl Device {
 
                 Ok(Surface {
                     egl_surface,
+                    id: next_surface_id(),
                     size: Size2D::new(width, height),
                     context_id: context.id,
                     context_descriptor,
+                    usage: SurfaceUsage::RenderTarget,
+                    access: SurfaceAccess::GPUOnly,
+                    alpha_type: AlphaType::Premultiplied,
+                    mipmapped: false,
                     win32_objects: Win32Objects::Window,
                 })
             })
         }
     }
 
+    /// Creates a widget surface that presents through a DXGI flip-model swap chain rather than
+    /// an EGL window surface.
+    ///
+    /// A `DXGI_SWAP_EFFECT_FLIP_DISCARD` swap chain with `buffer_count` buffers (2 to 16) is
+    /// created for the widget's window on this device's Direct3D 11 device, and its back buffer
+    /// is wrapped in the surface. `present_surface()` then flushes the context and calls
+    /// `IDXGISwapChain::Present()`, synchronized to vertical blank. This is the lowest-latency way
+    /// to get frames onto the screen, but the back buffer's contents are undefined after each
+    /// present, and the swap chain is sized to the window when it's created and isn't resized
+    /// along with it.
+    ///
+    /// Widget surfaces from `create_surface()` are EGL window surfaces, whose swap chains ANGLE
+    /// creates with a buffer count of its own choosing, so this is the only way to ask for triple
+    /// buffering.
+    ///
+    /// If `buffer_count` is out of range, or the window already has a swap chain or an EGL window
+    /// surface, a `SurfaceCreationFailed` error is returned.
+    pub fn create_swapchain_surface(
+        &self,
+        context: &Context,
+        native_widget: &NativeWidget,
+        buffer_count: u32,
+    ) -> Result<Surface, Error> {
+        device_span!(
+            "create_swapchain_surface",
+            context = self.context_id(context).0
+        );
+        if buffer_count < 2 || buffer_count > MAX_SWAP_CHAIN_BUFFER_COUNT {
+            return Err(Error::SurfaceCreationFailed(WindowingApiError::BadValue));
+        }
+
+        let context_descriptor = self.context_descriptor(context);
+        let egl_config = self.context_descriptor_to_egl_config(&context_descriptor);
+
+        unsafe {
+            // Create the swap chain with the factory that made this device's adapter, as DXGI
+            // requires.
+            let dxgi_device = match self.d3d11_device.cast::<IDXGIDevice>() {
+                Ok(dxgi_device) => dxgi_device,
+                Err(_) => return Err(Error::SurfaceCreationFailed(WindowingApiError::Failed)),
+            };
+            let mut dxgi_adapter = ptr::null_mut();
+            let result = dxgi_device.GetAdapter(&mut dxgi_adapter);
+            if !winerror::SUCCEEDED(result) {
+                return Err(Error::SurfaceCreationFailed(WindowingApiError::Failed));
+            }
+            let dxgi_adapter = ComPtr::from_raw(dxgi_adapter);
+            let mut dxgi_factory: *mut IDXGIFactory2 = ptr::null_mut();
+            let result = dxgi_adapter.GetParent(
+                &IDXGIFactory2::uuidof(),
+                &mut dxgi_factory as *mut *mut IDXGIFactory2 as *mut *mut c_void,
+            );
+            if !winerror::SUCCEEDED(result) {
+                return Err(Error::SurfaceCreationFailed(WindowingApiError::Failed));
+            }
+            let dxgi_factory = ComPtr::from_raw(dxgi_factory);
+
+            // A width and height of zero size the swap chain to the window's client area.
+            let desc = DXGI_SWAP_CHAIN_DESC1 {
+                Width: 0,
+                Height: 0,
+                Format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                Stereo: FALSE,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+                BufferCount: buffer_count,
+                Scaling: DXGI_SCALING_STRETCH,
+                SwapEffect: DXGI_SWAP_EFFECT_FLIP_DISCARD,
+                AlphaMode: DXGI_ALPHA_MODE_IGNORE,
+                Flags: 0,
+            };
+            let mut swap_chain = ptr::null_mut();
+            let result = dxgi_factory.CreateSwapChainForHwnd(
+                self.d3d11_device.as_raw() as *mut IUnknown,
+                native_widget.egl_native_window as HWND,
+                &desc,
+                ptr::null(),
+                ptr::null_mut(),
+                &mut swap_chain,
+            );
+            if !winerror::SUCCEEDED(result) {
+                return Err(Error::SurfaceCreationFailed(WindowingApiError::BadWindow));
+            }
+            let swap_chain = ComPtr::from_raw(swap_chain);
+
+            let mut desc = mem::zeroed::<DXGI_SWAP_CHAIN_DESC1>();
+            swap_chain.GetDesc1(&mut desc);
+            let size = Size2D::new(desc.Width as i32, desc.Height as i32);
+
+            // With the flip model, buffer 0 always refers to the current back buffer, so the
+            // surface follows the swap chain as it presents.
+            let mut back_buffer: *mut d3d11::ID3D11Texture2D = ptr::null_mut();
+            let result = swap_chain.GetBuffer(
+                0,
+                &d3d11::ID3D11Texture2D::uuidof(),
+                &mut back_buffer as *mut *mut d3d11::ID3D11Texture2D as *mut *mut c_void,
+            );
+            if !winerror::SUCCEEDED(result) {
+                return Err(Error::SurfaceCreationFailed(WindowingApiError::Failed));
+            }
+            let back_buffer = ComPtr::from_raw(back_buffer);
+
+            EGL_FUNCTIONS.with(|egl| {
+                let attributes = [
+                    egl::WIDTH as EGLint,
+                    size.width as EGLint,
+                    egl::HEIGHT as EGLint,
+                    size.height as EGLint,
+                    egl::NONE as EGLint,
+                ];
+                let egl_surface = egl.CreatePbufferFromClientBuffer(
+                    self.egl_display,
+                    EGL_D3D_TEXTURE_ANGLE,
+                    back_buffer.as_raw() as *const _,
+                    egl_config,
+                    attributes.as_ptr(),
+                );
+                if egl_surface == egl::NO_SURFACE {
+                    return Err(surface_creation_error(egl.GetError()));
+                }
+
+                Ok(Surface {
+                    egl_surface,
+                    id: next_surface_id(),
+                    size,
+                    context_id: context.id,
+                    context_descriptor,
+                    usage: SurfaceUsage::RenderTarget,
+                    access: SurfaceAccess::GPUOnly,
+                    alpha_type: AlphaType::Premultiplied,
+                    mipmapped: false,
+                    win32_objects: Win32Objects::SwapChain {
+                        swap_chain,
+                        back_buffer,
+                    },
+                })
+            })
+        }
+    }
+
     /// Creates a surface texture from an existing generic surface for use with the given context.
     ///
     /// The surface texture is local to the supplied context and takes ownership of the surface.
@@ -309,8 +1037,15 @@ impl Device {
         context: &mut Context,
         surface: Surface,
     ) -> Result<SurfaceTexture, (Error, Surface)> {
+        device_span!(
+            "create_surface_texture",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(&surface).size,
+        );
         let share_handle = match surface.win32_objects {
-            Win32Objects::Window => return Err((Error::WidgetAttached, surface)),
+            Win32Objects::Window | Win32Objects::SwapChain { .. } => {
+                return Err((Error::WidgetAttached, surface))
+            }
             Win32Objects::Pbuffer { share_handle, .. } => share_handle,
         };
 
@@ -327,6 +1062,8 @@ impl Device {
                     egl::TEXTURE_RGBA as EGLint,
                     egl::TEXTURE_TARGET as EGLint,
                     egl::TEXTURE_2D as EGLint,
+                    egl::MIPMAP_TEXTURE as EGLint,
+                    surface.mipmapped as EGLint,
                     egl::NONE as EGLint,
                     0,
                     0,
@@ -341,8 +1078,10 @@ impl Device {
                     pbuffer_attributes.as_ptr(),
                 );
                 if local_egl_surface == egl::NO_SURFACE {
-                    let windowing_api_error = egl.GetError().to_windowing_api_error();
-                    return Err((Error::SurfaceImportFailed(windowing_api_error), surface));
+                    return Err((
+                        egl_error(egl.GetError(), Error::SurfaceImportFailed),
+                        surface,
+                    ));
                 }
 
                 let mut local_keyed_mutex: *mut IDXGIKeyedMutex = ptr::null_mut();
@@ -375,6 +1114,45 @@ impl Device {
         })
     }
 
+    /// Creates a surface texture for one region of the given surface, such as a single image
+    /// packed into an atlas.
+    ///
+    /// The texture object still covers the whole surface; the region is only recorded, in pixels
+    /// from the bottom-left corner, so that `surface_texture_uv_rect()` can report it in texture
+    /// coordinates. If the region is empty or extends past the edges of the surface, a
+    /// `RectOutOfBounds` error is returned along with the surface.
+    pub fn create_surface_texture_region(
+        &self,
+        context: &mut Context,
+        surface: Surface,
+        region: Rect<i32>,
+    ) -> Result<SurfaceTexture, (Error, Surface)> {
+        device_span!(
+            "create_surface_texture_region",
+            context = self.context_id(context).0
+        );
+        let bounds = Rect::new(Point2D::zero(), surface.size);
+        if region.is_empty() || !bounds.contains_rect(&region) {
+            return Err((Error::RectOutOfBounds, surface));
+        }
+        let mut surface_texture = self.create_surface_texture(context, surface)?;
+        surface_texture.region = region;
+        Ok(surface_texture)
+    }
+
+    /// Returns the region of the surface that the given surface texture samples, normalized to
+    /// texture coordinates from 0 to 1.
+    ///
+    /// For surface textures not created with `create_surface_texture_region()`, this covers the
+    /// whole texture.
+    pub fn surface_texture_uv_rect(&self, surface_texture: &SurfaceTexture) -> Rect<f32> {
+        let size = surface_texture.surface.size.to_f32();
+        surface_texture
+            .region
+            .to_f32()
+            .scale(1.0 / size.width, 1.0 / size.height)
+    }
+
     fn create_surface_texture_from_local_surface(
         &self,
         context: &Context,
@@ -386,50 +1164,57 @@ impl Device {
             unsafe {
                 let _guard = self.temporarily_make_context_current(context);
 
-                GL_FUNCTIONS.with(|gl| {
-                    // Then bind that surface to the texture.
-                    let mut texture = 0;
-                    gl.GenTextures(1, &mut texture);
-                    debug_assert_ne!(texture, 0);
+                let gl = context.gl();
+                let _state_guard = GLStateGuard::new(gl);
 
-                    gl.BindTexture(gl::TEXTURE_2D, texture);
-                    if egl.BindTexImage(
-                        self.egl_display,
-                        local_egl_surface,
-                        egl::BACK_BUFFER as GLint,
-                    ) == egl::FALSE
-                    {
-                        let windowing_api_error = egl.GetError().to_windowing_api_error();
-                        return Err((
-                            Error::SurfaceTextureCreationFailed(windowing_api_error),
-                            surface,
-                        ));
-                    }
+                // Then bind that surface to the texture.
+                let mut texture = 0;
+                gl.GenTextures(1, &mut texture);
+                debug_assert_ne!(texture, 0);
 
-                    // Initialize the texture, for convenience.
-                    gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
-                    gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
-                    gl.TexParameteri(
-                        gl::TEXTURE_2D,
-                        gl::TEXTURE_WRAP_S,
-                        gl::CLAMP_TO_EDGE as GLint,
-                    );
-                    gl.TexParameteri(
-                        gl::TEXTURE_2D,
-                        gl::TEXTURE_WRAP_T,
-                        gl::CLAMP_TO_EDGE as GLint,
-                    );
+                gl.BindTexture(gl::TEXTURE_2D, texture);
+                if egl.BindTexImage(
+                    self.egl_display,
+                    local_egl_surface,
+                    egl::BACK_BUFFER as GLint,
+                ) == egl::FALSE
+                {
+                    return Err((
+                        egl_error(egl.GetError(), Error::SurfaceTextureCreationFailed),
+                        surface,
+                    ));
+                }
+
+                // Initialize the texture, for convenience.
+                let min_filter = if surface.mipmapped {
+                    gl::LINEAR_MIPMAP_LINEAR
+                } else {
+                    gl::LINEAR
+                };
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
+                gl.TexParameteri(
+                    gl::TEXTURE_2D,
+                    gl::TEXTURE_WRAP_S,
+                    gl::CLAMP_TO_EDGE as GLint,
+                );
+                gl.TexParameteri(
+                    gl::TEXTURE_2D,
+                    gl::TEXTURE_WRAP_T,
+                    gl::CLAMP_TO_EDGE as GLint,
+                );
 
-                    gl.BindTexture(gl::TEXTURE_2D, 0);
-                    debug_assert_eq!(gl.GetError(), gl::NO_ERROR);
+                debug_assert_eq!(gl.GetError(), gl::NO_ERROR);
 
-                    Ok(SurfaceTexture {
-                        surface,
-                        local_egl_surface,
-                        local_keyed_mutex,
-                        gl_texture: texture,
-                        phantom: PhantomData,
-                    })
+                let region = Rect::new(Point2D::zero(), surface.size);
+                Ok(SurfaceTexture {
+                    surface,
+                    local_egl_surface,
+                    local_keyed_mutex,
+                    gl_texture: texture,
+                    context_id: context.id,
+                    region,
+                    phantom: PhantomData,
                 })
             }
         })
@@ -444,7 +1229,18 @@ impl Device {
         size: &Size2D<i32>,
         texture: ComPtr<d3d11::ID3D11Texture2D>,
     ) -> Result<SurfaceTexture, Error> {
-        let surface = self.create_pbuffer_surface(context, size, Some(texture))?;
+        device_span!(
+            "create_surface_texture_from_texture",
+            context = self.context_id(context).0
+        );
+        let surface = self.create_pbuffer_surface(
+            context,
+            size,
+            Some(texture),
+            SurfaceUsage::RenderTarget,
+            false,
+            false,
+        )?;
         let local_egl_surface = surface.egl_surface;
         self.create_surface_texture_from_local_surface(context, surface, local_egl_surface, None)
             .map_err(|(err, mut surface)| {
@@ -453,30 +1249,112 @@ impl Device {
             })
     }
 
-    /// Destroys a surface.
+    /// Re-associates a surface with a different context and binds it to that context.
     ///
-    /// The supplied context must be the context the surface is associated with, or this returns
-    /// an `IncompatibleSurface` error.
+    /// This is for recovering after the surface's original context was lost or destroyed while
+    /// the Direct3D texture backing the surface survived: only the EGL pbuffer wrapping the
+    /// texture is re-created, so the surface's contents are preserved and don't need to be
+    /// uploaded again. The surface must not be bound to any context.
     ///
-    /// You must explicitly call this method to dispose of a surface. Otherwise, a panic occurs in
-    /// the `drop` method.
-    pub fn destroy_surface(
+    /// Calling this method on a widget surface returns a `WidgetAttached` error. If an error is
+    /// returned, the surface is returned alongside it.
+    pub fn rebind_surface(
         &self,
-        context: &mut Context,
-        surface: &mut Surface,
-    ) -> Result<(), Error> {
-        if context.id != surface.context_id {
-            return Err(Error::IncompatibleSurface);
-        }
-
-        EGL_FUNCTIONS.with(|egl| {
-            unsafe {
-                // If the surface is currently bound, unbind it.
-                if egl.GetCurrentSurface(egl::READ as EGLint) == surface.egl_surface
-                    || egl.GetCurrentSurface(egl::DRAW as EGLint) == surface.egl_surface
-                {
-                    self.make_no_context_current()?;
-                }
+        new_context: &mut Context,
+        mut surface: Surface,
+    ) -> Result<(), (Error, Surface)> {
+        device_span!("rebind_surface", context = self.context_id(new_context).0);
+        let (client_buffer_type, client_buffer) = match surface.win32_objects {
+            Win32Objects::Window | Win32Objects::SwapChain { .. } => {
+                return Err((Error::WidgetAttached, surface))
+            }
+            Win32Objects::Pbuffer {
+                texture: Some(ref texture),
+                ..
+            } => (EGL_D3D_TEXTURE_ANGLE, texture.as_raw() as EGLClientBuffer),
+            Win32Objects::Pbuffer { share_handle, .. } => (
+                EGL_D3D_TEXTURE_2D_SHARE_HANDLE_ANGLE,
+                share_handle as EGLClientBuffer,
+            ),
+        };
+
+        let context_descriptor = self.context_descriptor(new_context);
+        let egl_config = self.context_descriptor_to_egl_config(&context_descriptor);
+        let size = surface.size;
+        let result = EGL_FUNCTIONS.with(|egl| unsafe {
+            let attributes = [
+                egl::WIDTH as EGLint,
+                size.width,
+                egl::HEIGHT as EGLint,
+                size.height,
+                egl::TEXTURE_FORMAT as EGLint,
+                egl::TEXTURE_RGBA as EGLint,
+                egl::TEXTURE_TARGET as EGLint,
+                egl::TEXTURE_2D as EGLint,
+                egl::MIPMAP_TEXTURE as EGLint,
+                surface.mipmapped as EGLint,
+                egl::NONE as EGLint,
+                0,
+                0,
+                0,
+            ];
+            let egl_surface = egl.CreatePbufferFromClientBuffer(
+                self.egl_display,
+                client_buffer_type,
+                client_buffer,
+                egl_config,
+                attributes.as_ptr(),
+            );
+            if egl_surface == egl::NO_SURFACE {
+                return Err(surface_creation_error(egl.GetError()));
+            }
+            Ok(egl_surface)
+        });
+        let egl_surface = match result {
+            Ok(egl_surface) => egl_surface,
+            Err(err) => return Err((err, surface)),
+        };
+
+        // The new pbuffer holds its own reference to the texture, so the old one can go.
+        EGL_FUNCTIONS.with(|egl| unsafe {
+            egl.DestroySurface(self.egl_display, surface.egl_surface);
+        });
+        surface.egl_surface = egl_surface;
+        surface.context_id = new_context.id;
+        surface.context_descriptor = context_descriptor;
+
+        self.bind_surface_to_context(new_context, surface)
+    }
+
+    /// Destroys a surface.
+    ///
+    /// The supplied context must be the context the surface is associated with, or this returns
+    /// an `IncompatibleSurface` error.
+    ///
+    /// You must explicitly call this method to dispose of a surface. Otherwise, a panic occurs in
+    /// the `drop` method.
+    pub fn destroy_surface(
+        &self,
+        context: &mut Context,
+        surface: &mut Surface,
+    ) -> Result<(), Error> {
+        device_span!(
+            "destroy_surface",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(surface).size,
+        );
+        if context.id != surface.context_id {
+            return Err(Error::IncompatibleSurface);
+        }
+
+        EGL_FUNCTIONS.with(|egl| {
+            unsafe {
+                // If the surface is currently bound, unbind it.
+                if egl.GetCurrentSurface(egl::READ as EGLint) == surface.egl_surface
+                    || egl.GetCurrentSurface(egl::DRAW as EGLint) == surface.egl_surface
+                {
+                    self.make_no_context_current()?;
+                }
 
                 egl.DestroySurface(self.egl_display, surface.egl_surface);
                 surface.egl_surface = egl::NO_SURFACE;
@@ -500,11 +1378,15 @@ impl Device {
     /// occur.
     pub fn destroy_surface_texture(
         &self,
-        _: &mut Context,
+        context: &mut Context,
         mut surface_texture: SurfaceTexture,
     ) -> Result<Surface, (Error, SurfaceTexture)> {
+        device_span!(
+            "destroy_surface_texture",
+            context = self.context_id(context).0
+        );
         unsafe {
-            GL_FUNCTIONS.with(|gl| gl.DeleteTextures(1, &surface_texture.gl_texture));
+            context.gl().DeleteTextures(1, &surface_texture.gl_texture);
             surface_texture.gl_texture = 0;
 
             if let Some(ref local_keyed_mutex) = surface_texture.local_keyed_mutex {
@@ -520,6 +1402,246 @@ impl Device {
         Ok(surface_texture.surface)
     }
 
+    /// Releases a surface texture from its OpenGL texture and returns the underlying surface so
+    /// that it can be rendered to again.
+    ///
+    /// This is the inverse of `create_surface_texture()` and enables the classic
+    /// render→sample→render cycle. Ordering is strict: a surface must never be bound to a context
+    /// as a render target while it is also bound as a texture. Finish sampling from the surface
+    /// texture, call this method, and only then bind the returned surface to a context.
+    ///
+    /// The supplied context must be the same context the surface texture was created with.
+    pub fn present_surface_texture(
+        &self,
+        context: &mut Context,
+        mut surface_texture: SurfaceTexture,
+    ) -> Result<Surface, (Error, SurfaceTexture)> {
+        device_span!(
+            "present_surface_texture",
+            context = self.context_id(context).0
+        );
+        EGL_FUNCTIONS.with(|egl| {
+            unsafe {
+                let _guard = match self.temporarily_make_context_current(context) {
+                    Ok(guard) => guard,
+                    Err(err) => return Err((err, surface_texture)),
+                };
+
+                let gl = context.gl();
+
+                // Make sure all sampling from the texture is submitted before releasing it.
+                gl.Flush();
+                if egl.ReleaseTexImage(
+                    self.egl_display,
+                    surface_texture.local_egl_surface,
+                    egl::BACK_BUFFER as GLint,
+                ) == egl::FALSE
+                {
+                    let err = egl_error(egl.GetError(), Error::SurfaceTextureReleaseFailed);
+                    return Err((err, surface_texture));
+                }
+
+                gl.DeleteTextures(1, &surface_texture.gl_texture);
+                surface_texture.gl_texture = 0;
+
+                if let Some(ref local_keyed_mutex) = surface_texture.local_keyed_mutex {
+                    let result = local_keyed_mutex.ReleaseSync(0);
+                    assert_eq!(result, S_OK);
+                }
+
+                // A surface texture wrapping a local surface shares its EGL surface with the
+                // underlying surface, so it must not be destroyed here.
+                if surface_texture.local_egl_surface != surface_texture.surface.egl_surface {
+                    egl.DestroySurface(self.egl_display, surface_texture.local_egl_surface);
+                }
+                Ok(surface_texture.surface)
+            }
+        })
+    }
+
+    /// Blocks until all rendering to the given surface has completed on the GPU.
+    ///
+    /// Call this before reading the surface back or handing it to another API. The wait is
+    /// scoped to the commands issued on the surface's context, so unlike a global `glFinish()` it
+    /// doesn't wait for unrelated work. Where fence sync objects are unavailable, this falls back
+    /// to `glFinish()`.
+    ///
+    /// The supplied context must be the context the surface is associated with, or an
+    /// `IncompatibleSurface` error is returned.
+    pub fn wait_for_surface(&self, context: &Context, surface: &Surface) -> Result<(), Error> {
+        device_span!("wait_for_surface", context = self.context_id(context).0);
+        if context.id != surface.context_id {
+            return Err(Error::IncompatibleSurface);
+        }
+
+        let _guard = self.temporarily_make_context_current(context)?;
+        gl_utils::wait_for_gpu_completion(context.gl());
+        Ok(())
+    }
+
+    /// Creates a surface larger than a single texture can be, by splitting it into tiles of the
+    /// given size.
+    ///
+    /// The tile size must not exceed `GL_MAX_TEXTURE_SIZE`. Tiles are local to the given context.
+    pub fn create_tiled_surface(
+        &self,
+        context: &Context,
+        size: Size2D<i32>,
+        tile_size: Size2D<i32>,
+    ) -> Result<TiledSurface, Error> {
+        device_span!("create_tiled_surface", context = self.context_id(context).0);
+        let _guard = self.temporarily_make_context_current(context)?;
+        TiledSurface::new(context.gl(), context.id, size, tile_size)
+    }
+
+    /// Destroys a tiled surface.
+    ///
+    /// The supplied context must be the context the tiled surface was created with, or an
+    /// `IncompatibleSurface` error is returned.
+    pub fn destroy_tiled_surface(
+        &self,
+        context: &mut Context,
+        tiled_surface: &mut TiledSurface,
+    ) -> Result<(), Error> {
+        device_span!(
+            "destroy_tiled_surface",
+            context = self.context_id(context).0
+        );
+        if context.id != tiled_surface.context_id {
+            return Err(Error::IncompatibleSurface);
+        }
+        let _guard = self.temporarily_make_context_current(context)?;
+        tiled_surface.destroy(context.gl());
+        Ok(())
+    }
+
+    /// Clears every tile of a tiled surface to the given RGBA color.
+    ///
+    /// The supplied context must be the context the tiled surface was created with, or an
+    /// `IncompatibleSurface` error is returned.
+    pub fn clear_tiled_surface(
+        &self,
+        context: &Context,
+        tiled_surface: &TiledSurface,
+        color: [f32; 4],
+    ) -> Result<(), Error> {
+        device_span!("clear_tiled_surface", context = self.context_id(context).0);
+        if context.id != tiled_surface.context_id {
+            return Err(Error::IncompatibleSurface);
+        }
+        let _guard = self.temporarily_make_context_current(context)?;
+        tiled_surface.clear(context.gl(), color);
+        Ok(())
+    }
+
+    /// Reads back the RGBA pixels of a region of a tiled surface, which may span several tiles.
+    ///
+    /// Rows are returned bottom to top, as `glReadPixels()` does. The supplied context must be the
+    /// context the tiled surface was created with, or an `IncompatibleSurface` error is returned.
+    /// If the region is empty or extends past the edges of the surface, a `RectOutOfBounds` error
+    /// is returned.
+    pub fn read_tiled_surface_pixels(
+        &self,
+        context: &Context,
+        tiled_surface: &TiledSurface,
+        rect: &Rect<i32>,
+    ) -> Result<Vec<u8>, Error> {
+        device_span!(
+            "read_tiled_surface_pixels",
+            context = self.context_id(context).0
+        );
+        if context.id != tiled_surface.context_id {
+            return Err(Error::IncompatibleSurface);
+        }
+        let _guard = self.temporarily_make_context_current(context)?;
+        tiled_surface.read_pixels(context.gl(), rect)
+    }
+
+    /// Creates a surface backed by a `GL_TEXTURE_2D_ARRAY` with the given number of layers, such
+    /// as one per eye for stereo rendering.
+    ///
+    /// Each layer has its own framebuffer object. If `GL_OVR_multiview` is available, a
+    /// multiview framebuffer object covering every layer is created as well; see
+    /// `surface_array_framebuffer_object()`. The array surface is local to the given context.
+    pub fn create_array_surface(
+        &self,
+        context: &Context,
+        size: Size2D<i32>,
+        layers: u32,
+    ) -> Result<ArraySurface, Error> {
+        device_span!("create_array_surface", context = self.context_id(context).0);
+        let _guard = self.temporarily_make_context_current(context)?;
+        ArraySurface::new(context.gl(), &context.gl_info, context.id, size, layers)
+    }
+
+    /// Returns the multiview framebuffer object that renders to every layer of an array surface
+    /// at once, via `glFramebufferTextureMultiviewOVR()`.
+    ///
+    /// If the context the array surface was created with doesn't support `GL_OVR_multiview`, a
+    /// `RequiredExtensionUnavailable` error is returned; render to each layer's framebuffer object
+    /// in turn instead.
+    pub fn surface_array_framebuffer_object(
+        &self,
+        array_surface: &ArraySurface,
+    ) -> Result<GLuint, Error> {
+        array_surface
+            .multiview_framebuffer_object
+            .ok_or(Error::RequiredExtensionUnavailable)
+    }
+
+    /// Destroys an array surface.
+    ///
+    /// The supplied context must be the context the array surface was created with, or an
+    /// `IncompatibleSurface` error is returned.
+    pub fn destroy_array_surface(
+        &self,
+        context: &mut Context,
+        array_surface: &mut ArraySurface,
+    ) -> Result<(), Error> {
+        device_span!(
+            "destroy_array_surface",
+            context = self.context_id(context).0
+        );
+        if context.id != array_surface.context_id {
+            return Err(Error::IncompatibleSurface);
+        }
+        let _guard = self.temporarily_make_context_current(context)?;
+        array_surface.destroy(context.gl());
+        Ok(())
+    }
+
+    /// Overrides the minification and magnification filters of a surface texture.
+    ///
+    /// Surface textures are created with `GL_LINEAR` filtering and `GL_CLAMP_TO_EDGE` wrapping,
+    /// so they are complete and can be sampled without further setup. The supplied context must
+    /// be the same context the surface texture was created with, or an
+    /// `IncompatibleSurfaceTexture` error is returned.
+    pub fn set_surface_texture_filter(
+        &self,
+        context: &Context,
+        surface_texture: &SurfaceTexture,
+        min_filter: GLenum,
+        mag_filter: GLenum,
+    ) -> Result<(), Error> {
+        device_span!(
+            "set_surface_texture_filter",
+            context = self.context_id(context).0
+        );
+        if context.id != surface_texture.context_id {
+            return Err(Error::IncompatibleSurfaceTexture);
+        }
+
+        let _guard = self.temporarily_make_context_current(context)?;
+        let gl = context.gl();
+        unsafe {
+            let _state_guard = GLStateGuard::new(gl);
+            gl.BindTexture(gl::TEXTURE_2D, surface_texture.gl_texture);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag_filter as GLint);
+        }
+        Ok(())
+    }
+
     /// Returns the OpenGL texture target needed to read from this surface texture.
     ///
     /// This will be `GL_TEXTURE_2D` or `GL_TEXTURE_RECTANGLE`, depending on platform.
@@ -534,9 +1656,106 @@ impl Device {
         &self,
         _surface: &'s mut Surface,
     ) -> Result<SurfaceDataGuard<'s>, Error> {
+        device_span!("lock_surface_data");
         Err(Error::Unimplemented)
     }
 
+    /// Maps a generic surface so that the CPU can write pixels into it directly.
+    ///
+    /// The mapped pixels start out as the surface's current contents, so partial updates are
+    /// fine. When the returned guard is dropped, the pixels are copied into the surface, where
+    /// OpenGL will see them. The surface must have been created with `GPUCPU` or
+    /// `GPUCPUWriteCombined` access, or a `SurfaceDataInaccessible` error is returned.
+    pub fn map_surface_write<'s>(
+        &self,
+        surface: &'s mut Surface,
+    ) -> Result<SurfaceWriteGuard<'s>, Error> {
+        device_span!("map_surface_write");
+        if !surface.access.cpu_access_allowed() {
+            return Err(Error::SurfaceDataInaccessible);
+        }
+        let (share_handle, synchronization, texture) = match surface.win32_objects {
+            Win32Objects::Pbuffer {
+                share_handle,
+                ref synchronization,
+                ref texture,
+            } => (share_handle, synchronization, texture),
+            Win32Objects::Window | Win32Objects::SwapChain { .. } => {
+                return Err(Error::SurfaceDataInaccessible)
+            }
+        };
+
+        unsafe {
+            let texture = match *texture {
+                Some(ref texture) => texture.clone(),
+                None => {
+                    let mut texture: *mut d3d11::ID3D11Texture2D = ptr::null_mut();
+                    let result = self.d3d11_device.OpenSharedResource(
+                        share_handle,
+                        &d3d11::ID3D11Texture2D::uuidof(),
+                        &mut texture as *mut *mut d3d11::ID3D11Texture2D as *mut *mut c_void,
+                    );
+                    if !winerror::SUCCEEDED(result) || texture.is_null() {
+                        return Err(Error::SurfaceDataInaccessible);
+                    }
+                    ComPtr::from_raw(texture)
+                }
+            };
+            let keyed_mutex = match *synchronization {
+                Synchronization::KeyedMutex(_) => texture.cast::<IDXGIKeyedMutex>().ok(),
+                Synchronization::GLFinish | Synchronization::None => None,
+            };
+
+            let mut desc = mem::zeroed::<d3d11::D3D11_TEXTURE2D_DESC>();
+            texture.GetDesc(&mut desc);
+            desc.Usage = d3d11::D3D11_USAGE_STAGING;
+            desc.BindFlags = 0;
+            desc.CPUAccessFlags = d3d11::D3D11_CPU_ACCESS_READ | d3d11::D3D11_CPU_ACCESS_WRITE;
+            desc.MiscFlags = 0;
+            let mut staging_texture = ptr::null_mut();
+            let result =
+                self.d3d11_device
+                    .CreateTexture2D(&desc, ptr::null(), &mut staging_texture);
+            if !winerror::SUCCEEDED(result) {
+                return Err(Error::OutOfSurfaceMemory);
+            }
+            let staging_texture = ComPtr::from_raw(staging_texture);
+
+            let mut device_context = ptr::null_mut();
+            self.d3d11_device.GetImmediateContext(&mut device_context);
+            let device_context = ComPtr::from_raw(device_context);
+
+            copy_texture(
+                &device_context,
+                &staging_texture,
+                &texture,
+                keyed_mutex.as_ref(),
+            );
+            let mut mapped = mem::zeroed::<d3d11::D3D11_MAPPED_SUBRESOURCE>();
+            let result = device_context.Map(
+                staging_texture.as_raw() as *mut d3d11::ID3D11Resource,
+                0,
+                d3d11::D3D11_MAP_READ_WRITE,
+                0,
+                &mut mapped,
+            );
+            if !winerror::SUCCEEDED(result) {
+                return Err(Error::SurfaceDataInaccessible);
+            }
+
+            Ok(SurfaceWriteGuard {
+                data: mapped.pData as *mut u8,
+                len: mapped.RowPitch as usize * desc.Height as usize,
+                row_pitch: mapped.RowPitch as usize,
+                device_context,
+                staging_texture,
+                texture,
+                keyed_mutex,
+                phantom: PhantomData,
+            })
+        }
+    }
+
     /// Displays the contents of a widget surface on screen.
     ///
     /// Widget surfaces are internally double-buffered, so changes to them don't show up in their
@@ -544,16 +1763,169 @@ impl Device {
     ///
     /// The supplied context must match the context the surface was created with, or an
     /// `IncompatibleSurface` error is returned.
-    pub fn present_surface(&self, _: &Context, surface: &mut Surface) -> Result<(), Error> {
+    pub fn present_surface(&self, context: &Context, surface: &mut Surface) -> Result<(), Error> {
+        device_span!(
+            "present_surface",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(surface).size,
+        );
+        self.present_surface_reporting_occlusion(context, surface)?;
+        Ok(())
+    }
+
+    /// Displays the contents of a widget surface on screen, like `present_surface()`, and reports
+    /// whether the frame was actually shown.
+    ///
+    /// Swap chain surfaces created with `create_swapchain_surface()` return `Occluded` when DXGI
+    /// discards the frame because the window can't be seen, for instance because it's
+    /// minimized. Rendering can then be throttled until a later present returns `Presented`
+    /// again. EGL window surfaces can't detect this, so they always return `Presented`.
+    pub fn present_surface_reporting_occlusion(
+        &self,
+        context: &Context,
+        surface: &mut Surface,
+    ) -> Result<PresentResult, Error> {
+        device_span!(
+            "present_surface_reporting_occlusion",
+            context = self.context_id(context).0
+        );
+        let result = self.swap_buffers(context, surface);
+        self.check_context_lost(context, result)
+    }
+
+    /// Displays the contents of the widget surface attached to the given context on screen, and
+    /// reports how long that took.
+    ///
+    /// Frame timestamps aren't available through ANGLE, so the returned statistics only measure
+    /// the CPU-side time spent swapping buffers. A consistently long swap usually indicates that
+    /// rendering is GPU-bound.
+    ///
+    /// If the context has no widget surface attached, a `NoWidgetAttached` error is returned.
+    pub fn present_context_timed(&self, context: &mut Context) -> Result<PresentStats, Error> {
+        device_span!(
+            "present_context_timed",
+            context = self.context_id(context).0
+        );
+        let surface = match context.framebuffer {
+            Framebuffer::Surface(ref surface) => surface,
+            Framebuffer::None | Framebuffer::External(_) => return Err(Error::NoWidgetAttached),
+        };
+
+        let start = Instant::now();
+        let result = self.swap_buffers(context, surface);
+        self.check_context_lost(context, result)?;
+        Ok(PresentStats {
+            swap_duration: start.elapsed(),
+        })
+    }
+
+    /// Presents the surface attached to the given context and blocks until the GPU has finished
+    /// rendering it.
+    ///
+    /// Widget surfaces are displayed on screen as with `present_surface()`. Generic surfaces have
+    /// nothing to present, so only the wait happens. Either way, once this returns, the frame can
+    /// safely be read back through another API, such as Direct3D.
+    ///
+    /// If the context has no surface attached, a `NoWidgetAttached` error is returned.
+    pub fn present_and_wait(&self, context: &mut Context) -> Result<(), Error> {
+        device_span!("present_and_wait", context = self.context_id(context).0);
+        let surface = match context.framebuffer {
+            Framebuffer::Surface(ref surface) => surface,
+            Framebuffer::None | Framebuffer::External(_) => return Err(Error::NoWidgetAttached),
+        };
+
+        let _guard = self.temporarily_make_context_current(context)?;
+        match surface.win32_objects {
+            Win32Objects::Window | Win32Objects::SwapChain { .. } => {
+                let result = self.swap_buffers(context, surface);
+                self.check_context_lost(context, result)?;
+            }
+            Win32Objects::Pbuffer { .. } => {}
+        }
+
+        EGL_FUNCTIONS.with(|egl| unsafe {
+            let attributes = [egl::NONE as EGLAttrib];
+            let egl_sync = egl.CreateSync(self.egl_display, egl::SYNC_FENCE, attributes.as_ptr());
+            if egl_sync == egl::NO_SYNC {
+                return Err(egl_error(egl.GetError(), Error::PresentFailed));
+            }
+
+            let result = egl.ClientWaitSync(
+                self.egl_display,
+                egl_sync,
+                egl::SYNC_FLUSH_COMMANDS_BIT as EGLint,
+                egl::FOREVER,
+            );
+            let error = egl.GetError();
+            egl.DestroySync(self.egl_display, egl_sync);
+
+            if result as EGLenum != egl::CONDITION_SATISFIED {
+                return Err(egl_error(error, Error::PresentFailed));
+            }
+            Ok(())
+        })
+    }
+
+    /// Displays the contents of the widget surface attached to the given context on screen,
+    /// returning a future that resolves once the GPU has finished the frame.
+    ///
+    /// The future waits on an EGL fence inserted at present time. Polling never blocks the
+    /// thread. If the fence hasn't been signaled yet, a helper thread waits for it and wakes the
+    /// task, so GPU completion fits into an async scheduler. It can be awaited on any thread.
+    ///
+    /// If the context has no widget surface attached, a `NoWidgetAttached` error is returned.
+    /// Errors from presenting run the context's loss callback like those of `present_surface()`,
+    /// but the future may resolve on another thread, so a loss it reports doesn't; the next call
+    /// to `poll_context_lost()` detects it.
+    #[cfg(feature = "sm-async")]
+    pub fn present_context_async(
+        &self,
+        context: &mut Context,
+    ) -> impl Future<Output = Result<(), Error>> {
+        device_span!(
+            "present_context_async",
+            context = self.context_id(context).0
+        );
+        let fence = self.present_context_with_fence(context);
+        let fence = self.check_context_lost(context, fence);
+        async move { fence?.await }
+    }
+
+    #[cfg(feature = "sm-async")]
+    fn present_context_with_fence(&self, context: &Context) -> Result<EGLFenceFuture, Error> {
+        let surface = match context.framebuffer {
+            Framebuffer::Surface(ref surface) => surface,
+            Framebuffer::None | Framebuffer::External(_) => return Err(Error::NoWidgetAttached),
+        };
+
+        let _guard = self.temporarily_make_context_current(context)?;
+        self.swap_buffers(context, surface)?;
+        unsafe {
+            let fence = EGLFenceFuture::new(self.egl_display)?;
+            context.gl().Flush();
+            Ok(fence)
+        }
+    }
+
+    fn swap_buffers(&self, context: &Context, surface: &Surface) -> Result<PresentResult, Error> {
         match surface.win32_objects {
             Win32Objects::Window { .. } => {}
+            Win32Objects::SwapChain { ref swap_chain, .. } => {
+                // ANGLE doesn't know that the back buffer is being presented, so submit its
+                // rendering before DXGI flips it to the screen.
+                let _guard = self.temporarily_make_context_current(context)?;
+                context.gl().Flush();
+                return present_result(unsafe { swap_chain.Present(1, 0) });
+            }
             _ => return Err(Error::NoWidgetAttached),
         }
 
         EGL_FUNCTIONS.with(|egl| unsafe {
             let ok = egl.SwapBuffers(self.egl_display, surface.egl_surface);
-            assert_ne!(ok, egl::FALSE);
-            Ok(())
+            if ok == egl::FALSE {
+                return Err(egl_error(egl.GetError(), Error::PresentFailed));
+            }
+            Ok(PresentResult::Presented)
         })
     }
 
@@ -564,6 +1936,7 @@ impl Device {
         surface: &mut Surface,
         size: Size2D<i32>,
     ) -> Result<(), Error> {
+        device_span!("resize_surface", context = self.context_id(_context).0, size = ?size);
         surface.size = size;
         Ok(())
     }
@@ -574,6 +1947,9 @@ impl Device {
     /// Before rendering to a surface attached to a context, you must call `glBindFramebuffer()`
     /// on the framebuffer object returned by this function. This framebuffer object may or not be
     /// 0, the default framebuffer, depending on platform.
+    ///
+    /// On this backend, surface IDs are assigned from a per-process counter when the surface is
+    /// created, so they are never reused.
     #[inline]
     pub fn surface_info(&self, surface: &Surface) -> SurfaceInfo {
         SurfaceInfo {
@@ -584,6 +1960,419 @@ impl Device {
         }
     }
 
+    /// Returns whether the contents of the given surface have premultiplied or straight alpha.
+    #[inline]
+    pub fn surface_alpha_type(&self, surface: &Surface) -> AlphaType {
+        surface.alpha_type
+    }
+
+    /// Estimates how much GPU memory the given surface occupies, in bytes.
+    ///
+    /// The estimate is computed from the surface's size, the color, depth, and stencil sizes of
+    /// its config, its mipmap chain, and its sample count. Driver padding is not included. Widget
+    /// surfaces and surfaces wrapping textures that surfman didn't allocate return `None`, since
+    /// their memory belongs to a swap chain or to the caller.
+    pub fn surface_memory_bytes(&self, surface: &Surface) -> Option<u64> {
+        match surface.win32_objects {
+            Win32Objects::Pbuffer { texture: None, .. } => {}
+            Win32Objects::Pbuffer { .. }
+            | Win32Objects::Window
+            | Win32Objects::SwapChain { .. } => return None,
+        }
+
+        let egl_config = self.context_descriptor_to_egl_config(&surface.context_descriptor);
+        let get = |attr: EGLenum| unsafe {
+            context::get_config_attr(self.egl_display, egl_config, attr as EGLint)
+        };
+        Some(estimate_surface_memory_bytes(
+            surface.size,
+            get(egl::BUFFER_SIZE),
+            get(egl::DEPTH_SIZE) + get(egl::STENCIL_SIZE),
+            get(egl::SAMPLES),
+            surface.mipmapped,
+        ))
+    }
+
+    /// Tells the driver that the contents of the given buffers of a surface can be discarded.
+    ///
+    /// Call this before rendering a fresh frame to a reused surface, so that tiled GPUs don't
+    /// have to load the old contents. The invalidated buffers have undefined contents until they
+    /// are next cleared or drawn to. The surface must have been created for the given context,
+    /// or an `IncompatibleSurface` error is returned. If the driver doesn't support invalidation,
+    /// this does nothing.
+    pub fn invalidate_surface(
+        &self,
+        context: &Context,
+        surface: &Surface,
+        attachments: InvalidateFlags,
+    ) -> Result<(), Error> {
+        device_span!("invalidate_surface", context = self.context_id(context).0);
+        if surface.context_id != context.id {
+            return Err(Error::IncompatibleSurface);
+        }
+        if surface.usage == SurfaceUsage::TextureOnly {
+            return Err(Error::SurfaceNotRenderable);
+        }
+
+        let _guard = self.temporarily_make_surface_current(context, surface)?;
+
+        let mut gl_attachments = vec![];
+        if attachments.contains(InvalidateFlags::COLOR) {
+            gl_attachments.push(gl::COLOR);
+        }
+        if attachments.contains(InvalidateFlags::DEPTH) {
+            gl_attachments.push(gl::DEPTH);
+        }
+        if attachments.contains(InvalidateFlags::STENCIL) {
+            gl_attachments.push(gl::STENCIL);
+        }
+
+        let gl = context.gl();
+        unsafe {
+            if gl_attachments.is_empty() || !gl.InvalidateFramebuffer.is_loaded() {
+                return Ok(());
+            }
+            let _state_guard = GLStateGuard::new(gl);
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl.InvalidateFramebuffer(
+                gl::FRAMEBUFFER,
+                gl_attachments.len() as GLsizei,
+                gl_attachments.as_ptr(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Flushes rendering to the surface bound to a context, first discarding the given buffers.
+    ///
+    /// Use this when handing a surface off to a reader that only needs some of its buffers,
+    /// typically passing `DEPTH | STENCIL` to keep just the color. Discarding spares the driver
+    /// from writing those buffers back to memory, and they have undefined contents afterward. If
+    /// the context has no surface bound, a `NoSurfaceAttached` error is returned. If the driver
+    /// doesn't support invalidation, this only flushes.
+    pub fn flush_context_surface_discarding(
+        &self,
+        context: &Context,
+        discard: InvalidateFlags,
+    ) -> Result<(), Error> {
+        device_span!(
+            "flush_context_surface_discarding",
+            context = self.context_id(context).0
+        );
+        let surface = match context.framebuffer {
+            Framebuffer::Surface(ref surface) => surface,
+            Framebuffer::External(_) => return Err(Error::ExternalRenderTarget),
+            Framebuffer::None => return Err(Error::NoSurfaceAttached),
+        };
+
+        self.invalidate_surface(context, surface, discard)?;
+
+        let _guard = self.temporarily_make_context_current(context)?;
+        unsafe { context.gl().Flush() }
+        Ok(())
+    }
+
+    /// Clears the color buffer of a surface to the given RGBA color.
+    ///
+    /// The surface doesn't need to be bound to the context, but it must have been created for it,
+    /// or an `IncompatibleSurface` error is returned. Texture-only surfaces can't be cleared this
+    /// way. The context's OpenGL state is left as it was.
+    pub fn clear_surface(
+        &self,
+        context: &Context,
+        surface: &Surface,
+        color: [f32; 4],
+    ) -> Result<(), Error> {
+        device_span!("clear_surface", context = self.context_id(context).0);
+        if surface.context_id != context.id {
+            return Err(Error::IncompatibleSurface);
+        }
+        if surface.usage == SurfaceUsage::TextureOnly {
+            return Err(Error::SurfaceNotRenderable);
+        }
+
+        let _guard = self.temporarily_make_surface_current(context, surface)?;
+        let gl = context.gl();
+        unsafe {
+            let _state_guard = GLStateGuard::new(gl);
+            let mut color_mask = [gl::TRUE; 4];
+            gl.GetBooleanv(gl::COLOR_WRITEMASK, color_mask.as_mut_ptr());
+
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl.Disable(gl::SCISSOR_TEST);
+            gl.ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            gl.ClearColor(color[0], color[1], color[2], color[3]);
+            gl.Clear(gl::COLOR_BUFFER_BIT);
+            gl.ColorMask(color_mask[0], color_mask[1], color_mask[2], color_mask[3]);
+        }
+        Ok(())
+    }
+
+    /// Regenerates the mipmap levels of a surface from its base level.
+    ///
+    /// Call this after rendering to a surface created with `generate_mipmaps` set in its
+    /// `AngleSurfaceOptions`, and before sampling from it. The surface must not be
+    /// bound to a context; it is temporarily bound to a texture in the given context, which must
+    /// be the context the surface was created for, or an `IncompatibleSurface` error is returned.
+    /// For surfaces without mipmaps this does nothing.
+    pub fn generate_surface_mipmaps(
+        &self,
+        context: &Context,
+        surface: &Surface,
+    ) -> Result<(), Error> {
+        device_span!(
+            "generate_surface_mipmaps",
+            context = self.context_id(context).0
+        );
+        if surface.context_id != context.id {
+            return Err(Error::IncompatibleSurface);
+        }
+        let synchronization = match surface.win32_objects {
+            Win32Objects::Pbuffer {
+                ref synchronization,
+                ..
+            } if surface.mipmapped => synchronization,
+            _ => return Ok(()),
+        };
+
+        let _guard = self.temporarily_make_context_current(context)?;
+        unsafe {
+            self.modify_surface_texture(context, surface, synchronization, |gl| {
+                gl.GenerateMipmap(gl::TEXTURE_2D)
+            })
+        }
+    }
+
+    // Binds a pbuffer surface to a temporary `GL_TEXTURE_2D` texture in the given context, which
+    // must be current, and runs the closure with it bound. Then makes the changes visible to
+    // surface textures in other contexts. The surface must not be bound to a context.
+    unsafe fn modify_surface_texture(
+        &self,
+        context: &Context,
+        surface: &Surface,
+        synchronization: &Synchronization,
+        f: impl FnOnce(&Gl),
+    ) -> Result<(), Error> {
+        EGL_FUNCTIONS.with(|egl| {
+            let gl = context.gl();
+            let _state_guard = GLStateGuard::new(gl);
+            if let Synchronization::KeyedMutex(ref keyed_mutex) = *synchronization {
+                let result = keyed_mutex.AcquireSync(0, INFINITE);
+                assert_eq!(result, S_OK);
+            }
+
+            let mut texture = 0;
+            gl.GenTextures(1, &mut texture);
+            gl.BindTexture(gl::TEXTURE_2D, texture);
+            let result = if egl.BindTexImage(
+                self.egl_display,
+                surface.egl_surface,
+                egl::BACK_BUFFER as GLint,
+            ) == egl::FALSE
+            {
+                Err(egl_error(
+                    egl.GetError(),
+                    Error::SurfaceTextureCreationFailed,
+                ))
+            } else {
+                f(gl);
+                egl.ReleaseTexImage(
+                    self.egl_display,
+                    surface.egl_surface,
+                    egl::BACK_BUFFER as GLint,
+                );
+                Ok(())
+            };
+            gl.DeleteTextures(1, &texture);
+
+            match *synchronization {
+                Synchronization::KeyedMutex(ref keyed_mutex) => {
+                    let result = keyed_mutex.ReleaseSync(0);
+                    assert_eq!(result, S_OK);
+                }
+                Synchronization::GLFinish => gl.Finish(),
+                Synchronization::None => {}
+            }
+            result
+        })
+    }
+
+    // Makes the given surface the context's default framebuffer until the returned guard is
+    // dropped. The surface need not be bound to the context.
+    fn temporarily_make_surface_current(
+        &self,
+        context: &Context,
+        surface: &Surface,
+    ) -> Result<CurrentContextGuard, Error> {
+        let guard = CurrentContextGuard::new();
+        EGL_FUNCTIONS.with(|egl| unsafe {
+            let result = egl.MakeCurrent(
+                self.egl_display,
+                surface.egl_surface,
+                surface.egl_surface,
+                context.egl_context,
+            );
+            if result == egl::FALSE {
+                let err = egl.GetError();
+                return Err(context::make_current_error(
+                    self.egl_display,
+                    surface.egl_surface,
+                    context.egl_context,
+                    err,
+                ));
+            }
+            Ok(guard)
+        })
+    }
+
+    /// Reads back the RGBA pixels of the surface bound to the given context, converting them to
+    /// the given alpha type.
+    ///
+    /// Rows are returned bottom to top, as `glReadPixels()` does. If the context has no surface
+    /// bound, a `NoSurfaceAttached` error is returned.
+    pub fn read_surface_pixels(
+        &self,
+        context: &Context,
+        alpha_type: AlphaType,
+    ) -> Result<Vec<u8>, Error> {
+        device_span!("read_surface_pixels", context = self.context_id(context).0);
+        let surface = match context.framebuffer {
+            Framebuffer::Surface(ref surface) => surface,
+            Framebuffer::External(_) => return Err(Error::ExternalRenderTarget),
+            Framebuffer::None => return Err(Error::NoSurfaceAttached),
+        };
+
+        let _guard = self.temporarily_make_context_current(context)?;
+        let size = surface.size;
+        let mut pixels = vec![0; size.width as usize * size.height as usize * 4];
+        let gl = context.gl();
+        unsafe {
+            let _state_guard = GLStateGuard::new(gl);
+            let mut old_pack_alignment = 0;
+            gl.GetIntegerv(gl::PACK_ALIGNMENT, &mut old_pack_alignment);
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl.ReadPixels(
+                0,
+                0,
+                size.width,
+                size.height,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut c_void,
+            );
+            gl.PixelStorei(gl::PACK_ALIGNMENT, old_pack_alignment);
+        }
+
+        AlphaType::convert_pixels(&mut pixels, surface.alpha_type, alpha_type);
+        Ok(pixels)
+    }
+
+    /// Copies the contents of a surface into a level of an application-owned `GL_TEXTURE_2D`
+    /// texture in the given context.
+    ///
+    /// The whole surface is copied to the origin of the texture level with
+    /// `glCopyTexSubImage2D()`, so the level must already be allocated, at least as large as the
+    /// surface, and in a format compatible with the surface's. If it isn't, OpenGL rejects the
+    /// copy and a `TextureCopyFailed` error is returned with the OpenGL error. The surface must
+    /// not be bound to a context, and it must have been created for the given context, or an
+    /// `IncompatibleSurface` error is returned. The context's OpenGL state is left as it was.
+    pub fn copy_surface_to_gl_texture(
+        &self,
+        context: &Context,
+        surface: &Surface,
+        dest_texture: GLuint,
+        dest_level: GLint,
+    ) -> Result<(), Error> {
+        device_span!(
+            "copy_surface_to_gl_texture",
+            context = self.context_id(context).0
+        );
+        if surface.context_id != context.id {
+            return Err(Error::IncompatibleSurface);
+        }
+        if surface.usage == SurfaceUsage::TextureOnly {
+            return Err(Error::SurfaceNotRenderable);
+        }
+
+        let _guard = self.temporarily_make_surface_current(context, surface)?;
+        let gl = context.gl();
+        unsafe {
+            let _state_guard = GLStateGuard::new(gl);
+            gl_utils::drain_gl_errors(gl);
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl.BindTexture(gl::TEXTURE_2D, dest_texture);
+            gl.CopyTexSubImage2D(
+                gl::TEXTURE_2D,
+                dest_level,
+                0,
+                0,
+                0,
+                0,
+                surface.size.width,
+                surface.size.height,
+            );
+            match gl.GetError() {
+                gl::NO_ERROR => Ok(()),
+                error => Err(Error::TextureCopyFailed(error)),
+            }
+        }
+    }
+
+    /// Copies a generic surface into a widget surface of the same context, scaling it to fit.
+    ///
+    /// This is the usual way to put an offscreen rendering on screen: render to a generic
+    /// surface, blit it to the window with this method, and then present the widget surface. The
+    /// widget surface is bound for drawing and the generic surface for reading, and the copy is
+    /// made with `glBlitFramebuffer()` using the given filter. Afterward, the context and
+    /// surfaces that were current before are current again, and the context's OpenGL state is
+    /// left as it was.
+    ///
+    /// If the destination isn't a widget surface, a `NoWidgetAttached` error is returned. Both
+    /// surfaces must have been created for the given context, and neither can be bound to it.
+    pub fn blit_surface_to_window(
+        &self,
+        context: &Context,
+        src: &Surface,
+        dst_widget_surface: &mut Surface,
+        filter: BlitFilter,
+    ) -> Result<(), Error> {
+        device_span!(
+            "blit_surface_to_window",
+            context = self.context_id(context).0
+        );
+        match dst_widget_surface.win32_objects {
+            Win32Objects::Window | Win32Objects::SwapChain { .. } => {}
+            Win32Objects::Pbuffer { .. } => return Err(Error::NoWidgetAttached),
+        }
+
+        let _previous_binding = PreviousBinding::new(self.egl_display);
+        self.make_context_current_with_surfaces(context, dst_widget_surface, src)?;
+        let gl = context.gl();
+        unsafe {
+            let _state_guard = GLStateGuard::new(gl);
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+            gl.Disable(gl::SCISSOR_TEST);
+            gl.BlitFramebuffer(
+                0,
+                0,
+                src.size.width,
+                src.size.height,
+                0,
+                0,
+                dst_widget_surface.size.width,
+                dst_widget_surface.size.height,
+                gl::COLOR_BUFFER_BIT,
+                match filter {
+                    BlitFilter::Nearest => gl::NEAREST,
+                    BlitFilter::Linear => gl::LINEAR,
+                },
+            );
+        }
+        Ok(())
+    }
+
     /// Returns the OpenGL texture object containing the contents of this surface.
     ///
     /// It is only legal to read from, not write to, this texture object.
@@ -591,12 +2380,25 @@ impl Device {
     pub fn surface_texture_object(&self, surface_texture: &SurfaceTexture) -> GLuint {
         surface_texture.gl_texture
     }
+
+    /// Returns the OpenGL texture target and texture object needed to sample from this surface
+    /// texture, in that order.
+    ///
+    /// This is equivalent to calling `surface_gl_texture_target()` and `surface_texture_object()`
+    /// together. The target is always `GL_TEXTURE_2D`.
+    #[inline]
+    pub fn surface_texture_binding(&self, surface_texture: &SurfaceTexture) -> (GLenum, GLuint) {
+        (
+            SURFACE_GL_TEXTURE_TARGET,
+            self.surface_texture_object(surface_texture),
+        )
+    }
 }
 
 impl Surface {
     #[inline]
-    fn id(&self) -> SurfaceID {
-        SurfaceID(self.egl_surface as usize)
+    pub(crate) fn id(&self) -> SurfaceID {
+        self.id
     }
 
     #[inline]
@@ -615,3 +2417,128 @@ impl Surface {
 pub struct SurfaceDataGuard<'a> {
     phantom: PhantomData<&'a ()>,
 }
+
+/// A CPU-writable mapping of a surface's pixels, returned by `map_surface_write()`.
+///
+/// The pixels are copied into the surface when this guard is dropped.
+pub struct SurfaceWriteGuard<'a> {
+    data: *mut u8,
+    len: usize,
+    row_pitch: usize,
+    device_context: ComPtr<d3d11::ID3D11DeviceContext>,
+    staging_texture: ComPtr<d3d11::ID3D11Texture2D>,
+    texture: ComPtr<d3d11::ID3D11Texture2D>,
+    keyed_mutex: Option<ComPtr<IDXGIKeyedMutex>>,
+    phantom: PhantomData<&'a mut Surface>,
+}
+
+impl<'a> SurfaceWriteGuard<'a> {
+    /// Returns the mapped pixels.
+    ///
+    /// Rows are `row_pitch()` bytes apart, which may be more than the width of the surface.
+    #[inline]
+    pub fn data(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.data, self.len) }
+    }
+
+    /// Returns the distance in bytes between the starts of consecutive rows.
+    #[inline]
+    pub fn row_pitch(&self) -> usize {
+        self.row_pitch
+    }
+}
+
+impl<'a> Drop for SurfaceWriteGuard<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            self.device_context.Unmap(
+                self.staging_texture.as_raw() as *mut d3d11::ID3D11Resource,
+                0,
+            );
+            copy_texture(
+                &self.device_context,
+                &self.texture,
+                &self.staging_texture,
+                self.keyed_mutex.as_ref(),
+            );
+            self.device_context.Flush();
+        }
+    }
+}
+
+// Copies one Direct3D texture to another, holding the keyed mutex if the textures are shared.
+unsafe fn copy_texture(
+    device_context: &ComPtr<d3d11::ID3D11DeviceContext>,
+    destination: &ComPtr<d3d11::ID3D11Texture2D>,
+    source: &ComPtr<d3d11::ID3D11Texture2D>,
+    keyed_mutex: Option<&ComPtr<IDXGIKeyedMutex>>,
+) {
+    if let Some(keyed_mutex) = keyed_mutex {
+        let result = keyed_mutex.AcquireSync(0, INFINITE);
+        assert_eq!(result, S_OK);
+    }
+    device_context.CopyResource(
+        destination.as_raw() as *mut d3d11::ID3D11Resource,
+        source.as_raw() as *mut d3d11::ID3D11Resource,
+    );
+    if let Some(keyed_mutex) = keyed_mutex {
+        let result = keyed_mutex.ReleaseSync(0);
+        assert_eq!(result, S_OK);
+    }
+}
+
+// Interprets the result of `IDXGISwapChain::Present()`. `DXGI_STATUS_OCCLUDED` counts as success,
+// so it has to be picked out before the generic `SUCCEEDED()` test.
+pub(crate) fn present_result(result: HRESULT) -> Result<PresentResult, Error> {
+    if result == DXGI_STATUS_OCCLUDED {
+        Ok(PresentResult::Occluded)
+    } else if winerror::SUCCEEDED(result) {
+        Ok(PresentResult::Presented)
+    } else {
+        Err(Error::PresentFailed(WindowingApiError::Failed))
+    }
+}
+
+// Estimates the memory used by a surface's color and depth/stencil buffers. Direct3D has no
+// 3-byte formats, so pixel sizes are rounded up to a power of two bytes. Only the color buffer
+// has a mipmap chain.
+pub(crate) fn estimate_surface_memory_bytes(
+    size: Size2D<i32>,
+    color_bits: EGLint,
+    depth_stencil_bits: EGLint,
+    samples: EGLint,
+    mipmapped: bool,
+) -> u64 {
+    fn bytes_per_pixel(bits: EGLint) -> u64 {
+        match (bits.max(0) as u64 + 7) / 8 {
+            0 => 0,
+            bytes => bytes.next_power_of_two(),
+        }
+    }
+
+    let (width, height) = (size.width.max(1) as u64, size.height.max(1) as u64);
+    let mut color_texels = width * height;
+    if mipmapped {
+        let (mut level_width, mut level_height) = (width, height);
+        while level_width > 1 || level_height > 1 {
+            level_width = (level_width / 2).max(1);
+            level_height = (level_height / 2).max(1);
+            color_texels += level_width * level_height;
+        }
+    }
+
+    let samples = samples.max(1) as u64;
+    (color_texels * bytes_per_pixel(color_bits)
+        + width * height * bytes_per_pixel(depth_stencil_bits))
+        * samples
+}
+
+// Returns the length of RGBA8 pixel data covering a surface of the given size.
+fn pixel_data_len(size: Size2D<i32>) -> usize {
+    size.width as usize * size.height as usize * 4
+}
+
+// Allocates an ID for a newly created surface.
+fn next_surface_id() -> SurfaceID {
+    SurfaceID(NEXT_SURFACE_ID.fetch_add(1, Ordering::Relaxed))
+}
@@ -167,6 +167,7 @@ impl Device {
         &self,
         attributes: &ContextAttributes,
     ) -> Result<ContextDescriptor, Error> {
+        device_span!("create_context_descriptor", flags = ?attributes.flags);
         let flags = attributes.flags;
         let alpha_bits = if flags.contains(ContextAttributeFlags::ALPHA) {
             8
@@ -248,6 +249,7 @@ impl Device {
         descriptor: &ContextDescriptor,
         share_with: Option<&Context>,
     ) -> Result<Context, Error> {
+        device_span!("create_context", shared = share_with.is_some());
         let wglCreateContextAttribsARB = match WGL_EXTENSION_FUNCTIONS.CreateContextAttribsARB {
             None => return Err(Error::RequiredExtensionUnavailable),
             Some(wglCreateContextAttribsARB) => wglCreateContextAttribsARB,
@@ -322,6 +324,7 @@ impl Device {
         &self,
         native_context: NativeContext,
     ) -> Result<Context, Error> {
+        device_span!("create_context_from_native_context");
         let mut next_context_id = CREATE_CONTEXT_MUTEX.lock().unwrap();
         let hidden_window = HiddenWindow::new();
 
@@ -351,6 +354,7 @@ impl Device {
     ///
     /// The context must have been created on this device.
     pub fn destroy_context(&self, context: &mut Context) -> Result<(), Error> {
+        device_span!("destroy_context", context = self.context_id(context).0);
         if context.status == ContextStatus::Destroyed {
             return Ok(());
         }
@@ -436,6 +440,7 @@ impl Device {
             let mut attributes = ContextAttributes {
                 version: context_descriptor.gl_version,
                 flags: ContextAttributeFlags::empty(),
+                ..Default::default()
             };
             if alpha_bits > 0 {
                 attributes.flags.insert(ContextAttributeFlags::ALPHA);
@@ -476,6 +481,7 @@ impl Device {
     ///
     /// After calling this function, it is valid to use OpenGL rendering commands.
     pub fn make_context_current(&self, context: &Context) -> Result<(), Error> {
+        device_span!("make_context_current", context = self.context_id(context).0);
         unsafe {
             let dc_guard = self.get_context_dc(context);
             let ok = wglMakeCurrent(dc_guard.dc, context.glrc);
@@ -493,6 +499,7 @@ impl Device {
     /// made current.
     #[inline]
     pub fn make_no_context_current(&self) -> Result<(), Error> {
+        device_span!("make_no_context_current");
         unsafe {
             let ok = wglMakeCurrent(ptr::null_mut(), ptr::null_mut());
             if ok != FALSE {
@@ -535,6 +542,11 @@ impl Device {
         context: &mut Context,
         surface: Surface,
     ) -> Result<(), (Error, Surface)> {
+        device_span!(
+            "bind_surface_to_context",
+            context = self.context_id(context).0,
+            size = ?self.surface_info(&surface).size,
+        );
         if context.id != surface.context_id {
             return Err((Error::IncompatibleSurface, surface));
         }
@@ -566,6 +578,10 @@ impl Device {
         &self,
         context: &mut Context,
     ) -> Result<Option<Surface>, Error> {
+        device_span!(
+            "unbind_surface_from_context",
+            context = self.context_id(context).0
+        );
         match mem::replace(&mut context.framebuffer, Framebuffer::None) {
             Framebuffer::Surface(surface) => {
                 self.unlock_surface(&surface);
@@ -30,6 +30,17 @@ pub struct SurfaceInfo {
     pub framebuffer_object: GLuint,
 }
 
+/// What can be determined about a render target that surfman didn't create, such as the surface
+/// of an adopted native context.
+pub struct ExternalSurfaceInfo {
+    /// The surface's size, in device pixels, or `None` if the windowing system can't report it.
+    pub size: Option<Size2D<i32>>,
+    /// The ID of the context that renders to this surface.
+    pub context_id: ContextID,
+    /// The OpenGL framebuffer object that can be used to render to this surface.
+    pub framebuffer_object: GLuint,
+}
+
 // The default framebuffer for a context.
 #[allow(dead_code)]
 pub(crate) enum Framebuffer<S, E> {
@@ -43,13 +54,15 @@ pub(crate) enum Framebuffer<S, E> {
 
 /// A unique ID per allocated surface.
 ///
-/// If you destroy a surface and then create a new one, the ID may be reused.
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// ANGLE surfaces take their IDs from a counter, so an ID is never reused. Other backends derive
+/// the ID from the native surface, so if you destroy a surface and then create a new one there,
+/// the ID may be reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct SurfaceID(pub usize);
 
 impl Display for SurfaceID {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "{:?}", *self)
+        write!(f, "{}", self.0)
     }
 }
 
@@ -92,6 +105,83 @@ pub enum SurfaceAccess {
     GPUCPUWriteCombined,
 }
 
+/// Specifies how a generic surface will be used.
+///
+/// Surfaces that are only ever sampled from don't need the depth and stencil buffers that a
+/// render target does, so marking them as texture-only saves memory. This is worthwhile for large
+/// read-only surfaces such as atlases.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SurfaceUsage {
+    /// The surface can be bound to a context and rendered to, and it can be wrapped in a surface
+    /// texture.
+    ///
+    /// This is the default.
+    RenderTarget,
+
+    /// The surface can only be wrapped in a surface texture for sampling.
+    ///
+    /// Binding a texture-only surface to a context returns a `SurfaceNotRenderable` error.
+    TextureOnly,
+}
+
+/// Whether a surface's color channels have been multiplied by its alpha channel.
+///
+/// Compositing a surface under the wrong assumption produces dark fringes or washed-out edges, so
+/// compositors need to know which kind they're getting. OpenGL writes whatever values are
+/// rendered, so the alpha type is a declaration about the surface's contents rather than
+/// something that changes how rendering works.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AlphaType {
+    /// Color channels are premultiplied by alpha, as most compositors expect.
+    ///
+    /// This is the default.
+    Premultiplied,
+
+    /// Color channels are independent of alpha.
+    Straight,
+}
+
+impl AlphaType {
+    // Converts RGBA8 pixels from one alpha type to another in place.
+    #[allow(dead_code)]
+    pub(crate) fn convert_pixels(pixels: &mut [u8], from: AlphaType, to: AlphaType) {
+        match (from, to) {
+            (AlphaType::Straight, AlphaType::Premultiplied) => {
+                for pixel in pixels.chunks_mut(4) {
+                    let alpha = pixel[3] as u32;
+                    for channel in &mut pixel[0..3] {
+                        *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+                    }
+                }
+            }
+            (AlphaType::Premultiplied, AlphaType::Straight) => {
+                for pixel in pixels.chunks_mut(4) {
+                    let alpha = pixel[3] as u32;
+                    if alpha == 0 {
+                        continue;
+                    }
+                    for channel in &mut pixel[0..3] {
+                        *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+bitflags! {
+    /// The buffers of a surface whose contents may be discarded.
+    pub struct InvalidateFlags: u8 {
+        /// The color buffer.
+        const COLOR   = 0x01;
+        /// The depth buffer.
+        const DEPTH   = 0x02;
+        /// The stencil buffer.
+        const STENCIL = 0x04;
+    }
+}
+
 /// Information specific to the type of surface: generic or widget.
 pub enum SurfaceType<NativeWidget> {
     /// An off-screen surface that has a pixel size. Generic surfaces can sometimes be shown on
@@ -14,11 +14,14 @@ use super::device::{Adapter, Device};
 use super::surface::Surface;
 use crate::gl;
 use crate::gl::types::{GLenum, GLuint};
+use crate::gl_utils;
+use crate::WindowingApiError;
+use crate::{ColorBits, ContextPriority, FlushBehavior, ResetNotification, SurfaceType};
 use crate::{ContextAttributeFlags, ContextAttributes, Error, GLApi, GLVersion, Gl, SurfaceAccess};
-use crate::{SurfaceType, WindowingApiError};
 
 use euclid::default::Size2D;
 use std::os::raw::c_void;
+use std::ptr::{self, NonNull};
 use std::sync::mpsc;
 use std::thread;
 
@@ -105,7 +108,11 @@ pub fn test_context_creation() {
     for &version in versions {
         for flag_bits in 0..(ContextAttributeFlags::all().bits() + 1) {
             let flags = ContextAttributeFlags::from_bits_truncate(flag_bits);
-            let attributes = ContextAttributes { version, flags };
+            let attributes = ContextAttributes {
+                version,
+                flags,
+                ..Default::default()
+            };
             let descriptor = match device.create_context_descriptor(&attributes) {
                 Ok(descriptor) => descriptor,
                 Err(Error::UnsupportedGLProfile) | Err(Error::UnsupportedGLVersion) => {
@@ -179,6 +186,7 @@ pub fn test_newly_created_contexts_are_not_current() {
         .create_context_descriptor(&ContextAttributes {
             version: GLVersion::new(3, 0),
             flags: ContextAttributeFlags::empty(),
+            ..Default::default()
         })
         .unwrap();
 
@@ -247,6 +255,7 @@ pub fn test_context_sharing() {
         .create_context_descriptor(&ContextAttributes {
             version: GLVersion::new(3, 0),
             flags: ContextAttributeFlags::empty(),
+            ..Default::default()
         })
         .unwrap();
 
@@ -287,6 +296,7 @@ pub fn test_generic_surface_creation() {
         .create_context_descriptor(&ContextAttributes {
             version: GLVersion::new(3, 0),
             flags: ContextAttributeFlags::empty(),
+            ..Default::default()
         })
         .unwrap();
 
@@ -748,6 +758,7 @@ pub fn test_depth_and_stencil() {
         .create_context_descriptor(&ContextAttributes {
             version: GLVersion::new(3, 0),
             flags: ContextAttributeFlags::DEPTH,
+            ..Default::default()
         })
         .unwrap();
 
@@ -795,6 +806,7 @@ pub fn test_depth_and_stencil() {
         .create_context_descriptor(&ContextAttributes {
             version: GLVersion::new(3, 0),
             flags: ContextAttributeFlags::STENCIL,
+            ..Default::default()
         })
         .unwrap();
 
@@ -865,178 +877,4396 @@ pub fn test_get_native_context() {
     env.device.destroy_context(&mut env.context).unwrap();
 }
 
-fn bind_context_fbo(gl: &Gl, device: &Device, context: &Context) {
-    unsafe {
-        gl.BindFramebuffer(gl::FRAMEBUFFER, context_fbo(device, context));
-        check_gl(&gl);
+// Tests that allocation failures during surface creation are distinguished from other failures.
+#[cfg(any(android, angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_creation_bad_alloc_error() {
+    use crate::egl;
+    use crate::egl::types::EGLint;
+    use crate::platform::generic::egl::error::surface_creation_error;
+
+    match surface_creation_error(egl::BAD_ALLOC as EGLint) {
+        Error::OutOfSurfaceMemory => {}
+        err => panic!("Expected `OutOfSurfaceMemory` but got {:?}", err),
+    }
+    match surface_creation_error(egl::BAD_MATCH as EGLint) {
+        Error::SurfaceCreationFailed(WindowingApiError::BadMatch) => {}
+        err => panic!(
+            "Expected `SurfaceCreationFailed(BadMatch)` but got {:?}",
+            err
+        ),
     }
 }
 
-fn context_fbo(device: &Device, context: &Context) -> GLuint {
-    device
-        .context_surface_info(context)
+// Tests that EGL configs can be enumerated and used to create contexts directly.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_enumerate_configs() {
+    use crate::egl;
+    use crate::egl::types::EGLint;
+
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let device = connection.create_device(&adapter).unwrap();
+
+    let configs = device.enumerate_configs().unwrap();
+    assert!(!configs.is_empty());
+    for config in &configs {
+        assert!(config.config_id > 0);
+        assert!(config.red_size >= 0 && config.green_size >= 0 && config.blue_size >= 0);
+        assert!(config.alpha_size >= 0 && config.depth_size >= 0 && config.stencil_size >= 0);
+        assert!(config.samples >= 0);
+    }
+
+    let config = configs
+        .iter()
+        .find(|config| {
+            config.renderable_type & egl::OPENGL_ES2_BIT as EGLint != 0
+                && config.surface_type & egl::PBUFFER_BIT as EGLint != 0
+        })
+        .expect("No GLES 2 pbuffer config found!");
+    let mut context = device
+        .create_context_with_config_id(config.config_id, GLVersion::new(2, 0))
+        .unwrap();
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that an application-provided config chooser picks the config it scores highest.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_create_context_with_chooser() {
+    use super::context::ConfigInfo;
+    use crate::egl;
+    use crate::egl::types::EGLint;
+
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let device = connection.create_device(&adapter).unwrap();
+
+    let usable = |config: &ConfigInfo| {
+        config.renderable_type & egl::OPENGL_ES2_BIT as EGLint != 0
+            && config.surface_type & egl::PBUFFER_BIT as EGLint != 0
+    };
+    let max_depth = device
+        .enumerate_configs()
         .unwrap()
+        .iter()
+        .filter(|config| usable(config))
+        .map(|config| config.depth_size)
+        .max()
+        .expect("No GLES 2 pbuffer config found!");
+
+    let attributes = ContextAttributes {
+        version: GLVersion::new(2, 0),
+        flags: ContextAttributeFlags::empty(),
+        ..Default::default()
+    };
+    let mut context = device
+        .create_context_with_chooser(&attributes, |config| {
+            if usable(config) {
+                Some(config.depth_size as i64)
+            } else {
+                None
+            }
+        })
+        .unwrap();
+    let context_descriptor = device.context_descriptor(&context);
+    let chosen = device
+        .enumerate_configs()
         .unwrap()
-        .framebuffer_object
+        .into_iter()
+        .find(|config| config.config_id == context_descriptor.egl_config_id)
+        .unwrap();
+    assert_eq!(chosen.depth_size, max_depth);
+    device.destroy_context(&mut context).unwrap();
+
+    // A chooser that rejects everything finds nothing.
+    match device.create_context_with_chooser(&attributes, |_| None) {
+        Err(Error::NoPixelFormatFound) => {}
+        Err(err) => panic!("Expected `NoPixelFormatFound` but got {:?}", err),
+        Ok(mut context) => {
+            device.destroy_context(&mut context).unwrap();
+            panic!("Created a context with no acceptable config!");
+        }
+    }
 }
 
-fn make_surface(device: &mut Device, context: &Context) -> Surface {
-    device
-        .create_surface(
-            &context,
+// Tests that texture-only surfaces can be sampled from but not rendered to.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_texture_only_surface() {
+    use super::surface::AngleSurfaceOptions;
+    use crate::SurfaceUsage;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let surface = env
+        .device
+        .create_surface_with_options(
+            &env.context,
             SurfaceAccess::GPUOnly,
             SurfaceType::Generic {
                 size: Size2D::new(640, 480),
             },
+            &AngleSurfaceOptions {
+                usage: SurfaceUsage::TextureOnly,
+                ..Default::default()
+            },
         )
+        .unwrap();
+
+    // Texture-only surfaces can be sampled from.
+    let surface_texture = env
+        .device
+        .create_surface_texture(&mut env.context, surface)
+        .unwrap();
+    assert_ne!(env.device.surface_texture_object(&surface_texture), 0);
+    let surface = env
+        .device
+        .destroy_surface_texture(&mut env.context, surface_texture)
+        .unwrap();
+
+    // But they can't be bound as a render target.
+    let mut surface = match env
+        .device
+        .bind_surface_to_context(&mut env.context, surface)
+    {
+        Err((Error::SurfaceNotRenderable, surface)) => surface,
+        Err((err, _)) => panic!("Expected `SurfaceNotRenderable` but got {:?}", err),
+        Ok(()) => panic!("Texture-only surface was bound to a context!"),
+    };
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that waiting for a surface makes its rendering visible.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_wait_for_surface() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    clear(&env.gl, &[255, 0, 0, 255]);
+    let surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
         .unwrap()
+        .unwrap();
+    env.device.wait_for_surface(&env.context, &surface).unwrap();
+
+    env.device
+        .bind_surface_to_context(&mut env.context, surface)
+        .unwrap();
+    env.device.make_context_current(&env.context).unwrap();
+    bind_context_fbo(&env.gl, &env.device, &env.context);
+    assert_eq!(get_pixel_from_bottom_row(&env.gl), [255, 0, 0, 255]);
+
+    env.device.destroy_context(&mut env.context).unwrap();
 }
 
-fn blit_fbo(gl: &Gl, dest_fbo: GLuint, src_fbo: GLuint) {
-    unsafe {
-        gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, dest_fbo);
-        check_gl(gl);
-        gl.BindFramebuffer(gl::READ_FRAMEBUFFER, src_fbo);
-        check_gl(gl);
-        gl.BlitFramebuffer(
-            0,
-            0,
-            640,
-            480,
-            0,
-            0,
-            640,
-            480,
-            gl::COLOR_BUFFER_BIT,
-            gl::NEAREST,
-        );
-        check_gl(gl);
-    }
+// Tests that an existing EGL display can be wrapped without taking ownership of it.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_device_from_egl_display() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = connection.create_device(&adapter).unwrap();
+    let attributes = ContextAttributes {
+        version: GLVersion::new(3, 0),
+        flags: ContextAttributeFlags::empty(),
+        ..Default::default()
+    };
+
+    let egl_display = device.egl_display;
+    let egl_device = Device::egl_device_from_egl_display(egl_display).unwrap();
+    let mut wrapper = unsafe { Device::from_egl_display(egl_display, egl_device).unwrap() };
+    let descriptor = wrapper.create_context_descriptor(&attributes).unwrap();
+    let mut context = wrapper.create_context(&descriptor, None).unwrap();
+    wrapper.destroy_context(&mut context).unwrap();
+    drop(wrapper);
+
+    // Dropping the wrapper must not have terminated the display.
+    let descriptor = device.create_context_descriptor(&attributes).unwrap();
+    let mut context = device.create_context(&descriptor, None).unwrap();
+    device.destroy_context(&mut context).unwrap();
 }
 
-fn make_fbo(gl: &Gl, texture_target: GLenum, texture: GLuint) -> GLuint {
-    unsafe {
-        let mut framebuffer_object = 0;
-        gl.GenFramebuffers(1, &mut framebuffer_object);
-        check_gl(&gl);
-        gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer_object);
-        check_gl(&gl);
-        gl.FramebufferTexture2D(
-            gl::FRAMEBUFFER,
-            gl::COLOR_ATTACHMENT0,
-            texture_target,
-            texture,
-            0,
-        );
-        check_gl(&gl);
-        assert_eq!(
-            gl.CheckFramebufferStatus(gl::FRAMEBUFFER),
-            gl::FRAMEBUFFER_COMPLETE
-        );
-        framebuffer_object
-    }
+// Tests that OpenGL implementation information is gathered when contexts are created, and that
+// gathering it again gives the same answers without disturbing the current binding.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_gl_info() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let gl_info = env.device.context_gl_info(&env.context).clone();
+    assert!(!gl_info.vendor().is_empty());
+    assert!(gl_info.renderer().contains("ANGLE"));
+    assert!(gl_info.shading_language_version().major >= 1);
+
+    env.device.make_no_context_current().unwrap();
+    env.device
+        .repopulate_context_gl_info(&mut env.context)
+        .unwrap();
+    assert!(NativeContext::current().is_err());
+    let new_gl_info = env.device.context_gl_info(&env.context);
+    assert_eq!(new_gl_info.vendor(), gl_info.vendor());
+    assert_eq!(new_gl_info.renderer(), gl_info.renderer());
+    assert_eq!(
+        new_gl_info.shading_language_version(),
+        gl_info.shading_language_version()
+    );
+
+    env.device.destroy_context(&mut env.context).unwrap();
 }
 
-struct BasicEnvironment {
-    connection: Connection,
-    adapter: Adapter,
-    device: Device,
-    context_descriptor: ContextDescriptor,
-    context: Context,
-    gl: Gl,
+// Tests that version numbers are extracted from the various formats drivers report.
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_parse_version_string() {
+    use crate::info::parse_version_string;
+
+    assert_eq!(
+        parse_version_string("OpenGL ES GLSL ES 3.00 (ANGLE 2.1.0)"),
+        Some(GLVersion::new(3, 0))
+    );
+    assert_eq!(
+        parse_version_string("4.60 NVIDIA"),
+        Some(GLVersion::new(4, 60))
+    );
+    assert_eq!(
+        parse_version_string("OpenGL ES 3.1 Mesa 21.0.3"),
+        Some(GLVersion::new(3, 1))
+    );
+    assert_eq!(parse_version_string("unknown"), None);
 }
 
-impl BasicEnvironment {
-    fn new() -> Option<BasicEnvironment> {
-        let connection = Connection::new().unwrap();
-        let adapter = connection
-            .create_low_power_adapter()
-            .expect("Failed to create adapter!");
-        let mut device = match connection.create_device(&adapter) {
-            Ok(device) => device,
-            Err(Error::RequiredExtensionUnavailable) => {
-                // Can't run these tests on this hardware.
-                return None;
-            }
-            Err(err) => panic!("Failed to create device: {:?}", err),
-        };
+// Tests that GL versions are ordered by major version, then minor version.
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_gl_version_ordering() {
+    assert!(GLVersion::new(3, 3) > GLVersion::new(3, 2));
+    assert!(GLVersion::new(4, 0) > GLVersion::new(3, 3));
+    assert!(GLVersion::new(2, 1) < GLVersion::new(3, 0));
+    assert!(GLVersion::new(10, 0) > GLVersion::new(9, 9));
+    assert_eq!(
+        GLVersion::new(3, 3).cmp(&GLVersion::GL_3_3),
+        std::cmp::Ordering::Equal
+    );
+
+    assert!(GLVersion::GL_4_6.supports(GLVersion::GL_3_3));
+    assert!(GLVersion::GL_3_3.supports(GLVersion::GL_3_3));
+    assert!(!GLVersion::GL_3_2.supports(GLVersion::GL_3_3));
+    assert!(!GLVersion::GLES_3_0.supports(GLVersion::GLES_3_1));
+
+    let mut versions = GLVersion::probe_ladder(GLApi::GL).to_vec();
+    versions.sort();
+    assert_eq!(versions.first(), Some(&GLVersion::GL_2_1));
+    assert_eq!(versions.last(), Some(&GLVersion::GL_4_6));
+}
 
-        let context_descriptor = device
-            .create_context_descriptor(&ContextAttributes {
-                version: GLVersion::new(3, 0),
-                flags: ContextAttributeFlags::empty(),
-            })
-            .unwrap();
+// Tests that a surface can alternate between being rendered to and sampled from.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_present_surface_texture() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
 
-        let mut context = device.create_context(&context_descriptor, None).unwrap();
-        let surface = make_surface(&mut device, &context);
-        device
-            .bind_surface_to_context(&mut context, surface)
+    unsafe {
+        let mut surface = env
+            .device
+            .unbind_surface_from_context(&mut env.context)
+            .unwrap()
+            .unwrap();
+        let main_surface = make_surface(&mut env.device, &env.context);
+        env.device
+            .bind_surface_to_context(&mut env.context, main_surface)
             .unwrap();
-        device.make_context_current(&context).unwrap();
-
-        let gl = Gl::load_with(|symbol| device.get_proc_address(&context, symbol));
 
-        unsafe {
-            let framebuffer_object = device
-                .context_surface_info(&context)
+        for color in &[[255, 0, 0, 255], [0, 255, 0, 255]] {
+            // Render into the surface.
+            let main_surface = env
+                .device
+                .unbind_surface_from_context(&mut env.context)
                 .unwrap()
+                .unwrap();
+            env.device
+                .bind_surface_to_context(&mut env.context, surface)
+                .unwrap();
+            bind_context_fbo(&env.gl, &env.device, &env.context);
+            clear(&env.gl, color);
+            surface = env
+                .device
+                .unbind_surface_from_context(&mut env.context)
                 .unwrap()
-                .framebuffer_object;
-            gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer_object);
-            gl.Viewport(0, 0, 640, 480);
+                .unwrap();
+            env.device
+                .bind_surface_to_context(&mut env.context, main_surface)
+                .unwrap();
+
+            // Sample from it.
+            let surface_texture = env
+                .device
+                .create_surface_texture(&mut env.context, surface)
+                .unwrap();
+            let main_framebuffer_object = context_fbo(&env.device, &env.context);
+            let mut texture_framebuffer_object = make_fbo(
+                &env.gl,
+                env.device.surface_gl_texture_target(),
+                env.device.surface_texture_object(&surface_texture),
+            );
+            blit_fbo(&env.gl, main_framebuffer_object, texture_framebuffer_object);
+            env.gl
+                .BindFramebuffer(gl::FRAMEBUFFER, main_framebuffer_object);
+            check_gl(&env.gl);
+            assert_eq!(get_pixel_from_bottom_row(&env.gl), *color);
+            env.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+            env.gl
+                .DeleteFramebuffers(1, &mut texture_framebuffer_object);
+
+            // Get the surface back so it can be rendered to again.
+            surface = env
+                .device
+                .present_surface_texture(&mut env.context, surface_texture)
+                .unwrap();
         }
 
-        Some(BasicEnvironment {
-            connection,
-            adapter,
-            device,
-            context_descriptor,
-            context,
-            gl,
-        })
+        env.device
+            .destroy_surface(&mut env.context, &mut surface)
+            .unwrap();
+        env.device.destroy_context(&mut env.context).unwrap();
     }
 }
 
-fn clear(gl: &Gl, color: &[u8; 4]) {
-    unsafe {
-        gl.ClearColor(
-            color[0] as f32 / 255.0,
-            color[1] as f32 / 255.0,
-            color[2] as f32 / 255.0,
-            color[3] as f32 / 255.0,
-        );
-        gl.Clear(gl::COLOR_BUFFER_BIT);
-    }
-}
+// Tests that surfaces created for a depth and stencil context actually have those buffers.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_framebuffer_completeness() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let context_descriptor = env
+        .device
+        .create_context_descriptor(&ContextAttributes {
+            version: GLVersion::new(3, 0),
+            flags: ContextAttributeFlags::DEPTH | ContextAttributeFlags::STENCIL,
+            ..Default::default()
+        })
+        .unwrap();
+    let mut context = env
+        .device
+        .create_context(&context_descriptor, None)
+        .unwrap();
+    let surface = match env.device.create_surface(
+        &context,
+        SurfaceAccess::GPUOnly,
+        SurfaceType::Generic {
+            size: Size2D::new(640, 480),
+        },
+    ) {
+        Ok(surface) => surface,
+        Err(Error::SurfaceFramebufferIncomplete(_)) => {
+            // This config can't back pbuffers with depth and stencil, which is what the error is
+            // for.
+            env.device.destroy_context(&mut context).unwrap();
+            env.device.destroy_context(&mut env.context).unwrap();
+            return;
+        }
+        Err(err) => panic!("Failed to create surface: {:?}", err),
+    };
+    env.device
+        .bind_surface_to_context(&mut context, surface)
+        .unwrap();
+    env.device.make_context_current(&context).unwrap();
+    bind_context_fbo(&env.gl, &env.device, &context);
 
-fn clear_bottom_row(gl: &Gl, color: &[u8; 4]) {
     unsafe {
-        gl.Scissor(0, 0, 640, 1);
-        gl.Enable(gl::SCISSOR_TEST);
-        gl.ClearColor(
-            color[0] as f32 / 255.0,
-            color[1] as f32 / 255.0,
-            color[2] as f32 / 255.0,
-            color[3] as f32 / 255.0,
+        assert_eq!(
+            env.gl.CheckFramebufferStatus(gl::FRAMEBUFFER),
+            gl::FRAMEBUFFER_COMPLETE
         );
-        gl.Clear(gl::COLOR_BUFFER_BIT);
-        gl.Disable(gl::SCISSOR_TEST);
-        gl.Scissor(0, 0, 640, 480);
     }
+
+    env.device.destroy_context(&mut context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
 }
 
-fn get_pixel_from_bottom_row(gl: &Gl) -> [u8; 4] {
-    unsafe {
-        let mut pixel: [u8; 4] = [0; 4];
-        gl.ReadPixels(
-            0,
-            0,
-            1,
-            1,
-            gl::RGBA,
-            gl::UNSIGNED_BYTE,
-            pixel.as_mut_ptr() as *mut c_void,
+// Tests that ANGLE feature overrides can be passed when opening a device.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_device_with_features() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = Device::new_with_features(&adapter, &[], &["disableProgramCaching"]).unwrap();
+
+    let context_descriptor = device
+        .create_context_descriptor(&ContextAttributes {
+            version: GLVersion::new(3, 0),
+            flags: ContextAttributeFlags::empty(),
+            ..Default::default()
+        })
+        .unwrap();
+    let mut context = device.create_context(&context_descriptor, None).unwrap();
+    let surface = make_surface(&mut device, &context);
+    device
+        .bind_surface_to_context(&mut context, surface)
+        .unwrap();
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that a device can be opened with the program cache disabled and still compile shaders.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_device_without_program_cache() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = Device::new_without_program_cache(&adapter).unwrap();
+
+    let context_descriptor = device
+        .create_context_descriptor(&ContextAttributes {
+            version: GLVersion::new(3, 0),
+            flags: ContextAttributeFlags::empty(),
+            ..Default::default()
+        })
+        .unwrap();
+    let mut context = device.create_context(&context_descriptor, None).unwrap();
+    device.prewarm(&context).unwrap();
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that GL work surfman does internally on a context doesn't clobber the application's
+// GL state.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_internal_gl_state_is_preserved() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    unsafe {
+        env.gl.Viewport(1, 2, 30, 40);
+        env.gl.ClearColor(0.25, 0.5, 0.75, 1.0);
+
+        let other_surface = make_surface(&mut env.device, &env.context);
+        let surface_texture = env
+            .device
+            .create_surface_texture(&mut env.context, other_surface)
+            .unwrap();
+
+        let mut viewport = [0; 4];
+        env.gl.GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        assert_eq!(viewport, [1, 2, 30, 40]);
+        let mut clear_color = [0.0; 4];
+        env.gl
+            .GetFloatv(gl::COLOR_CLEAR_VALUE, clear_color.as_mut_ptr());
+        assert_eq!(clear_color, [0.25, 0.5, 0.75, 1.0]);
+        check_gl(&env.gl);
+
+        let mut other_surface = env
+            .device
+            .destroy_surface_texture(&mut env.context, surface_texture)
+            .unwrap();
+        env.device
+            .destroy_surface(&mut env.context, &mut other_surface)
+            .unwrap();
+        env.device.destroy_context(&mut env.context).unwrap();
+    }
+}
+
+// Tests that GL reset statuses and EGL errors are interpreted correctly when polling for context
+// loss.
+#[cfg(any(android, angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_lost_status_interpretation() {
+    use crate::egl;
+    use crate::egl::types::EGLint;
+    use crate::platform::generic::egl::context::{
+        egl_error_means_context_lost, reset_status_means_context_lost,
+    };
+
+    assert!(!reset_status_means_context_lost(gl::NO_ERROR));
+    assert!(reset_status_means_context_lost(0x8253));
+    assert!(reset_status_means_context_lost(0x8254));
+    assert!(reset_status_means_context_lost(0x8255));
+    assert!(!reset_status_means_context_lost(gl::INVALID_ENUM));
+
+    assert!(egl_error_means_context_lost(egl::CONTEXT_LOST as EGLint));
+    assert!(!egl_error_means_context_lost(egl::SUCCESS as EGLint));
+    assert!(!egl_error_means_context_lost(egl::BAD_CONTEXT as EGLint));
+}
+
+// Tests that a freshly created context isn't reported as lost.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_poll_context_lost() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+    assert!(!env.device.poll_context_lost(&env.context));
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that tiled surfaces are split into a grid that exactly covers the surface.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_tile_rects() {
+    use crate::tiled_surface::tile_rects;
+    use euclid::default::{Point2D, Rect};
+
+    let rects = tile_rects(Size2D::new(8192, 8192), Size2D::new(4096, 4096));
+    assert_eq!(rects.len(), 4);
+    assert_eq!(
+        rects[3],
+        Rect::new(Point2D::new(4096, 4096), Size2D::new(4096, 4096))
+    );
+
+    // Edge tiles shrink to fit.
+    let rects = tile_rects(Size2D::new(100, 50), Size2D::new(64, 32));
+    assert_eq!(
+        rects,
+        vec![
+            Rect::new(Point2D::new(0, 0), Size2D::new(64, 32)),
+            Rect::new(Point2D::new(64, 0), Size2D::new(36, 32)),
+            Rect::new(Point2D::new(0, 32), Size2D::new(64, 18)),
+            Rect::new(Point2D::new(64, 32), Size2D::new(36, 18)),
+        ]
+    );
+    let area: i32 = rects.iter().map(|rect| rect.size.area()).sum();
+    assert_eq!(area, 100 * 50);
+}
+
+// Tests that a tiled surface larger than the maximum texture size can be cleared and read back
+// across tile boundaries.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_tiled_surface() {
+    use euclid::default::{Point2D, Rect};
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let mut max_texture_size = 0;
+    unsafe {
+        env.gl
+            .GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size);
+    }
+    let tile_size = Size2D::new(max_texture_size.min(4096), max_texture_size.min(4096));
+    let size = tile_size * 2;
+
+    // Tiles larger than a texture are rejected.
+    match env
+        .device
+        .create_tiled_surface(&env.context, size, Size2D::new(max_texture_size + 1, 1))
+    {
+        Err(Error::SurfaceCreationFailed(_)) => {}
+        Err(err) => panic!("Expected `SurfaceCreationFailed` but got {:?}", err),
+        Ok(mut tiled_surface) => {
+            env.device
+                .destroy_tiled_surface(&mut env.context, &mut tiled_surface)
+                .unwrap();
+            panic!("Created a tile larger than the maximum texture size!");
+        }
+    }
+
+    let mut tiled_surface = env
+        .device
+        .create_tiled_surface(&env.context, size, tile_size)
+        .unwrap();
+    assert_eq!(tiled_surface.size(), size);
+    assert_eq!(tiled_surface.tiles().len(), 4);
+
+    env.device
+        .clear_tiled_surface(&env.context, &tiled_surface, [0.0, 1.0, 0.0, 1.0])
+        .unwrap();
+
+    // Read a 2x2 region straddling all four tiles.
+    let region = Rect::new(
+        Point2D::new(tile_size.width - 1, tile_size.height - 1),
+        Size2D::new(2, 2),
+    );
+    let pixels = env
+        .device
+        .read_tiled_surface_pixels(&env.context, &tiled_surface, &region)
+        .unwrap();
+    assert_eq!(pixels.len(), 16);
+    for pixel in pixels.chunks(4) {
+        assert_eq!(pixel, [0, 255, 0, 255]);
+    }
+
+    // Regions past the edges of the surface are rejected.
+    let region = Rect::new(Point2D::new(-1, 0), Size2D::new(2, 2));
+    match env
+        .device
+        .read_tiled_surface_pixels(&env.context, &tiled_surface, &region)
+    {
+        Err(Error::RectOutOfBounds) => {}
+        other => panic!("Expected `RectOutOfBounds`, got {:?}", other),
+    }
+
+    env.device
+        .destroy_tiled_surface(&mut env.context, &mut tiled_surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that dropping the texture binding requirement widens the pool of candidate configs.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_descriptor_without_texture_binding() {
+    use super::context::config_attributes;
+    use crate::egl;
+    use crate::egl::types::EGLint;
+    use crate::platform::generic::egl::device::EGL_FUNCTIONS;
+    use std::ptr;
+
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = connection.create_device(&adapter).unwrap();
+
+    let count_candidates = |needs_texture_binding| {
+        let mut attributes = config_attributes(needs_texture_binding);
+        attributes.push(egl::NONE as EGLint);
+        let mut config_count = 0;
+        EGL_FUNCTIONS.with(|egl| unsafe {
+            let result = egl.ChooseConfig(
+                device.egl_display,
+                attributes.as_ptr(),
+                ptr::null_mut(),
+                0,
+                &mut config_count,
+            );
+            assert_ne!(result, egl::FALSE);
+        });
+        config_count
+    };
+    assert!(count_candidates(false) >= count_candidates(true));
+
+    let attributes = ContextAttributes {
+        version: GLVersion::new(3, 0),
+        flags: ContextAttributeFlags::empty(),
+        ..Default::default()
+    };
+    let context_descriptor = device
+        .create_context_descriptor_with_texture_binding(&attributes, false)
+        .unwrap();
+    let mut context = device.create_context(&context_descriptor, None).unwrap();
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that an asynchronous present can be awaited on an executor.
+#[cfg(all(angle, feature = "sm-async", feature = "sm-winit"))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_present_context_async() {
+    use winit::event_loop::EventLoop;
+    use winit::platform::windows::EventLoopExtWindows;
+    use winit::window::WindowBuilder;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let event_loop: EventLoop<()> = EventLoop::new_any_thread();
+    let window = WindowBuilder::new()
+        .with_visible(false)
+        .build(&event_loop)
+        .unwrap();
+    let native_widget = env
+        .connection
+        .create_native_widget_from_winit_window(&window)
+        .unwrap();
+    let surface = env
+        .device
+        .create_surface(
+            &env.context,
+            SurfaceAccess::GPUOnly,
+            SurfaceType::Widget { native_widget },
+        )
+        .unwrap();
+    let mut old_surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut old_surface)
+        .unwrap();
+    env.device
+        .bind_surface_to_context(&mut env.context, surface)
+        .unwrap();
+
+    clear(&env.gl, &[255, 0, 0, 255]);
+    block_on(env.device.present_context_async(&mut env.context)).unwrap();
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that an asynchronous present without a widget surface resolves to an error, without
+// mistaking it for context loss.
+#[cfg(all(angle, feature = "sm-async"))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_present_context_async_without_widget() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let lost_count = Rc::new(Cell::new(0));
+    let lost_count_ = lost_count.clone();
+    env.device.set_context_lost_callback(
+        &env.context,
+        Box::new(move || lost_count_.set(lost_count_.get() + 1)),
+    );
+
+    match block_on(env.device.present_context_async(&mut env.context)) {
+        Err(Error::NoWidgetAttached) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(lost_count.get(), 0);
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that surface textures are created complete and that their filters can be overridden.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_texture_filter() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    // Sampling on the boundary between the bottom two rows shows which filter is in effect.
+    clear(&env.gl, &[255, 0, 0, 255]);
+    clear_bottom_row(&env.gl, &[0, 255, 0, 255]);
+    let surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    let surface_texture = env
+        .device
+        .create_surface_texture(&mut env.context, surface)
+        .unwrap();
+    let texture = env.device.surface_texture_object(&surface_texture);
+
+    let main_surface = make_surface(&mut env.device, &env.context);
+    env.device
+        .bind_surface_to_context(&mut env.context, main_surface)
+        .unwrap();
+    bind_context_fbo(&env.gl, &env.device, &env.context);
+
+    let filters = |gl: &Gl| unsafe {
+        let (mut min_filter, mut mag_filter, mut wrap_s) = (0, 0, 0);
+        gl.BindTexture(gl::TEXTURE_2D, texture);
+        gl.GetTexParameteriv(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, &mut min_filter);
+        gl.GetTexParameteriv(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, &mut mag_filter);
+        gl.GetTexParameteriv(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, &mut wrap_s);
+        gl.BindTexture(gl::TEXTURE_2D, 0);
+        (min_filter as GLenum, mag_filter as GLenum, wrap_s as GLenum)
+    };
+    let texcoord = [0.5, 1.0 / 480.0];
+
+    // The defaults don't need mipmaps, and blend neighboring texels.
+    assert_eq!(
+        filters(&env.gl),
+        (gl::LINEAR, gl::LINEAR, gl::CLAMP_TO_EDGE)
+    );
+    let pixel = sample_texture(&env.gl, texture, texcoord);
+    assert!(pixel[0] > 64 && pixel[0] < 192, "{:?}", pixel);
+    assert!(pixel[1] > 64 && pixel[1] < 192, "{:?}", pixel);
+
+    env.device
+        .set_surface_texture_filter(&env.context, &surface_texture, gl::NEAREST, gl::NEAREST)
+        .unwrap();
+    assert_eq!(
+        filters(&env.gl),
+        (gl::NEAREST, gl::NEAREST, gl::CLAMP_TO_EDGE)
+    );
+    let pixel = sample_texture(&env.gl, texture, texcoord);
+    assert!(
+        pixel == [255, 0, 0, 255] || pixel == [0, 255, 0, 255],
+        "{:?}",
+        pixel
+    );
+    check_gl(&env.gl);
+
+    // Only the context that created the surface texture may change its filters.
+    let mut other_context = env
+        .device
+        .create_context(&env.context_descriptor, None)
+        .unwrap();
+    match env.device.set_surface_texture_filter(
+        &other_context,
+        &surface_texture,
+        gl::LINEAR,
+        gl::LINEAR,
+    ) {
+        Err(Error::IncompatibleSurfaceTexture) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+    env.device.destroy_context(&mut other_context).unwrap();
+
+    let mut surface = env
+        .device
+        .destroy_surface_texture(&mut env.context, surface_texture)
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests the one-shot context and surface creation helper.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_create_context_with_surface() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = connection.create_device(&adapter).unwrap();
+
+    let attributes = ContextAttributes {
+        version: GLVersion::new(3, 0),
+        flags: ContextAttributeFlags::empty(),
+        ..Default::default()
+    };
+    let mut context = device
+        .create_context_with_surface(&attributes, Size2D::new(64, 32))
+        .unwrap();
+    assert!(device.context_is_current(&context));
+    let gl = Gl::load_with(|symbol| device.get_proc_address(&context, symbol));
+
+    bind_context_fbo(&gl, &device, &context);
+    clear(&gl, &[0, 0, 255, 255]);
+    assert_eq!(get_pixel_from_bottom_row(&gl), [0, 0, 255, 255]);
+    let surface_info = device.context_surface_info(&context).unwrap().unwrap();
+    assert_eq!(surface_info.size, Size2D::new(64, 32));
+
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests the human-readable formatting of GL APIs and versions.
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_gl_api_and_version_display() {
+    assert_eq!(GLApi::GL.to_string(), "OpenGL");
+    assert_eq!(GLApi::GLES.to_string(), "OpenGL ES");
+    assert_eq!(GLVersion::new(3, 1).to_string(), "3.1");
+    assert_eq!(
+        format!("{} {}", GLApi::GLES, GLVersion::new(3, 1)),
+        "OpenGL ES 3.1"
+    );
+    assert_eq!(
+        format!("{} {}", GLApi::GL, GLVersion::new(4, 6)),
+        "OpenGL 4.6"
+    );
+}
+
+// Tests that probing for the best context finds one no older than OpenGL ES 2.0 or OpenGL 2.1.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_probe_best_context() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = connection.create_device(&adapter).unwrap();
+
+    let (mut context, attributes) = device.probe_best_context().unwrap();
+    assert!(attributes.version.major >= 2);
+    let context_descriptor = device.context_descriptor(&context);
+    let context_attributes = device.context_descriptor_attributes(&context_descriptor);
+    assert!(context_attributes.version.major >= attributes.version.major);
+
+    let surface = make_surface(&mut device, &context);
+    device
+        .bind_surface_to_context(&mut context, surface)
+        .unwrap();
+    device.make_context_current(&context).unwrap();
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that a surface outlives its context and keeps its contents when rebound to a new one.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_rebind_surface() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    clear(&env.gl, &[255, 0, 255, 255]);
+    let surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    env.device.wait_for_surface(&env.context, &surface).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+
+    let mut context = env
+        .device
+        .create_context(&env.context_descriptor, None)
+        .unwrap();
+    env.device.rebind_surface(&mut context, surface).unwrap();
+    env.device.make_context_current(&context).unwrap();
+    bind_context_fbo(&env.gl, &env.device, &context);
+    assert_eq!(get_pixel_from_bottom_row(&env.gl), [255, 0, 255, 255]);
+
+    env.device.destroy_context(&mut context).unwrap();
+}
+
+// Tests conversion between premultiplied and straight alpha.
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_alpha_type_conversion() {
+    use crate::AlphaType;
+
+    let mut pixels = [255, 128, 0, 128, 10, 20, 30, 0, 200, 100, 50, 255];
+    AlphaType::convert_pixels(&mut pixels, AlphaType::Straight, AlphaType::Premultiplied);
+    assert_eq!(pixels, [128, 64, 0, 128, 0, 0, 0, 0, 200, 100, 50, 255]);
+    AlphaType::convert_pixels(&mut pixels, AlphaType::Premultiplied, AlphaType::Straight);
+    assert_eq!(pixels, [255, 128, 0, 128, 0, 0, 0, 0, 200, 100, 50, 255]);
+
+    AlphaType::convert_pixels(&mut pixels, AlphaType::Straight, AlphaType::Straight);
+    assert_eq!(pixels, [255, 128, 0, 128, 0, 0, 0, 0, 200, 100, 50, 255]);
+}
+
+// Tests that a premultiplied surface's alpha type is recorded and honored on readback.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_alpha_type() {
+    use super::surface::AngleSurfaceOptions;
+    use crate::AlphaType;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let surface = env
+        .device
+        .create_surface_with_options(
+            &env.context,
+            SurfaceAccess::GPUOnly,
+            SurfaceType::Generic {
+                size: Size2D::new(16, 16),
+            },
+            &AngleSurfaceOptions {
+                alpha_type: AlphaType::Premultiplied,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        env.device.surface_alpha_type(&surface),
+        AlphaType::Premultiplied
+    );
+    let mut old_surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut old_surface)
+        .unwrap();
+    env.device
+        .bind_surface_to_context(&mut env.context, surface)
+        .unwrap();
+    env.device.make_context_current(&env.context).unwrap();
+    bind_context_fbo(&env.gl, &env.device, &env.context);
+
+    // Half-transparent red, premultiplied.
+    clear(&env.gl, &[128, 0, 0, 128]);
+    let premultiplied = env
+        .device
+        .read_surface_pixels(&env.context, AlphaType::Premultiplied)
+        .unwrap();
+    assert_eq!(&premultiplied[0..4], [128, 0, 0, 128]);
+    let straight = env
+        .device
+        .read_surface_pixels(&env.context, AlphaType::Straight)
+        .unwrap();
+    assert_eq!(&straight[0..4], [255, 0, 0, 128]);
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that the device reports which surfaces are bound to which contexts.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_bindings() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let first_surface_id = env
+        .device
+        .context_surface_info(&env.context)
+        .unwrap()
+        .unwrap()
+        .id;
+    assert_eq!(
+        env.device.surface_bindings(),
+        vec![(env.context.id, first_surface_id)]
+    );
+
+    let mut other_context = env
+        .device
+        .create_context(&env.context_descriptor, None)
+        .unwrap();
+    let other_surface = make_surface(&mut env.device, &other_context);
+    let other_surface_id = env.device.surface_info(&other_surface).id;
+    env.device
+        .bind_surface_to_context(&mut other_context, other_surface)
+        .unwrap();
+    let mut bindings = env.device.surface_bindings();
+    bindings.sort_by_key(|&(context_id, _)| context_id.0);
+    assert_eq!(
+        bindings,
+        vec![
+            (env.context.id, first_surface_id),
+            (other_context.id, other_surface_id)
+        ]
+    );
+
+    env.device.destroy_context(&mut other_context).unwrap();
+    assert_eq!(
+        env.device.surface_bindings(),
+        vec![(env.context.id, first_surface_id)]
+    );
+    env.device.destroy_context(&mut env.context).unwrap();
+    assert!(env.device.surface_bindings().is_empty());
+}
+
+// Tests that identical context descriptor requests reuse the chosen config, and that purging the
+// cache leaves the device usable.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_descriptor_cache() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = connection.create_device(&adapter).unwrap();
+
+    let attributes = ContextAttributes {
+        version: GLVersion::new(3, 0),
+        flags: ContextAttributeFlags::ALPHA,
+        ..Default::default()
+    };
+    let first_descriptor = device.create_context_descriptor(&attributes).unwrap();
+    let second_descriptor = device.create_context_descriptor(&attributes).unwrap();
+    assert_eq!(device.context_descriptor_cache.borrow().len(), 1);
+    assert_eq!(
+        first_descriptor.egl_config_id,
+        second_descriptor.egl_config_id
+    );
+
+    let mut first_context = device.create_context(&first_descriptor, None).unwrap();
+    let mut second_context = device.create_context(&second_descriptor, None).unwrap();
+    device.destroy_context(&mut first_context).unwrap();
+    device.destroy_context(&mut second_context).unwrap();
+
+    // Different attributes get their own entry.
+    device
+        .create_context_descriptor(&ContextAttributes {
+            version: GLVersion::new(3, 0),
+            flags: ContextAttributeFlags::empty(),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(device.context_descriptor_cache.borrow().len(), 2);
+
+    // Purging empties the cache, and the device still creates working contexts afterward.
+    device.purge_caches();
+    assert!(device.context_descriptor_cache.borrow().is_empty());
+    let descriptor = device.create_context_descriptor(&attributes).unwrap();
+    assert_eq!(device.context_descriptor_cache.borrow().len(), 1);
+    let mut context = device.create_context(&descriptor, None).unwrap();
+    device.make_context_current(&context).unwrap();
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that a live Direct3D texture can be wrapped in a surface, rendered to, and read back.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_from_client_buffer() {
+    use std::ptr;
+    use winapi::shared::dxgiformat::{DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_FORMAT_R8_UNORM};
+    use winapi::shared::dxgitype::DXGI_SAMPLE_DESC;
+    use winapi::um::d3d11::{D3D11_BIND_RENDER_TARGET, D3D11_BIND_SHADER_RESOURCE};
+    use winapi::um::d3d11::{D3D11_TEXTURE2D_DESC, D3D11_USAGE_DEFAULT};
+    use wio::com::ComPtr;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let d3d11_device = env.device.d3d11_device.clone();
+    let create_texture = |format| unsafe {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: 64,
+            Height: 64,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_SHADER_RESOURCE | D3D11_BIND_RENDER_TARGET,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+        };
+        let mut texture = ptr::null_mut();
+        let result = d3d11_device.CreateTexture2D(&desc, ptr::null(), &mut texture);
+        assert_eq!(result, 0);
+        ComPtr::from_raw(texture)
+    };
+
+    // A texture in the wrong format is rejected when surfman checks it.
+    let wrong_texture = create_texture(DXGI_FORMAT_R8_UNORM);
+    unsafe {
+        match env.device.create_surface_from_client_buffer(
+            &env.context,
+            wrong_texture.as_raw() as *mut _,
+            false,
+            Size2D::new(64, 64),
+        ) {
+            Err(Error::IncompatibleSurface) => {}
+            Err(err) => panic!("Expected `IncompatibleSurface` but got {:?}", err),
+            Ok(mut surface) => {
+                env.device
+                    .destroy_surface(&mut env.context, &mut surface)
+                    .unwrap();
+                panic!("Wrapped a texture in an incompatible format!");
+            }
+        }
+    }
+
+    let texture = create_texture(DXGI_FORMAT_R8G8B8A8_UNORM);
+    let surface = unsafe {
+        env.device
+            .create_surface_from_client_buffer(
+                &env.context,
+                texture.as_raw() as *mut _,
+                false,
+                Size2D::new(64, 64),
+            )
+            .unwrap()
+    };
+    let mut old_surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut old_surface)
+        .unwrap();
+    env.device
+        .bind_surface_to_context(&mut env.context, surface)
+        .unwrap();
+    env.device.make_context_current(&env.context).unwrap();
+    bind_context_fbo(&env.gl, &env.device, &env.context);
+
+    clear(&env.gl, &[0, 128, 255, 255]);
+    assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 128, 255, 255]);
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that application errors can be drained and that surfman's internal GL work leaves no
+// errors behind.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_drain_gl_errors() {
+    use crate::AlphaType;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let surface = make_surface(&mut env.device, &env.context);
+    let surface_texture = env
+        .device
+        .create_surface_texture(&mut env.context, surface)
+        .unwrap();
+    assert!(env.device.drain_gl_errors(&env.context).unwrap().is_empty());
+
+    unsafe {
+        env.gl.BindTexture(gl::INVALID_ENUM, 0);
+    }
+    assert_eq!(
+        env.device.drain_gl_errors(&env.context).unwrap(),
+        vec![gl::INVALID_ENUM]
+    );
+    check_gl(&env.gl);
+
+    // Internal GL work clears pending errors, including the application's.
+    unsafe {
+        env.gl.BindTexture(gl::INVALID_ENUM, 0);
+    }
+    env.device
+        .read_surface_pixels(&env.context, AlphaType::Premultiplied)
+        .unwrap();
+    check_gl(&env.gl);
+
+    let mut surface = env
+        .device
+        .destroy_surface_texture(&mut env.context, surface_texture)
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that requesting a newer OpenGL ES version than the device supports falls back to the
+// newest version it does support.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_create_context_with_version_downgrade() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let device = match connection.create_device(&adapter) {
+        Ok(device) => device,
+        Err(Error::RequiredExtensionUnavailable) => return,
+        Err(err) => panic!("Failed to create device: {:?}", err),
+    };
+
+    let attributes = ContextAttributes {
+        version: GLVersion::new(3, 2),
+        flags: ContextAttributeFlags::empty(),
+        ..Default::default()
+    };
+    let (mut context, version) = device
+        .create_context_with_version_downgrade(&attributes, None)
+        .unwrap();
+    assert!(version.supports(GLVersion::GLES_2_0));
+    assert!(version <= GLVersion::GLES_3_2);
+
+    let actual_descriptor = device.context_descriptor(&context);
+    let actual_attributes = device.context_descriptor_attributes(&actual_descriptor);
+    assert!(actual_attributes.version.major >= version.major);
+
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that an invalidated surface has well-defined contents again once it's cleared.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_invalidate_surface() {
+    use crate::InvalidateFlags;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let mut surface = make_surface(&mut env.device, &env.context);
+    env.device
+        .invalidate_surface(&env.context, &surface, InvalidateFlags::all())
+        .unwrap();
+    check_gl(&env.gl);
+
+    let mut old_surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    env.device
+        .bind_surface_to_context(&mut env.context, surface)
+        .unwrap();
+    env.device.make_context_current(&env.context).unwrap();
+    bind_context_fbo(&env.gl, &env.device, &env.context);
+    clear(&env.gl, &[0, 255, 0, 255]);
+    assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 255, 0, 255]);
+
+    surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut old_surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that work run under a make-current timeout either returns its result or times out.
+#[cfg(any(android, angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_run_with_timeout() {
+    use crate::platform::generic::egl::context::run_with_timeout;
+    use std::time::Duration;
+
+    let result = run_with_timeout(Duration::from_secs(10), || 42);
+    assert!(matches!(result, Ok(42)));
+
+    // Simulate a hung `eglMakeCurrent()`.
+    let result = run_with_timeout(Duration::from_millis(10), || {
+        thread::sleep(Duration::from_secs(1));
+        42
+    });
+    assert!(matches!(result, Err(Error::MakeCurrentTimedOut)));
+}
+
+// Tests that contexts created from window-only configs, which can't have pbuffers, can be made
+// current without a surface.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_window_only_config_context() {
+    use crate::egl;
+    use crate::egl::types::EGLint;
+
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let device = connection.create_device(&adapter).unwrap();
+
+    let window_only_config = device
+        .enumerate_configs()
+        .unwrap()
+        .into_iter()
+        .find(|config| {
+            config.renderable_type & egl::OPENGL_ES2_BIT as EGLint != 0
+                && config.surface_type & egl::WINDOW_BIT as EGLint != 0
+                && config.surface_type & egl::PBUFFER_BIT as EGLint == 0
+        });
+    let window_only_config = match window_only_config {
+        None => return,
+        Some(config) => config,
+    };
+
+    let mut context = device
+        .create_context_with_config_id(window_only_config.config_id, GLVersion::GLES_2_0)
+        .unwrap();
+    device.make_context_current(&context).unwrap();
+    assert!(device.context_is_current(&context));
+
+    device.make_no_context_current().unwrap();
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that the EGL display and client extension sets can be queried.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_egl_extensions() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let device = connection.create_device(&adapter).unwrap();
+
+    let display_extensions = device.egl_display_extensions();
+    assert!(!display_extensions.is_empty());
+    assert!(display_extensions
+        .iter()
+        .all(|extension| extension.starts_with("EGL_")));
+
+    // surfman can't create an ANGLE device without `EGL_ANGLE_device_creation`.
+    assert!(Device::egl_client_extensions().contains("EGL_ANGLE_device_creation"));
+}
+
+// Tests that the maximum OpenGL ES version follows the Direct3D feature level, and that a context
+// of that version can be created.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_max_gles_version() {
+    use super::device::max_gles_version_for_feature_level;
+    use winapi::um::d3dcommon;
+
+    assert_eq!(
+        max_gles_version_for_feature_level(d3dcommon::D3D_FEATURE_LEVEL_9_3),
+        GLVersion::GLES_2_0
+    );
+    assert_eq!(
+        max_gles_version_for_feature_level(d3dcommon::D3D_FEATURE_LEVEL_10_1),
+        GLVersion::GLES_3_0
+    );
+    assert_eq!(
+        max_gles_version_for_feature_level(d3dcommon::D3D_FEATURE_LEVEL_11_0),
+        GLVersion::GLES_3_0
+    );
+    assert_eq!(
+        max_gles_version_for_feature_level(d3dcommon::D3D_FEATURE_LEVEL_11_1),
+        GLVersion::GLES_3_1
+    );
+
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = connection.create_device(&adapter).unwrap();
+    let version = device.max_gles_version();
+    assert!(version.supports(GLVersion::GLES_2_0));
+
+    let attributes = ContextAttributes {
+        version,
+        flags: ContextAttributeFlags::empty(),
+        ..Default::default()
+    };
+    let descriptor = device.create_context_descriptor(&attributes).unwrap();
+    let mut context = device.create_context(&descriptor, None).unwrap();
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that a context's loss callback fires when an operation fails because the context was
+// lost, and only then.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_lost_callback() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let old_count = Rc::new(Cell::new(0));
+    let old_count_ = old_count.clone();
+    env.device.set_context_lost_callback(
+        &env.context,
+        Box::new(move || old_count_.set(old_count_.get() + 1)),
+    );
+    let count = Rc::new(Cell::new(0));
+    let count_ = count.clone();
+    env.device
+        .set_context_lost_callback(&env.context, Box::new(move || count_.set(count_.get() + 1)));
+
+    env.device.make_context_current(&env.context).unwrap();
+    let result = env.device.check_context_lost(
+        &env.context,
+        Err::<(), _>(Error::MakeCurrentFailed(WindowingApiError::BadMatch)),
+    );
+    assert!(result.is_err());
+    assert_eq!(count.get(), 0);
+
+    let result = env.device.check_context_lost(
+        &env.context,
+        Err::<(), _>(Error::MakeCurrentFailed(WindowingApiError::ContextLost)),
+    );
+    assert!(matches!(
+        result,
+        Err(Error::MakeCurrentFailed(WindowingApiError::ContextLost))
+    ));
+    assert_eq!(count.get(), 1);
+    assert_eq!(old_count.get(), 0);
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that a device can be opened on an adapter chosen by its LUID.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_device_on_adapter_luid() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let luid = adapter.luid();
+
+    let device = Device::new_on_adapter_luid(luid).unwrap();
+    let device_luid = device.adapter().luid();
+    assert_eq!(device_luid.LowPart, luid.LowPart);
+    assert_eq!(device_luid.HighPart, luid.HighPart);
+}
+
+// Tests that pixels written by the CPU into a mapped surface are visible to OpenGL.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_map_surface_write() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let mut gpu_only_surface = make_surface(&mut env.device, &env.context);
+    assert!(matches!(
+        env.device.map_surface_write(&mut gpu_only_surface),
+        Err(Error::SurfaceDataInaccessible)
+    ));
+    env.device
+        .destroy_surface(&mut env.context, &mut gpu_only_surface)
+        .unwrap();
+
+    let size = Size2D::new(16, 16);
+    let mut surface = env
+        .device
+        .create_surface(
+            &env.context,
+            SurfaceAccess::GPUCPU,
+            SurfaceType::Generic { size },
+        )
+        .unwrap();
+    {
+        // Magenta, so that it doesn't matter whether the surface is RGBA or BGRA.
+        let mut guard = env.device.map_surface_write(&mut surface).unwrap();
+        let row_pitch = guard.row_pitch();
+        let data = guard.data();
+        for y in 0..size.height as usize {
+            for pixel in
+                data[(y * row_pitch)..(y * row_pitch + size.width as usize * 4)].chunks_mut(4)
+            {
+                pixel.copy_from_slice(&[255, 0, 255, 255]);
+            }
+        }
+    }
+
+    let mut old_surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    env.device
+        .bind_surface_to_context(&mut env.context, surface)
+        .unwrap();
+    env.device.make_context_current(&env.context).unwrap();
+    bind_context_fbo(&env.gl, &env.device, &env.context);
+    assert_eq!(get_pixel_from_bottom_row(&env.gl), [255, 0, 255, 255]);
+
+    env.device
+        .destroy_surface(&mut env.context, &mut old_surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that making a context current with a surface of an incompatible config reports both
+// config IDs.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_config_mismatch() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let other_attributes = ContextAttributes {
+        version: env
+            .device
+            .context_descriptor_attributes(&env.context_descriptor)
+            .version,
+        flags: ContextAttributeFlags::ALPHA
+            | ContextAttributeFlags::DEPTH
+            | ContextAttributeFlags::STENCIL,
+        ..Default::default()
+    };
+    let other_descriptor = env
+        .device
+        .create_context_descriptor(&other_attributes)
+        .unwrap();
+    let mut other_context = env.device.create_context(&other_descriptor, None).unwrap();
+    let mut other_surface = make_surface(&mut env.device, &other_context);
+
+    let native_context = NativeContext {
+        egl_context: env.device.native_context(&env.context).egl_context,
+        egl_draw_surface: other_surface.egl_surface,
+        egl_read_surface: other_surface.egl_surface,
+    };
+    let mut mismatched_context = unsafe {
+        env.device
+            .create_context_from_native_context(native_context)
+            .unwrap()
+    };
+    let surface_config = env.device.context_descriptor(&other_context).egl_config_id;
+    let context_config = env.device.context_descriptor(&env.context).egl_config_id;
+    if surface_config != context_config {
+        match env.device.make_context_current(&mismatched_context) {
+            Err(Error::SurfaceConfigMismatch {
+                surface_config: actual_surface_config,
+                context_config: actual_context_config,
+            }) => {
+                assert_eq!(actual_surface_config, surface_config);
+                assert_eq!(actual_context_config, context_config);
+            }
+            result => panic!("Expected a config mismatch but got {:?}", result),
+        }
+    }
+
+    env.device.make_no_context_current().unwrap();
+    env.device.destroy_context(&mut mismatched_context).unwrap();
+    env.device
+        .destroy_surface(&mut other_context, &mut other_surface)
+        .unwrap();
+    env.device.destroy_context(&mut other_context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that a context that doesn't flush on release can be created where that's supported, and
+// that other backends fall back to flushing.
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_flush_behavior_none() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let attributes = ContextAttributes {
+        flush_behavior: FlushBehavior::None,
+        color_bits: ColorBits::default(),
+        priority: ContextPriority::Medium,
+        reset_notification: ResetNotification::None,
+        ..env
+            .device
+            .context_descriptor_attributes(&env.context_descriptor)
+    };
+    let descriptor = env.device.create_context_descriptor(&attributes).unwrap();
+    let granted_attributes = env.device.context_descriptor_attributes(&descriptor);
+    assert_eq!(granted_attributes.version, attributes.version);
+    assert_eq!(granted_attributes.flags, attributes.flags);
+
+    let mut context = env.device.create_context(&descriptor, None).unwrap();
+    let surface = make_surface(&mut env.device, &context);
+    env.device
+        .bind_surface_to_context(&mut context, surface)
+        .unwrap();
+    env.device.make_context_current(&context).unwrap();
+    bind_context_fbo(&env.gl, &env.device, &context);
+    clear(&env.gl, &[255, 0, 0, 255]);
+    assert_eq!(get_pixel_from_bottom_row(&env.gl), [255, 0, 0, 255]);
+
+    // With no implicit flush, the commands must be flushed by hand before releasing the context.
+    unsafe {
+        env.gl.Flush();
+    }
+    env.device.make_no_context_current().unwrap();
+
+    env.device.destroy_context(&mut context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that surfaces created with `clear_on_create` start out transparent black.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_create_surface_with_clear() {
+    use super::surface::AngleSurfaceOptions;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let attributes = ContextAttributes {
+        flags: ContextAttributeFlags::ALPHA,
+        ..env
+            .device
+            .context_descriptor_attributes(&env.context_descriptor)
+    };
+    let descriptor = env.device.create_context_descriptor(&attributes).unwrap();
+    let mut context = env.device.create_context(&descriptor, None).unwrap();
+    let surface = env
+        .device
+        .create_surface_with_options(
+            &context,
+            SurfaceAccess::GPUOnly,
+            SurfaceType::Generic {
+                size: Size2D::new(640, 480),
+            },
+            &AngleSurfaceOptions {
+                clear_color: Some([0.0; 4]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    env.device
+        .bind_surface_to_context(&mut context, surface)
+        .unwrap();
+    env.device.make_context_current(&context).unwrap();
+    bind_context_fbo(&env.gl, &env.device, &context);
+
+    let mut pixels = vec![0xff; 640 * 480 * 4];
+    unsafe {
+        env.gl.ReadPixels(
+            0,
+            0,
+            640,
+            480,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut c_void,
+        );
+    }
+    check_gl(&env.gl);
+    assert!(pixels.iter().all(|&byte| byte == 0));
+
+    env.device.destroy_context(&mut context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that window surfaces can be created and presented in each present mode that the device
+// supports.
+#[cfg(all(angle, feature = "sm-winit"))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_present_modes() {
+    use super::surface::PresentMode;
+    use winit::event_loop::EventLoop;
+    use winit::platform::windows::EventLoopExtWindows;
+    use winit::window::WindowBuilder;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+    assert_eq!(env.device.present_mode(), PresentMode::Copy);
+
+    let event_loop: EventLoop<()> = EventLoop::new_any_thread();
+    for &mode in &[
+        PresentMode::Copy,
+        PresentMode::Flip,
+        PresentMode::FlipDiscard,
+    ] {
+        match env.device.set_present_mode(mode) {
+            Ok(()) => {}
+            Err(Error::RequiredExtensionUnavailable) => continue,
+            Err(err) => panic!("Failed to set present mode {:?}: {:?}", mode, err),
+        }
+        assert_eq!(env.device.present_mode(), mode);
+
+        let window = WindowBuilder::new()
+            .with_visible(false)
+            .build(&event_loop)
+            .unwrap();
+        let native_widget = env
+            .connection
+            .create_native_widget_from_winit_window(&window)
+            .unwrap();
+        let surface = env
+            .device
+            .create_surface(
+                &env.context,
+                SurfaceAccess::GPUOnly,
+                SurfaceType::Widget { native_widget },
+            )
+            .unwrap();
+        let mut old_surface = env
+            .device
+            .unbind_surface_from_context(&mut env.context)
+            .unwrap()
+            .unwrap();
+        env.device
+            .destroy_surface(&mut env.context, &mut old_surface)
+            .unwrap();
+        env.device
+            .bind_surface_to_context(&mut env.context, surface)
+            .unwrap();
+        env.device.make_context_current(&env.context).unwrap();
+
+        clear(&env.gl, &[255, 0, 0, 255]);
+        env.device.present_context_timed(&mut env.context).unwrap();
+    }
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that all contexts in a group share objects, even after the first context is destroyed.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_group() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let attributes = env
+        .device
+        .context_descriptor_attributes(&env.context_descriptor);
+    let mut group = env.device.create_context_group(&attributes, 3).unwrap();
+    assert_eq!(group.len(), 3);
+
+    // Upload a texture in the second context.
+    let surface = make_surface(&mut env.device, &group[1]);
+    env.device
+        .bind_surface_to_context(&mut group[1], surface)
+        .unwrap();
+    env.device.make_context_current(&group[1]).unwrap();
+    let mut texture = 0;
+    unsafe {
+        let pixel: [u8; 4] = [0, 0, 255, 255];
+        env.gl.GenTextures(1, &mut texture);
+        env.gl.BindTexture(gl::TEXTURE_2D, texture);
+        env.gl.TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA as _,
+            1,
+            1,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixel.as_ptr() as *const c_void,
+        );
+        env.gl.Finish();
+    }
+    check_gl(&env.gl);
+
+    // The share group must outlive the first context.
+    env.device.destroy_context(&mut group[0]).unwrap();
+
+    // Read the texture back in the third context.
+    let surface = make_surface(&mut env.device, &group[2]);
+    env.device
+        .bind_surface_to_context(&mut group[2], surface)
+        .unwrap();
+    env.device.make_context_current(&group[2]).unwrap();
+    unsafe {
+        let mut framebuffer = 0;
+        env.gl.GenFramebuffers(1, &mut framebuffer);
+        env.gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        env.gl.FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            texture,
+            0,
+        );
+        assert_eq!(
+            env.gl.CheckFramebufferStatus(gl::FRAMEBUFFER),
+            gl::FRAMEBUFFER_COMPLETE
+        );
+        assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 0, 255, 255]);
+        env.gl.DeleteFramebuffers(1, &framebuffer);
+        env.gl.DeleteTextures(1, &texture);
+    }
+    check_gl(&env.gl);
+
+    for context in &mut group[1..] {
+        env.device.destroy_context(context).unwrap();
+    }
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that surface and context IDs are distinct, stable, and usable as map keys.
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_and_context_ids() {
+    use std::collections::HashSet;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let mut first_surface = make_surface(&mut env.device, &env.context);
+    let mut second_surface = make_surface(&mut env.device, &env.context);
+    let first_id = env.device.surface_info(&first_surface).id;
+    let second_id = env.device.surface_info(&second_surface).id;
+    assert_ne!(first_id, second_id);
+    assert_eq!(env.device.surface_info(&first_surface).id, first_id);
+    assert_eq!(first_id.to_string(), first_id.0.to_string());
+
+    let surface_ids: HashSet<_> = vec![first_id, second_id, first_id].into_iter().collect();
+    assert_eq!(surface_ids.len(), 2);
+
+    let context_id = env.device.context_id(&env.context);
+    assert_eq!(
+        env.device.surface_info(&first_surface).context_id,
+        context_id
+    );
+    assert_eq!(context_id.to_string(), context_id.0.to_string());
+
+    env.device
+        .destroy_surface(&mut env.context, &mut second_surface)
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut first_surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that spurious empty config queries are retried a bounded number of times.
+#[cfg(any(android, angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_choose_config_retry() {
+    use crate::platform::generic::egl::context::{self, CHOOSE_CONFIG_ATTEMPTS};
+
+    // The first queries come up empty, then configs appear.
+    let mut results = vec![Ok(4), Ok(0), Ok(0)];
+    let mut revalidations = 0;
+    let config_count =
+        context::count_configs_with_retry(|| results.pop().unwrap(), || revalidations += 1);
+    assert_eq!(config_count.unwrap(), 4);
+    assert_eq!(revalidations, 2);
+
+    // If there really are no configs, give up after the last attempt.
+    let mut attempts = 0;
+    let config_count = context::count_configs_with_retry(
+        || {
+            attempts += 1;
+            Ok(0)
+        },
+        || {},
+    );
+    assert_eq!(config_count.unwrap(), 0);
+    assert_eq!(attempts, CHOOSE_CONFIG_ATTEMPTS);
+
+    // Errors aren't retried.
+    let mut attempts = 0;
+    let config_count = context::count_configs_with_retry(
+        || {
+            attempts += 1;
+            Err(Error::PixelFormatSelectionFailed(
+                WindowingApiError::BadDisplay,
+            ))
+        },
+        || {},
+    );
+    assert!(matches!(
+        config_count,
+        Err(Error::PixelFormatSelectionFailed(_))
+    ));
+    assert_eq!(attempts, 1);
+}
+
+// Tests that a context can be created from a hand-built EGL attribute list.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_create_context_with_raw_attributes() {
+    use crate::egl;
+    use crate::egl::types::EGLint;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let raw_attributes = [
+        egl::CONTEXT_CLIENT_VERSION as EGLint,
+        3,
+        egl::NONE as EGLint,
+    ];
+    let mut context = unsafe {
+        env.device
+            .create_context_with_raw_attributes(
+                env.context_descriptor.egl_config_id,
+                &raw_attributes,
+            )
+            .unwrap()
+    };
+    assert!(!env.device.context_gl_info(&context).renderer().is_empty());
+    assert_ne!(
+        env.device.context_id(&context),
+        env.device.context_id(&env.context)
+    );
+
+    let surface = make_surface(&mut env.device, &context);
+    env.device
+        .bind_surface_to_context(&mut context, surface)
+        .unwrap();
+    env.device.make_context_current(&context).unwrap();
+    bind_context_fbo(&env.gl, &env.device, &context);
+    clear(&env.gl, &[0, 255, 0, 255]);
+    assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 255, 0, 255]);
+
+    let bogus_config_id = -1;
+    let result = unsafe {
+        env.device
+            .create_context_with_raw_attributes(bogus_config_id, &raw_attributes)
+    };
+    assert!(matches!(result, Err(Error::NoPixelFormatFound)));
+
+    env.device.destroy_context(&mut context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that surfaceless context support is reported consistently with the display extensions.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_supports_surfaceless_context() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    assert_eq!(
+        env.device.supports_surfaceless_context(),
+        env.device
+            .egl_display_extensions()
+            .contains("EGL_KHR_surfaceless_context")
+    );
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that mipmaps generated for a surface can be sampled through a surface texture.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_mipmaps() {
+    use super::surface::AngleSurfaceOptions;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let size = Size2D::new(256, 256);
+    let surface = env
+        .device
+        .create_surface_with_options(
+            &env.context,
+            SurfaceAccess::GPUOnly,
+            SurfaceType::Generic { size },
+            &AngleSurfaceOptions {
+                generate_mipmaps: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    // Render to the base level.
+    let old_surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    env.device
+        .bind_surface_to_context(&mut env.context, surface)
+        .unwrap();
+    env.device.make_context_current(&env.context).unwrap();
+    bind_context_fbo(&env.gl, &env.device, &env.context);
+    clear(&env.gl, &[0, 0, 255, 255]);
+    let surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    env.device
+        .bind_surface_to_context(&mut env.context, old_surface)
+        .unwrap();
+
+    env.device
+        .generate_surface_mipmaps(&env.context, &surface)
+        .unwrap();
+    let surface_texture = env
+        .device
+        .create_surface_texture(&mut env.context, surface)
+        .unwrap();
+    let texture = env.device.surface_texture_object(&surface_texture);
+
+    env.device.make_context_current(&env.context).unwrap();
+    unsafe {
+        let mut min_filter = 0;
+        env.gl.BindTexture(gl::TEXTURE_2D, texture);
+        env.gl
+            .GetTexParameteriv(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, &mut min_filter);
+        env.gl.BindTexture(gl::TEXTURE_2D, 0);
+        assert_eq!(min_filter as GLenum, gl::LINEAR_MIPMAP_LINEAR);
+
+        // Read back a level well below the base.
+        let mut framebuffer = 0;
+        env.gl.GenFramebuffers(1, &mut framebuffer);
+        env.gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        env.gl.FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            texture,
+            4,
+        );
+        assert_eq!(
+            env.gl.CheckFramebufferStatus(gl::FRAMEBUFFER),
+            gl::FRAMEBUFFER_COMPLETE
+        );
+        assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 0, 255, 255]);
+        env.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        env.gl.DeleteFramebuffers(1, &framebuffer);
+    }
+    check_gl(&env.gl);
+
+    let mut surface = env
+        .device
+        .destroy_surface_texture(&mut env.context, surface_texture)
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that replacing a context's surface doesn't change which context is current.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_replace_context_surface() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let mut other_context = env
+        .device
+        .create_context(&env.context_descriptor, None)
+        .unwrap();
+    let surface = make_surface(&mut env.device, &other_context);
+    env.device
+        .bind_surface_to_context(&mut other_context, surface)
+        .unwrap();
+
+    // Replacing the surface of a context that isn't current leaves the current one alone.
+    env.device.make_context_current(&env.context).unwrap();
+    let new_surface = make_surface(&mut env.device, &other_context);
+    let mut old_surface = env
+        .device
+        .replace_context_surface(&mut other_context, new_surface)
+        .unwrap()
+        .unwrap();
+    assert!(env.device.context_is_current(&env.context));
+    assert!(!env.device.context_is_current(&other_context));
+    env.device
+        .destroy_surface(&mut other_context, &mut old_surface)
+        .unwrap();
+
+    // Replacing the surface of the current context keeps it current, rendering to the new one.
+    env.device.make_context_current(&other_context).unwrap();
+    let new_surface = make_surface(&mut env.device, &other_context);
+    let new_surface_id = env.device.surface_info(&new_surface).id;
+    let mut old_surface = env
+        .device
+        .replace_context_surface(&mut other_context, new_surface)
+        .unwrap()
+        .unwrap();
+    assert!(env.device.context_is_current(&other_context));
+    assert_eq!(
+        env.device
+            .context_surface_info(&other_context)
+            .unwrap()
+            .unwrap()
+            .id,
+        new_surface_id
+    );
+    bind_context_fbo(&env.gl, &env.device, &other_context);
+    clear(&env.gl, &[255, 0, 255, 255]);
+    assert_eq!(get_pixel_from_bottom_row(&env.gl), [255, 0, 255, 255]);
+    env.device
+        .destroy_surface(&mut other_context, &mut old_surface)
+        .unwrap();
+
+    // Surfaces from other contexts are rejected and handed back.
+    let foreign_surface = make_surface(&mut env.device, &env.context);
+    let (err, mut foreign_surface) = env
+        .device
+        .replace_context_surface(&mut other_context, foreign_surface)
+        .unwrap_err();
+    assert!(matches!(err, Error::IncompatibleSurface));
+    env.device
+        .destroy_surface(&mut env.context, &mut foreign_surface)
+        .unwrap();
+
+    env.device.destroy_context(&mut other_context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that descriptors created from identical attributes compare and hash as equal.
+#[cfg(any(android, angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_descriptor_equality() {
+    use std::collections::HashMap;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let attributes = env
+        .device
+        .context_descriptor_attributes(&env.context_descriptor);
+    let descriptor = env.device.create_context_descriptor(&attributes).unwrap();
+    assert!(descriptor == env.context_descriptor);
+
+    let mut pools = HashMap::new();
+    pools.insert(env.context_descriptor.clone(), 1);
+    *pools.entry(descriptor).or_insert(0) += 1;
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[&env.context_descriptor], 2);
+
+    let other_attributes = ContextAttributes {
+        flags: attributes.flags ^ ContextAttributeFlags::DEPTH,
+        ..attributes
+    };
+    if let Ok(other_descriptor) = env.device.create_context_descriptor(&other_attributes) {
+        assert!(other_descriptor != env.context_descriptor);
+    }
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that a device falls back to WARP when the requested adapter can't be opened.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_device_warp_fallback() {
+    use winapi::um::d3dcommon::D3D_DRIVER_TYPE_WARP;
+
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_hardware_adapter()
+        .expect("Failed to create adapter!");
+
+    // Direct3D rejects unknown driver types, so this adapter can never be opened.
+    let bad_adapter = Adapter {
+        dxgi_adapter: adapter.dxgi_adapter.clone(),
+        d3d_driver_type: 0x7fff,
+    };
+    assert!(Device::new(&bad_adapter).is_err());
+
+    let mut device = Device::new_with_fallback(&bad_adapter).unwrap();
+    assert_eq!(device.d3d_driver_type, D3D_DRIVER_TYPE_WARP);
+
+    let context_descriptor = device
+        .create_context_descriptor(&ContextAttributes {
+            version: GLVersion::new(3, 0),
+            flags: ContextAttributeFlags::empty(),
+            ..Default::default()
+        })
+        .unwrap();
+    let mut context = device.create_context(&context_descriptor, None).unwrap();
+    let mut surface = make_surface(&mut device, &context);
+    device
+        .bind_surface_to_context(&mut context, surface)
+        .unwrap();
+    device.make_context_current(&context).unwrap();
+    surface = device
+        .unbind_surface_from_context(&mut context)
+        .unwrap()
+        .unwrap();
+    device.destroy_surface(&mut context, &mut surface).unwrap();
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that rendering is complete and visible once `present_and_wait()` returns.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_present_and_wait() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    clear(&env.gl, &[0, 255, 0, 255]);
+    env.device.present_and_wait(&mut env.context).unwrap();
+    assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 255, 0, 255]);
+
+    let mut surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        env.device.present_and_wait(&mut env.context),
+        Err(Error::NoWidgetAttached)
+    ));
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that explicit per-channel color sizes select a matching config.
+#[cfg(any(android, angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_color_bits() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = match connection.create_device(&adapter) {
+        Ok(device) => device,
+        Err(Error::RequiredExtensionUnavailable) => return,
+        Err(err) => panic!("Failed to create device: {:?}", err),
+    };
+
+    let default_attributes = ContextAttributes {
+        version: GLVersion::new(3, 0),
+        flags: ContextAttributeFlags::ALPHA,
+        ..Default::default()
+    };
+    let descriptor = device
+        .create_context_descriptor(&default_attributes)
+        .unwrap();
+    assert_eq!(
+        device.context_descriptor_attributes(&descriptor).color_bits,
+        ColorBits::default()
+    );
+
+    let rgb565 = ColorBits {
+        red: 5,
+        green: 6,
+        blue: 5,
+        alpha: 0,
+    };
+    let descriptor = match device.create_context_descriptor(&ContextAttributes {
+        flags: ContextAttributeFlags::empty(),
+        color_bits: rgb565,
+        ..default_attributes
+    }) {
+        Ok(descriptor) => descriptor,
+        // Not every implementation exposes 565 configs.
+        Err(Error::NoPixelFormatFound) => return,
+        Err(err) => panic!("Failed to create context descriptor: {:?}", err),
+    };
+    let granted = device.context_descriptor_attributes(&descriptor).color_bits;
+    assert_eq!((granted.red, granted.green, granted.blue), (5, 6, 5));
+
+    let mut context = device.create_context(&descriptor, None).unwrap();
+    let surface = make_surface(&mut device, &context);
+    device
+        .bind_surface_to_context(&mut context, surface)
+        .unwrap();
+    device.make_context_current(&context).unwrap();
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that surfaces larger than the Direct3D texture limit are rejected with a clear error.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_too_large() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let max_dimension = env.device.max_surface_dimension();
+    assert!(max_dimension >= 2048);
+
+    let size = Size2D::new(20000, 20000);
+    match env.device.create_surface(
+        &env.context,
+        SurfaceAccess::GPUOnly,
+        SurfaceType::Generic { size },
+    ) {
+        Err(Error::SurfaceTooLarge { requested, max }) => {
+            assert_eq!(requested, size);
+            assert_eq!(max, max_dimension);
+        }
+        Err(err) => panic!("Expected `SurfaceTooLarge` but got {:?}", err),
+        Ok(mut surface) => {
+            env.device
+                .destroy_surface(&mut env.context, &mut surface)
+                .unwrap();
+            panic!("Expected `SurfaceTooLarge` but the surface was created");
+        }
+    }
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that clearing a surface repeatedly doesn't allocate framebuffer objects behind the
+// caller's back.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_clear_surface_does_not_leak_framebuffers() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    // ANGLE hands out the lowest free framebuffer name, so a leak would show up as a different
+    // name being returned afterward.
+    let gen_framebuffer_name = |gl: &Gl| unsafe {
+        let mut framebuffer_object = 0;
+        gl.GenFramebuffers(1, &mut framebuffer_object);
+        gl.DeleteFramebuffers(1, &framebuffer_object);
+        framebuffer_object
+    };
+
+    let mut surface = make_surface(&mut env.device, &env.context);
+    let first_name = gen_framebuffer_name(&env.gl);
+    for _ in 0..100 {
+        env.device
+            .clear_surface(&env.context, &surface, [0.0, 0.0, 1.0, 1.0])
+            .unwrap();
+    }
+    env.device.make_context_current(&env.context).unwrap();
+    assert_eq!(gen_framebuffer_name(&env.gl), first_name);
+    check_gl(&env.gl);
+
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that contexts remember the descriptor they were created with and that surfaces are
+// created with it.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_records_descriptor() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let descriptor = env.device.context_descriptor(&env.context);
+    assert!(descriptor == env.context_descriptor);
+
+    let mut surfaces: Vec<Surface> = (0..16)
+        .map(|_| make_surface(&mut env.device, &env.context))
+        .collect();
+    for surface in &surfaces {
+        assert!(surface.context_descriptor == descriptor);
+    }
+    for surface in &mut surfaces {
+        env.device
+            .destroy_surface(&mut env.context, surface)
+            .unwrap();
+    }
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that surface memory estimates account for pixel size, depth/stencil, and mipmaps.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_memory_bytes() {
+    use super::surface::estimate_surface_memory_bytes;
+
+    // 256x128 RGBA8 with a packed 24/8 depth/stencil buffer.
+    let size = Size2D::new(256, 128);
+    assert_eq!(
+        estimate_surface_memory_bytes(size, 32, 32, 0, false),
+        262144
+    );
+    // 24-bit color is stored in 4 bytes, and 565 in 2.
+    assert_eq!(estimate_surface_memory_bytes(size, 24, 0, 0, false), 131072);
+    assert_eq!(estimate_surface_memory_bytes(size, 16, 0, 0, false), 65536);
+    // The mipmap chain of a 256x128 texture has 43691 texels in total.
+    assert_eq!(estimate_surface_memory_bytes(size, 32, 0, 0, true), 174764);
+    // Multisampled buffers are counted once per sample.
+    assert_eq!(
+        estimate_surface_memory_bytes(size, 32, 32, 4, false),
+        1048576
+    );
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let mut surface = make_surface(&mut env.device, &env.context);
+    let bytes = env.device.surface_memory_bytes(&surface).unwrap();
+    assert!(bytes >= 640 * 480 * 4);
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that contexts can be created with each priority, whether or not the driver honors it.
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_priority() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = match connection.create_device(&adapter) {
+        Ok(device) => device,
+        Err(Error::RequiredExtensionUnavailable) => return,
+        Err(err) => panic!("Failed to create device: {:?}", err),
+    };
+
+    for &priority in &[
+        ContextPriority::Low,
+        ContextPriority::Medium,
+        ContextPriority::High,
+    ] {
+        let attributes = ContextAttributes {
+            version: GLVersion::new(3, 0),
+            flags: ContextAttributeFlags::empty(),
+            priority,
+            ..Default::default()
+        };
+        let descriptor = device.create_context_descriptor(&attributes).unwrap();
+        let granted = device.context_descriptor_attributes(&descriptor).priority;
+        assert!(granted == priority || granted == ContextPriority::Medium);
+
+        let mut context = device.create_context(&descriptor, None).unwrap();
+        let surface = make_surface(&mut device, &context);
+        device
+            .bind_surface_to_context(&mut context, surface)
+            .unwrap();
+        device.make_context_current(&context).unwrap();
+        device.destroy_context(&mut context).unwrap();
+    }
+}
+
+// Tests that contexts can be created with each reset notification strategy, whether or not the
+// driver honors it.
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_reset_notification() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = match connection.create_device(&adapter) {
+        Ok(device) => device,
+        Err(Error::RequiredExtensionUnavailable) => return,
+        Err(err) => panic!("Failed to create device: {:?}", err),
+    };
+
+    for &reset_notification in &[ResetNotification::None, ResetNotification::LoseContext] {
+        let attributes = ContextAttributes {
+            version: GLVersion::new(3, 0),
+            flags: ContextAttributeFlags::empty(),
+            reset_notification,
+            ..Default::default()
+        };
+        let descriptor = device.create_context_descriptor(&attributes).unwrap();
+        let granted = device
+            .context_descriptor_attributes(&descriptor)
+            .reset_notification;
+        assert!(granted == reset_notification || granted == ResetNotification::None);
+
+        let mut context = device.create_context(&descriptor, None).unwrap();
+        let surface = make_surface(&mut device, &context);
+        device
+            .bind_surface_to_context(&mut context, surface)
+            .unwrap();
+        device.make_context_current(&context).unwrap();
+        device.destroy_context(&mut context).unwrap();
+    }
+}
+
+// Tests that a context can draw to one surface while reading from another.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_make_context_current_with_surfaces() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let mut draw_surface = make_surface(&mut env.device, &env.context);
+    let mut read_surface = make_surface(&mut env.device, &env.context);
+    env.device
+        .clear_surface(&env.context, &draw_surface, [1.0, 0.0, 0.0, 1.0])
+        .unwrap();
+    env.device
+        .clear_surface(&env.context, &read_surface, [0.0, 1.0, 0.0, 1.0])
+        .unwrap();
+
+    env.device
+        .make_context_current_with_surfaces(&env.context, &draw_surface, &read_surface)
+        .unwrap();
+    unsafe {
+        env.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        env.gl.BlitFramebuffer(
+            0,
+            0,
+            640,
+            480,
+            0,
+            0,
+            640,
+            480,
+            gl::COLOR_BUFFER_BIT,
+            gl::NEAREST,
+        );
+    }
+    check_gl(&env.gl);
+
+    // Reading from the draw surface now shows the contents copied from the read surface.
+    env.device
+        .make_context_current_with_surfaces(&env.context, &draw_surface, &draw_surface)
+        .unwrap();
+    assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 255, 0, 255]);
+
+    env.device.make_context_current(&env.context).unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut draw_surface)
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut read_surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that configs granted beyond the requested attributes are reported.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_config_upgrades() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = connection.create_device(&adapter).unwrap();
+
+    let attributes = ContextAttributes {
+        version: GLVersion::new(3, 0),
+        flags: ContextAttributeFlags::empty(),
+        ..Default::default()
+    };
+    let (mut context, upgrades) = device
+        .create_context_reporting_upgrades(&attributes, None)
+        .unwrap();
+
+    // Whatever config was chosen, the report must agree with it.
+    let granted = device.context_descriptor_attributes(&device.context_descriptor(&context));
+    assert_eq!(
+        upgrades.alpha_size.is_some(),
+        granted.flags.contains(ContextAttributeFlags::ALPHA)
+    );
+    assert_eq!(
+        upgrades.depth_size.is_some(),
+        granted.flags.contains(ContextAttributeFlags::DEPTH)
+    );
+    assert_eq!(
+        upgrades.stencil_size.is_some(),
+        granted.flags.contains(ContextAttributeFlags::STENCIL)
+    );
+
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that destroying a context leaves an unrelated current context bound.
+#[cfg(any(android, angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_destroy_unrelated_context() {
+    use crate::platform::generic::egl::device::EGL_FUNCTIONS;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let mut other_context = env
+        .device
+        .create_context(&env.context_descriptor, None)
+        .unwrap();
+    env.device.make_context_current(&env.context).unwrap();
+    let current_egl_context = env.device.native_context(&env.context).egl_context;
+
+    env.device.destroy_context(&mut other_context).unwrap();
+    let still_current = EGL_FUNCTIONS.with(|egl| unsafe { egl.GetCurrentContext() });
+    assert_eq!(still_current, current_egl_context);
+
+    env.device.destroy_context(&mut env.context).unwrap();
+    let now_current = EGL_FUNCTIONS.with(|egl| unsafe { egl.GetCurrentContext() });
+    assert_eq!(now_current, crate::egl::NO_CONTEXT);
+}
+
+// Tests that creating a surface with an inherent `Device` call emits a tracing span.
+#[cfg(feature = "sm-tracing")]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_create_surface_emits_span() {
+    use std::sync::{Arc, Mutex};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    struct SpanRecorder(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Subscriber for SpanRecorder {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+        fn new_span(&self, attributes: &Attributes) -> Id {
+            let mut names = self.0.lock().unwrap();
+            names.push(attributes.metadata().name());
+            Id::from_u64(names.len() as u64)
+        }
+        fn record(&self, _: &Id, _: &Record) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let names = Arc::new(Mutex::new(vec![]));
+    let mut surface = tracing::subscriber::with_default(SpanRecorder(names.clone()), || {
+        env.device
+            .create_surface(
+                &env.context,
+                SurfaceAccess::GPUOnly,
+                SurfaceType::Generic {
+                    size: Size2D::new(640, 480),
+                },
+            )
+            .unwrap()
+    });
+    assert!(names.lock().unwrap().contains(&"create_surface"));
+
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that each context loads its own OpenGL function table and that calls made through it
+// reach that context rather than whichever one was created last.
+#[cfg(any(angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_per_context_gl_functions() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let mut other_context = env
+        .device
+        .create_context(&env.context_descriptor, None)
+        .unwrap();
+    let surface = make_surface(&mut env.device, &other_context);
+    env.device
+        .bind_surface_to_context(&mut other_context, surface)
+        .unwrap();
+
+    let colors = [[255, 0, 0, 255], [0, 255, 0, 255]];
+    for (context, color) in [&env.context, &other_context].iter().zip(colors.iter()) {
+        env.device.make_context_current(context).unwrap();
+        let gl = context_gl(context);
+        unsafe {
+            assert!(!gl.GetString(gl::VERSION).is_null());
+            gl.BindFramebuffer(gl::FRAMEBUFFER, context_fbo(&env.device, context));
+            gl.Viewport(0, 0, 640, 480);
+            clear(gl, color);
+            assert_eq!(get_pixel_from_bottom_row(gl), *color);
+            assert_eq!(gl.GetError(), gl::NO_ERROR);
+        }
+    }
+
+    // Clearing the second context must not have touched the first.
+    env.device.make_context_current(&env.context).unwrap();
+    assert_eq!(
+        get_pixel_from_bottom_row(context_gl(&env.context)),
+        colors[0]
+    );
+
+    env.device.destroy_context(&mut other_context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that the texture target and object returned together for a surface texture can be bound
+// and read from.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_texture_binding() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    unsafe {
+        clear(&env.gl, &[0, 0, 255, 255]);
+
+        let blue_surface = env
+            .device
+            .unbind_surface_from_context(&mut env.context)
+            .unwrap()
+            .unwrap();
+        let blue_surface_texture = env
+            .device
+            .create_surface_texture(&mut env.context, blue_surface)
+            .unwrap();
+
+        let (target, texture) = env.device.surface_texture_binding(&blue_surface_texture);
+        assert_eq!(target, gl::TEXTURE_2D);
+        assert_eq!(
+            texture,
+            env.device.surface_texture_object(&blue_surface_texture)
+        );
+
+        // Binding the pair to a sampler unit must succeed.
+        env.gl.ActiveTexture(gl::TEXTURE0);
+        env.gl.BindTexture(target, texture);
+        check_gl(&env.gl);
+        env.gl.BindTexture(target, 0);
+
+        // Read the texture's contents back through a framebuffer.
+        let mut framebuffer_object = make_fbo(&env.gl, target, texture);
+        assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 0, 255, 255]);
+        env.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        env.gl.DeleteFramebuffers(1, &mut framebuffer_object);
+
+        let mut blue_surface = env
+            .device
+            .destroy_surface_texture(&mut env.context, blue_surface_texture)
+            .unwrap();
+        env.device
+            .destroy_surface(&mut env.context, &mut blue_surface)
+            .unwrap();
+        env.device.destroy_context(&mut env.context).unwrap();
+    }
+}
+
+// Tests that a device opened from a shared display on another thread can render to a surface that
+// the original device then reads from, and that dropping a clone leaves the display alive.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_shared_display_device() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    // Dropping a clone must not terminate the display.
+    drop(env.device.create_thread_local_clone());
+    env.device.make_context_current(&env.context).unwrap();
+
+    let (to_main_sender, to_main_receiver) = mpsc::channel();
+    let (to_worker_sender, to_worker_receiver) = mpsc::channel();
+
+    let shared_display = env.device.shared_display();
+    let other_context_descriptor = env.context_descriptor.clone();
+    let worker = thread::spawn(move || {
+        let mut device = shared_display.create_device();
+        let mut context = device
+            .create_context(&other_context_descriptor, None)
+            .unwrap();
+        let gl = Gl::load_with(|symbol| device.get_proc_address(&context, symbol));
+
+        let surface = make_surface(&mut device, &context);
+        device
+            .bind_surface_to_context(&mut context, surface)
+            .unwrap();
+        device.make_context_current(&context).unwrap();
+        bind_context_fbo(&gl, &device, &context);
+        clear(&gl, &[0, 255, 0, 255]);
+
+        let surface = device
+            .unbind_surface_from_context(&mut context)
+            .unwrap()
+            .unwrap();
+        to_main_sender.send(surface).unwrap();
+
+        let mut surface = to_worker_receiver.recv().unwrap();
+        device.destroy_surface(&mut context, &mut surface).unwrap();
+        device.destroy_context(&mut context).unwrap();
+    });
+
+    unsafe {
+        let green_surface = to_main_receiver.recv().unwrap();
+        let green_surface_texture = env
+            .device
+            .create_surface_texture(&mut env.context, green_surface)
+            .unwrap();
+
+        env.device.make_context_current(&env.context).unwrap();
+        let mut green_framebuffer_object = make_fbo(
+            &env.gl,
+            env.device.surface_gl_texture_target(),
+            env.device.surface_texture_object(&green_surface_texture),
+        );
+        assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 255, 0, 255]);
+        env.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        env.gl.DeleteFramebuffers(1, &mut green_framebuffer_object);
+
+        let green_surface = env
+            .device
+            .destroy_surface_texture(&mut env.context, green_surface_texture)
+            .unwrap();
+        to_worker_sender.send(green_surface).unwrap();
+    }
+
+    worker.join().unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that a swap chain surface can be created with three buffers for triple buffering and that
+// more buffers than DXGI allows are rejected.
+#[cfg(all(angle, feature = "sm-winit"))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_window_surface_buffer_count() {
+    use winit::event_loop::EventLoop;
+    use winit::platform::windows::EventLoopExtWindows;
+    use winit::window::WindowBuilder;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let event_loop: EventLoop<()> = EventLoop::new_any_thread();
+    let window = WindowBuilder::new()
+        .with_visible(false)
+        .build(&event_loop)
+        .unwrap();
+    let native_widget = env
+        .connection
+        .create_native_widget_from_winit_window(&window)
+        .unwrap();
+
+    match env
+        .device
+        .create_swapchain_surface(&env.context, &native_widget, 17)
+    {
+        Err(Error::SurfaceCreationFailed(WindowingApiError::BadValue)) => {}
+        Err(err) => panic!("Unexpected error: {:?}", err),
+        Ok(_) => panic!("Shouldn't have created a swap chain with 17 buffers!"),
+    }
+
+    let mut surface = env
+        .device
+        .create_swapchain_surface(&env.context, &native_widget, 3)
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that discarding depth and stencil while flushing a surface for handoff preserves its color.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_flush_context_surface_discarding() {
+    use crate::InvalidateFlags;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    unsafe {
+        clear(&env.gl, &[0, 255, 0, 255]);
+        env.device
+            .flush_context_surface_discarding(
+                &env.context,
+                InvalidateFlags::DEPTH | InvalidateFlags::STENCIL,
+            )
+            .unwrap();
+        check_gl(&env.gl);
+
+        let green_surface = env
+            .device
+            .unbind_surface_from_context(&mut env.context)
+            .unwrap()
+            .unwrap();
+        match env
+            .device
+            .flush_context_surface_discarding(&env.context, InvalidateFlags::DEPTH)
+        {
+            Err(Error::NoSurfaceAttached) => {}
+            other => panic!("Expected `NoSurfaceAttached`, got {:?}", other),
+        }
+
+        let green_surface_texture = env
+            .device
+            .create_surface_texture(&mut env.context, green_surface)
+            .unwrap();
+        env.device.make_context_current(&env.context).unwrap();
+        let mut green_framebuffer_object = make_fbo(
+            &env.gl,
+            env.device.surface_gl_texture_target(),
+            env.device.surface_texture_object(&green_surface_texture),
+        );
+        assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 255, 0, 255]);
+        env.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        env.gl.DeleteFramebuffers(1, &mut green_framebuffer_object);
+
+        let mut green_surface = env
+            .device
+            .destroy_surface_texture(&mut env.context, green_surface_texture)
+            .unwrap();
+        env.device
+            .destroy_surface(&mut env.context, &mut green_surface)
+            .unwrap();
+        env.device.destroy_context(&mut env.context).unwrap();
+    }
+}
+
+// Tests that a loader context is current and can be used to load working OpenGL functions.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_loader_context() {
+    use std::ffi::CStr;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+    env.device.destroy_context(&mut env.context).unwrap();
+
+    let mut context = env.device.create_loader_context().unwrap();
+    let gl = Gl::load_with(|symbol| env.device.get_proc_address(&context, symbol));
+    unsafe {
+        let version = gl.GetString(gl::VERSION);
+        assert!(!version.is_null());
+        let version = CStr::from_ptr(version as *const _).to_string_lossy();
+        assert!(version.starts_with("OpenGL ES"), "{}", version);
+        check_gl(&gl);
+    }
+
+    env.device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that `EGL_NOT_INITIALIZED` is reported as a terminated display rather than as a failure of
+// the operation.
+#[cfg(any(android, angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_egl_not_initialized_error() {
+    use crate::egl;
+    use crate::egl::types::EGLint;
+    use crate::platform::generic::egl::error::{egl_error, surface_creation_error};
+
+    let not_initialized = egl::NOT_INITIALIZED as EGLint;
+    assert!(matches!(
+        egl_error(not_initialized, Error::MakeCurrentFailed),
+        Error::DisplayNotInitialized
+    ));
+    assert!(matches!(
+        surface_creation_error(not_initialized),
+        Error::DisplayNotInitialized
+    ));
+
+    // Other errors are still attributed to the operation.
+    assert!(matches!(
+        egl_error(egl::BAD_MATCH as EGLint, Error::MakeCurrentFailed),
+        Error::MakeCurrentFailed(WindowingApiError::BadMatch)
+    ));
+    assert!(matches!(
+        surface_creation_error(egl::BAD_ALLOC as EGLint),
+        Error::OutOfSurfaceMemory
+    ));
+    assert!(matches!(
+        surface_creation_error(egl::BAD_CONFIG as EGLint),
+        Error::SurfaceCreationFailed(WindowingApiError::BadConfig)
+    ));
+}
+
+// Tests that a closure run with a context current sees that context, and that the previous binding
+// is restored afterward, even if the closure panics.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_with_context_current() {
+    use crate::platform::generic::egl::device::EGL_FUNCTIONS;
+    use std::panic::{self, AssertUnwindSafe};
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+    let current_egl_context = || EGL_FUNCTIONS.with(|egl| unsafe { egl.GetCurrentContext() });
+
+    let mut other_context = env
+        .device
+        .create_context(&env.context_descriptor, None)
+        .unwrap();
+    let other_egl_context = env.device.native_context(&other_context).egl_context;
+
+    env.device.make_context_current(&env.context).unwrap();
+    let egl_context = env.device.native_context(&env.context).egl_context;
+    let version_is_available = env
+        .device
+        .with_context_current(&other_context, || unsafe {
+            assert_eq!(current_egl_context(), other_egl_context);
+            !env.gl.GetString(gl::VERSION).is_null()
+        })
+        .unwrap();
+    assert!(version_is_available);
+    assert_eq!(current_egl_context(), egl_context);
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        env.device
+            .with_context_current(&other_context, || panic!("Expected panic"))
+    }));
+    assert!(result.is_err());
+    assert_eq!(current_egl_context(), egl_context);
+
+    env.device.make_no_context_current().unwrap();
+    env.device
+        .with_context_current(&other_context, || ())
+        .unwrap();
+    assert_eq!(current_egl_context(), crate::egl::NO_CONTEXT);
+
+    env.device.destroy_context(&mut other_context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that the format of an adopted context's external draw surface can be queried while the
+// context isn't current.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_external_surface_format() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    env.device.make_context_current(&env.context).unwrap();
+    let native_context = NativeContext::current().unwrap();
+    let mut adopted_context = unsafe {
+        env.device
+            .create_context_from_native_context(native_context)
+            .unwrap()
+    };
+    match env.device.context_surface_info(&adopted_context) {
+        Err(Error::ExternalRenderTarget) => {}
+        _ => panic!("The adopted context should render to an external target!"),
+    }
+
+    env.device.make_no_context_current().unwrap();
+    let format = env
+        .device
+        .external_surface_format(&adopted_context)
+        .unwrap();
+    assert_eq!(format.config_id, env.context_descriptor.egl_config_id);
+    assert_eq!(format.color_bits, ColorBits::default());
+
+    let mut other_context = env
+        .device
+        .create_context(&env.context_descriptor, None)
+        .unwrap();
+    match env.device.external_surface_format(&other_context) {
+        Err(Error::NoSurfaceAttached) => {}
+        other => panic!("Expected `NoSurfaceAttached`, got {:?}", other),
+    }
+
+    env.device.destroy_context(&mut other_context).unwrap();
+    env.device.destroy_context(&mut adopted_context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that the GL functions surfman relies on internally are all loaded for a new context.
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_required_gl_functions_loaded() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+    gl_utils::check_required_gl_functions(&env.gl).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that a GL function that failed to load is reported by name.
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_required_gl_function_missing() {
+    // The pointers are never called; only whether they are null matters.
+    let gl = Gl::load_with(|name| {
+        if name == "glReadPixels" {
+            ptr::null()
+        } else {
+            NonNull::<c_void>::dangling().as_ptr()
+        }
+    });
+    match gl_utils::check_required_gl_functions(&gl) {
+        Err(Error::RequiredGLFunctionMissing("glReadPixels")) => {}
+        result => panic!("Expected glReadPixels to be reported missing: {:?}", result),
+    }
+}
+
+// Tests that an offscreen context comes with a framebuffer object that can be rendered into.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_create_offscreen_context() {
+    let connection = Connection::new().unwrap();
+    let adapter = connection
+        .create_low_power_adapter()
+        .expect("Failed to create adapter!");
+    let mut device = connection.create_device(&adapter).unwrap();
+
+    let attributes = ContextAttributes {
+        version: GLVersion::new(3, 0),
+        flags: ContextAttributeFlags::empty(),
+        ..Default::default()
+    };
+    let (mut context, framebuffer_object) = device
+        .create_offscreen_context(&attributes, Size2D::new(64, 32))
+        .unwrap();
+    assert!(device.context_is_current(&context));
+    let surface_info = device.context_surface_info(&context).unwrap().unwrap();
+    assert_eq!(surface_info.framebuffer_object, framebuffer_object);
+    assert_eq!(surface_info.size, Size2D::new(64, 32));
+
+    let gl = Gl::load_with(|symbol| device.get_proc_address(&context, symbol));
+    unsafe {
+        gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer_object);
+        gl.Viewport(0, 0, 64, 32);
+    }
+    clear(&gl, &[0, 255, 0, 255]);
+    assert_eq!(get_pixel_from_bottom_row(&gl), [0, 255, 0, 255]);
+
+    device.destroy_context(&mut context).unwrap();
+}
+
+// Tests that each layer of an array surface can be rendered to and read back independently.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_array_surface() {
+    use crate::GLCapability;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let mut array_surface = env
+        .device
+        .create_array_surface(&env.context, Size2D::new(64, 32), 2)
+        .unwrap();
+    assert_eq!(array_surface.layers(), 2);
+    let supports_multiview = env
+        .device
+        .context_supports(&env.context, GLCapability::Multiview);
+    match env.device.surface_array_framebuffer_object(&array_surface) {
+        Ok(framebuffer_object) => {
+            assert!(supports_multiview);
+            assert_ne!(framebuffer_object, 0);
+        }
+        Err(Error::RequiredExtensionUnavailable) => assert!(!supports_multiview),
+        Err(err) => panic!("Unexpected error: {:?}", err),
+    }
+
+    let colors = [[255, 0, 0, 255], [0, 0, 255, 255]];
+    unsafe {
+        env.gl.Viewport(0, 0, 64, 32);
+    }
+    for (layer, color) in colors.iter().enumerate() {
+        unsafe {
+            env.gl.BindFramebuffer(
+                gl::FRAMEBUFFER,
+                array_surface.layer_framebuffer_object(layer as u32),
+            );
+        }
+        clear(&env.gl, color);
+    }
+    for (layer, color) in colors.iter().enumerate() {
+        unsafe {
+            env.gl.BindFramebuffer(
+                gl::FRAMEBUFFER,
+                array_surface.layer_framebuffer_object(layer as u32),
+            );
+        }
+        assert_eq!(get_pixel_from_bottom_row(&env.gl), *color);
+    }
+    check_gl(&env.gl);
+
+    env.device
+        .destroy_array_surface(&mut env.context, &mut array_surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that a binding saved when switching contexts can be restored later, including the
+// binding of no context at all.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_make_context_current_saving() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+    let mut other_context = env
+        .device
+        .create_context(&env.context_descriptor, None)
+        .unwrap();
+
+    let previous_binding = env
+        .device
+        .make_context_current_saving(&other_context)
+        .unwrap();
+    assert!(env.device.context_is_current(&other_context));
+    env.device.restore_binding(previous_binding);
+    assert!(env.device.context_is_current(&env.context));
+
+    env.device.make_no_context_current().unwrap();
+    let previous_binding = env
+        .device
+        .make_context_current_saving(&other_context)
+        .unwrap();
+    assert!(env.device.context_is_current(&other_context));
+    env.device.restore_binding(previous_binding);
+    assert!(!env.device.context_is_current(&other_context));
+    assert!(!env.device.context_is_current(&env.context));
+
+    env.device.destroy_context(&mut other_context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that a surface created with initial pixel data reads back with that data, and that data
+// of the wrong length is rejected.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_create_surface_with_data() {
+    use super::surface::AngleSurfaceOptions;
+    use crate::AlphaType;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let size = Size2D::new(16, 16);
+    let mut data = vec![];
+    for y in 0..size.height {
+        for x in 0..size.width {
+            data.extend_from_slice(&[(x * 16) as u8, (y * 16) as u8, 128, 255]);
+        }
+    }
+    match env.device.create_surface_with_options(
+        &env.context,
+        SurfaceAccess::GPUOnly,
+        SurfaceType::Generic { size },
+        &AngleSurfaceOptions {
+            data: Some(&data[4..]),
+            ..Default::default()
+        },
+    ) {
+        Err(Error::InvalidSurfaceData) => {}
+        Err(err) => panic!("Unexpected error: {:?}", err),
+        Ok(mut surface) => {
+            env.device
+                .destroy_surface(&mut env.context, &mut surface)
+                .unwrap();
+            panic!("Created a surface from too little data!");
+        }
+    }
+
+    let surface = env
+        .device
+        .create_surface_with_options(
+            &env.context,
+            SurfaceAccess::GPUOnly,
+            SurfaceType::Generic { size },
+            &AngleSurfaceOptions {
+                data: Some(&data[..]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let mut old_surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut old_surface)
+        .unwrap();
+    env.device
+        .bind_surface_to_context(&mut env.context, surface)
+        .unwrap();
+    let pixels = env
+        .device
+        .read_surface_pixels(&env.context, AlphaType::Premultiplied)
+        .unwrap();
+    assert_eq!(pixels, data);
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that the API of an adopted context is queried from the context itself.
+#[cfg(any(android, angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_adopted_context_gl_api() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+    assert_eq!(env.device.context_gl_api(&env.context), env.device.gl_api());
+
+    let native_context = NativeContext::current().unwrap();
+    let mut adopted_context = unsafe {
+        env.device
+            .create_context_from_native_context(native_context)
+            .unwrap()
+    };
+    assert_eq!(
+        env.device.context_gl_api(&adopted_context),
+        env.device.gl_api()
+    );
+    #[cfg(any(android, angle))]
+    assert_eq!(env.device.context_gl_api(&adopted_context), GLApi::GLES);
+
+    env.device.destroy_context(&mut adopted_context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that a surface can be copied into an application-owned texture, and that a texture too
+// small for the surface is rejected.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_copy_surface_to_gl_texture() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let surface_size = Size2D::new(16, 16);
+    let mut surface = env
+        .device
+        .create_surface(
+            &env.context,
+            SurfaceAccess::GPUOnly,
+            SurfaceType::Generic { size: surface_size },
+        )
+        .unwrap();
+    env.device
+        .clear_surface(&env.context, &surface, [0.0, 1.0, 0.0, 1.0])
+        .unwrap();
+
+    unsafe {
+        let mut textures = [0; 2];
+        env.gl.GenTextures(2, textures.as_mut_ptr());
+        for (&texture, &size) in textures.iter().zip(&[surface_size, Size2D::new(8, 8)]) {
+            env.gl.BindTexture(gl::TEXTURE_2D, texture);
+            env.gl.TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as _,
+                size.width,
+                size.height,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                ptr::null(),
+            );
+        }
+        env.gl.BindTexture(gl::TEXTURE_2D, 0);
+        check_gl(&env.gl);
+
+        env.device
+            .copy_surface_to_gl_texture(&env.context, &surface, textures[0], 0)
+            .unwrap();
+        match env
+            .device
+            .copy_surface_to_gl_texture(&env.context, &surface, textures[1], 0)
+        {
+            Err(Error::TextureCopyFailed(gl::INVALID_VALUE)) => {}
+            result => panic!("Expected the copy to be rejected: {:?}", result),
+        }
+
+        let mut framebuffer_object = make_fbo(&env.gl, gl::TEXTURE_2D, textures[0]);
+        assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 255, 0, 255]);
+        env.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        env.gl.DeleteFramebuffers(1, &mut framebuffer_object);
+        env.gl.DeleteTextures(2, textures.as_ptr());
+    }
+
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that a window surface backed by a DXGI flip-model swap chain can be rendered to and
+// presented, and that an invalid buffer count is rejected.
+#[cfg(all(angle, feature = "sm-winit"))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_swapchain_surface() {
+    use winit::event_loop::EventLoop;
+    use winit::platform::windows::EventLoopExtWindows;
+    use winit::window::WindowBuilder;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let event_loop: EventLoop<()> = EventLoop::new_any_thread();
+    let window = WindowBuilder::new()
+        .with_visible(false)
+        .build(&event_loop)
+        .unwrap();
+    let native_widget = env
+        .connection
+        .create_native_widget_from_winit_window(&window)
+        .unwrap();
+
+    match env
+        .device
+        .create_swapchain_surface(&env.context, &native_widget, 1)
+    {
+        Err(Error::SurfaceCreationFailed(WindowingApiError::BadValue)) => {}
+        Err(err) => panic!("Unexpected error: {:?}", err),
+        Ok(_) => panic!("Shouldn't have created a swap chain with one buffer!"),
+    }
+
+    let surface = env
+        .device
+        .create_swapchain_surface(&env.context, &native_widget, 2)
+        .unwrap();
+    let mut old_surface = env
+        .device
+        .unbind_surface_from_context(&mut env.context)
+        .unwrap()
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut old_surface)
+        .unwrap();
+    env.device
+        .bind_surface_to_context(&mut env.context, surface)
+        .unwrap();
+    env.device.make_context_current(&env.context).unwrap();
+
+    for _ in 0..3 {
+        clear(&env.gl, &[255, 0, 0, 255]);
+        assert_eq!(get_pixel_from_bottom_row(&env.gl), [255, 0, 0, 255]);
+        env.device.present_context_timed(&mut env.context).unwrap();
+    }
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that prewarming the shader compiler can be repeated and leaves the context able to compile
+// shaders.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_prewarm() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    env.device.prewarm(&env.context).unwrap();
+    env.device.prewarm(&env.context).unwrap();
+
+    env.device.make_context_current(&env.context).unwrap();
+    let source = b"precision mediump float;\nvoid main() { gl_FragColor = vec4(0.0); }\n";
+    unsafe {
+        let shader = env.gl.CreateShader(gl::FRAGMENT_SHADER);
+        env.gl.ShaderSource(
+            shader,
+            1,
+            &(source.as_ptr() as *const _),
+            &(source.len() as _),
+        );
+        env.gl.CompileShader(shader);
+        let mut compile_status = 0;
+        env.gl
+            .GetShaderiv(shader, gl::COMPILE_STATUS, &mut compile_status);
+        assert_eq!(compile_status, gl::TRUE as _);
+        env.gl.DeleteShader(shader);
+    }
+    check_gl(&env.gl);
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that what is known about an adopted context's render target can be queried, even though
+// `context_surface_info()` can't describe it.
+#[cfg(any(android, angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_external_surface_info() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+    assert!(env
+        .device
+        .context_external_surface_info(&env.context)
+        .is_none());
+
+    let native_context = NativeContext::current().unwrap();
+    let mut adopted_context = unsafe {
+        env.device
+            .create_context_from_native_context(native_context)
+            .unwrap()
+    };
+    match env.device.context_surface_info(&adopted_context) {
+        Err(Error::ExternalRenderTarget) => {}
+        _ => panic!("Adopted contexts should have external render targets!"),
+    }
+    let info = env
+        .device
+        .context_external_surface_info(&adopted_context)
+        .unwrap();
+    assert_eq!(info.context_id, env.device.context_id(&adopted_context));
+    assert_eq!(info.framebuffer_object, 0);
+    if let Some(size) = info.size {
+        assert!(size.width > 0 && size.height > 0);
+    }
+    #[cfg(angle)]
+    assert_eq!(
+        info.size,
+        Some(
+            env.device
+                .context_surface_info(&env.context)
+                .unwrap()
+                .unwrap()
+                .size
+        )
+    );
+
+    env.device.destroy_context(&mut adopted_context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that an offscreen surface can be blitted to a window surface and presented, and that
+// generic surfaces are rejected as destinations.
+#[cfg(all(angle, feature = "sm-winit"))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_blit_surface_to_window() {
+    use super::surface::BlitFilter;
+    use winit::event_loop::EventLoop;
+    use winit::platform::windows::EventLoopExtWindows;
+    use winit::window::WindowBuilder;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let event_loop: EventLoop<()> = EventLoop::new_any_thread();
+    let window = WindowBuilder::new()
+        .with_visible(false)
+        .build(&event_loop)
+        .unwrap();
+    let native_widget = env
+        .connection
+        .create_native_widget_from_winit_window(&window)
+        .unwrap();
+    let mut widget_surface = env
+        .device
+        .create_surface(
+            &env.context,
+            SurfaceAccess::GPUOnly,
+            SurfaceType::Widget { native_widget },
+        )
+        .unwrap();
+
+    let mut offscreen_surface = make_surface(&mut env.device, &env.context);
+    env.device
+        .clear_surface(&env.context, &offscreen_surface, [0.0, 1.0, 0.0, 1.0])
+        .unwrap();
+    let mut other_surface = make_surface(&mut env.device, &env.context);
+    match env.device.blit_surface_to_window(
+        &env.context,
+        &offscreen_surface,
+        &mut other_surface,
+        BlitFilter::Nearest,
+    ) {
+        Err(Error::NoWidgetAttached) => {}
+        result => panic!("Expected the blit to be rejected: {:?}", result),
+    }
+
+    for &filter in &[BlitFilter::Nearest, BlitFilter::Linear] {
+        env.device
+            .blit_surface_to_window(
+                &env.context,
+                &offscreen_surface,
+                &mut widget_surface,
+                filter,
+            )
+            .unwrap();
+        env.device
+            .make_context_current_with_surfaces(&env.context, &widget_surface, &widget_surface)
+            .unwrap();
+        unsafe {
+            env.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 255, 0, 255]);
+        env.device
+            .present_surface(&env.context, &mut widget_surface)
+            .unwrap();
+    }
+
+    for surface in &mut [widget_surface, offscreen_surface, other_surface] {
+        env.device
+            .destroy_surface(&mut env.context, surface)
+            .unwrap();
+    }
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that the native visual ID of a context's config can be read, including for an adopted
+// context.
+#[cfg(any(android, angle, linux))]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_native_visual_id() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+    let native_visual_id = env.device.context_native_visual_id(&env.context);
+    assert!(native_visual_id >= 0);
+
+    let native_context = NativeContext::current().unwrap();
+    let mut adopted_context = unsafe {
+        env.device
+            .create_context_from_native_context(native_context)
+            .unwrap()
+    };
+    assert_eq!(
+        env.device.context_native_visual_id(&adopted_context),
+        native_visual_id
+    );
+
+    env.device.destroy_context(&mut adopted_context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that a surface created with a placeholder color can be sampled before anything renders
+// to it.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_create_surface_with_placeholder() {
+    use super::surface::AngleSurfaceOptions;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let surface = env
+        .device
+        .create_surface_with_options(
+            &env.context,
+            SurfaceAccess::GPUOnly,
+            SurfaceType::Generic {
+                size: Size2D::new(16, 16),
+            },
+            &AngleSurfaceOptions {
+                clear_color: Some([1.0, 0.0, 1.0, 1.0]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let surface_texture = env
+        .device
+        .create_surface_texture(&mut env.context, surface)
+        .unwrap();
+    env.device.make_context_current(&env.context).unwrap();
+
+    unsafe {
+        let mut framebuffer_object = make_fbo(
+            &env.gl,
+            env.device.surface_gl_texture_target(),
+            env.device.surface_texture_object(&surface_texture),
+        );
+        assert_eq!(get_pixel_from_bottom_row(&env.gl), [255, 0, 255, 255]);
+        env.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        env.gl.DeleteFramebuffers(1, &mut framebuffer_object);
+    }
+    check_gl(&env.gl);
+
+    let mut surface = env
+        .device
+        .destroy_surface_texture(&mut env.context, surface_texture)
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that swap chain presents tell occlusion apart from both success and failure.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_present_result() {
+    use super::surface::{self, PresentResult};
+    use winapi::shared::winerror::{DXGI_STATUS_OCCLUDED, E_FAIL, S_OK};
+
+    assert_eq!(
+        surface::present_result(S_OK).unwrap(),
+        PresentResult::Presented
+    );
+    assert_eq!(
+        surface::present_result(DXGI_STATUS_OCCLUDED).unwrap(),
+        PresentResult::Occluded
+    );
+    match surface::present_result(E_FAIL) {
+        Err(Error::PresentFailed(_)) => {}
+        result => panic!("Expected the present to fail: {:?}", result),
+    }
+}
+
+// Tests that the surface formats a device supports include RGBA8, and that each names a real
+// config.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_supported_surface_formats() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let formats = env.device.supported_surface_formats().unwrap();
+    let rgba8 = ColorBits {
+        red: 8,
+        green: 8,
+        blue: 8,
+        alpha: 8,
+    };
+    assert!(formats.iter().any(|format| format.color_bits == rgba8));
+    let config_ids: Vec<_> = env
+        .device
+        .enumerate_configs()
+        .unwrap()
+        .iter()
+        .map(|config| config.config_id)
+        .collect();
+    for format in &formats {
+        assert!(config_ids.contains(&format.config_id));
+    }
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that application data can be attached to a context, replaced, and recovered by type.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_user_data() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+    assert!(env.device.context_user_data(&env.context).is_none());
+
+    env.device
+        .set_context_user_data(&mut env.context, Box::new(42u32));
+    env.device
+        .set_context_user_data(&mut env.context, Box::new(String::from("resources")));
+    let user_data = env.device.context_user_data(&env.context).unwrap();
+    assert!(user_data.downcast_ref::<u32>().is_none());
+    assert_eq!(
+        user_data.downcast_ref::<String>().map(String::as_str),
+        Some("resources")
+    );
+
+    env.device.destroy_context(&mut env.context).unwrap();
+    assert!(env.device.context_user_data(&env.context).is_none());
+}
+
+// Tests that signaling a semaphore either reports the missing extension or rejects an invalid
+// handle without leaving GL errors behind.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_signal_semaphore_on_flush() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let result = unsafe {
+        env.device
+            .signal_semaphore_on_flush(&env.context, ptr::null_mut())
+    };
+    match result {
+        Err(Error::RequiredExtensionUnavailable) | Err(Error::Failed) => {}
+        Ok(()) => panic!("Signaling a null semaphore handle should fail!"),
+        Err(err) => panic!("Unexpected error: {:?}", err),
+    }
+    assert!(env.device.drain_gl_errors(&env.context).unwrap().is_empty());
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that an oversized surface requested with `EGL_LARGEST_PBUFFER` is clamped instead of
+// rejected, and that the clamped size is reported.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_create_surface_with_largest_pbuffer() {
+    use super::surface::AngleSurfaceOptions;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let max_dimension = env.device.max_surface_dimension();
+    let requested = Size2D::new(max_dimension + 1000, 64);
+    let mut surface = env
+        .device
+        .create_surface_with_options(
+            &env.context,
+            SurfaceAccess::GPUOnly,
+            SurfaceType::Generic { size: requested },
+            &AngleSurfaceOptions {
+                largest_pbuffer: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let size = env.device.surface_info(&surface).size;
+    assert!(size.width > 0 && size.width <= max_dimension);
+    assert!(size.height > 0 && size.height <= requested.height);
+
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that a Direct3D 11 texture imported through the unified entry point can be rendered to
+// and sampled.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_import_external_surface() {
+    use super::surface::ExternalSurfaceHandle;
+    use winapi::shared::dxgiformat::DXGI_FORMAT_R8G8B8A8_UNORM;
+    use winapi::shared::dxgitype::DXGI_SAMPLE_DESC;
+    use winapi::um::d3d11::{D3D11_BIND_RENDER_TARGET, D3D11_BIND_SHADER_RESOURCE};
+    use winapi::um::d3d11::{D3D11_TEXTURE2D_DESC, D3D11_USAGE_DEFAULT};
+    use wio::com::ComPtr;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let texture = unsafe {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: 64,
+            Height: 64,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_SHADER_RESOURCE | D3D11_BIND_RENDER_TARGET,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+        };
+        let mut texture = ptr::null_mut();
+        let result = env
+            .device
+            .d3d11_device
+            .CreateTexture2D(&desc, ptr::null(), &mut texture);
+        assert_eq!(result, 0);
+        ComPtr::from_raw(texture)
+    };
+
+    let surface = unsafe {
+        env.device
+            .import_external_surface(
+                &env.context,
+                ExternalSurfaceHandle::D3D11Texture(texture.as_raw() as *mut _),
+                Size2D::new(64, 64),
+            )
+            .unwrap()
+    };
+    env.device
+        .clear_surface(&env.context, &surface, [0.0, 1.0, 0.0, 1.0])
+        .unwrap();
+    let surface_texture = env
+        .device
+        .create_surface_texture(&mut env.context, surface)
+        .unwrap();
+
+    env.device.make_context_current(&env.context).unwrap();
+    unsafe {
+        let mut framebuffer_object = make_fbo(
+            &env.gl,
+            env.device.surface_gl_texture_target(),
+            env.device.surface_texture_object(&surface_texture),
+        );
+        assert_eq!(get_pixel_from_bottom_row(&env.gl), [0, 255, 0, 255]);
+        env.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        env.gl.DeleteFramebuffers(1, &mut framebuffer_object);
+    }
+
+    let mut surface = env
+        .device
+        .destroy_surface_texture(&mut env.context, surface_texture)
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that capabilities are resolved against the context's version and extensions.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_context_supports() {
+    use crate::GLCapability;
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    // The basic environment asks for OpenGL ES 3.0, which has texture arrays.
+    let gl_info = env.device.context_gl_info(&env.context);
+    assert_eq!(gl_info.api(), GLApi::GLES);
+    assert!(gl_info.version().supports(GLVersion::GLES_3_0));
+    assert!(env
+        .device
+        .context_supports(&env.context, GLCapability::TextureArrays));
+    assert_eq!(
+        env.device
+            .context_supports(&env.context, GLCapability::Multiview),
+        gl_info.has_extension("GL_OVR_multiview")
+    );
+
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that the live context count covers created and adopted contexts and goes back to zero
+// once every context is destroyed.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_live_context_count() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+    assert_eq!(env.device.live_context_count(), 1);
+
+    let context_descriptor = env.device.context_descriptor(&env.context);
+    let mut contexts = vec![];
+    for _ in 0..2 {
+        let context = env
+            .device
+            .create_context(&context_descriptor, Some(&env.context))
+            .unwrap();
+        contexts.push(context);
+    }
+    assert_eq!(env.device.live_context_count(), 3);
+
+    for context in &mut contexts {
+        env.device.destroy_context(context).unwrap();
+    }
+    assert_eq!(env.device.live_context_count(), 1);
+
+    // Destroying a context twice doesn't count it twice.
+    env.device.destroy_context(&mut contexts[0]).unwrap();
+    assert_eq!(env.device.live_context_count(), 1);
+
+    // Adopting the current context counts it until the adopted context is destroyed.
+    env.device.make_context_current(&env.context).unwrap();
+    let native_context = NativeContext::current().unwrap();
+    let mut adopted_context = unsafe {
+        env.device
+            .create_context_from_native_context(native_context)
+            .unwrap()
+    };
+    assert_eq!(env.device.live_context_count(), 2);
+    env.device.destroy_context(&mut adopted_context).unwrap();
+    assert_eq!(env.device.live_context_count(), 1);
+
+    // Thread-local clones share the count, so a context can be destroyed with either device.
+    let mut clone = env.device.create_thread_local_clone();
+    let mut context = clone.create_context(&context_descriptor, None).unwrap();
+    assert_eq!(env.device.live_context_count(), 2);
+    env.device.destroy_context(&mut context).unwrap();
+    assert_eq!(clone.live_context_count(), 1);
+
+    env.device.destroy_context(&mut env.context).unwrap();
+    assert_eq!(env.device.live_context_count(), 0);
+}
+
+// Tests that a surface texture for an atlas region reports the region in texture coordinates,
+// and that regions outside the surface are rejected.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_surface_texture_region() {
+    use euclid::default::{Point2D, Rect};
+
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    // `make_surface()` creates 640x480 surfaces.
+    let surface = make_surface(&mut env.device, &env.context);
+    let outside = Rect::new(Point2D::new(600, 0), Size2D::new(64, 64));
+    let surface = match env
+        .device
+        .create_surface_texture_region(&mut env.context, surface, outside)
+    {
+        Err((Error::RectOutOfBounds, surface)) => surface,
+        Err((err, _)) => panic!("Expected `RectOutOfBounds` but got {:?}", err),
+        Ok(_) => panic!("Created a surface texture for a region outside the surface!"),
+    };
+
+    let region = Rect::new(Point2D::new(160, 120), Size2D::new(320, 240));
+    let surface_texture = env
+        .device
+        .create_surface_texture_region(&mut env.context, surface, region)
+        .unwrap();
+    assert_eq!(
+        env.device.surface_texture_uv_rect(&surface_texture),
+        Rect::new(Point2D::new(0.25, 0.25), Size2D::new(0.5, 0.5))
+    );
+
+    let mut surface = env
+        .device
+        .destroy_surface_texture(&mut env.context, surface_texture)
+        .unwrap();
+    env.device
+        .destroy_surface(&mut env.context, &mut surface)
+        .unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+// Tests that the primary monitor maps to the adapter that a device for it is opened on.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_device_for_output() {
+    use super::device::Adapter;
+    use winapi::shared::windef::POINT;
+    use winapi::um::winuser::{MonitorFromPoint, MONITOR_DEFAULTTOPRIMARY};
+
+    let hmonitor = unsafe { MonitorFromPoint(POINT { x: 0, y: 0 }, MONITOR_DEFAULTTOPRIMARY) };
+    if hmonitor.is_null() {
+        // Headless, so there's no monitor to look up.
+        return;
+    }
+    let luid = match Adapter::from_monitor(hmonitor) {
+        Ok(adapter) => adapter.luid(),
+        // Some virtual displays aren't outputs of any DXGI adapter.
+        Err(Error::NoAdapterFound) => return,
+        Err(err) => panic!("Failed to look up the monitor's adapter: {:?}", err),
+    };
+
+    let device = Device::new_for_output(hmonitor).unwrap();
+    let device_luid = device.adapter().luid();
+    assert_eq!(device_luid.LowPart, luid.LowPart);
+    assert_eq!(device_luid.HighPart, luid.HighPart);
+}
+
+// Tests that the reporter sees the attributes that the context was actually created with.
+#[cfg(angle)]
+#[cfg_attr(not(feature = "sm-test"), test)]
+pub fn test_create_context_with_reporter() {
+    let mut env = match BasicEnvironment::new() {
+        None => return,
+        Some(env) => env,
+    };
+
+    let descriptor = env.device.context_descriptor(&env.context);
+    let requested = env.device.context_descriptor_attributes(&descriptor);
+    let mut granted = None;
+    let mut context = env
+        .device
+        .create_context_with_reporter(&descriptor, None, |attributes| granted = Some(*attributes))
+        .unwrap();
+
+    let granted = granted.expect("The reporter wasn't called!");
+    assert_eq!(granted.api, GLApi::GLES);
+    assert_eq!(granted.api, env.device.context_gl_api(&context));
+    assert!(granted.version.supports(requested.version));
+    assert_eq!(granted.format.config_id, descriptor.egl_config_id);
+    assert!(granted.format.color_bits.red >= requested.color_bits.red);
+    assert!(granted.format.color_bits.alpha >= requested.color_bits.alpha);
+
+    env.device.destroy_context(&mut context).unwrap();
+    env.device.destroy_context(&mut env.context).unwrap();
+}
+
+fn bind_context_fbo(gl: &Gl, device: &Device, context: &Context) {
+    unsafe {
+        gl.BindFramebuffer(gl::FRAMEBUFFER, context_fbo(device, context));
+        check_gl(&gl);
+    }
+}
+
+fn context_fbo(device: &Device, context: &Context) -> GLuint {
+    device
+        .context_surface_info(context)
+        .unwrap()
+        .unwrap()
+        .framebuffer_object
+}
+
+// Returns the function table that surfman itself uses for the given context.
+#[cfg(angle)]
+fn context_gl(context: &Context) -> &Gl {
+    context.gl()
+}
+
+#[cfg(linux)]
+fn context_gl(context: &Context) -> &Gl {
+    context.0.gl()
+}
+
+fn make_surface(device: &mut Device, context: &Context) -> Surface {
+    device
+        .create_surface(
+            &context,
+            SurfaceAccess::GPUOnly,
+            SurfaceType::Generic {
+                size: Size2D::new(640, 480),
+            },
+        )
+        .unwrap()
+}
+
+fn blit_fbo(gl: &Gl, dest_fbo: GLuint, src_fbo: GLuint) {
+    unsafe {
+        gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, dest_fbo);
+        check_gl(gl);
+        gl.BindFramebuffer(gl::READ_FRAMEBUFFER, src_fbo);
+        check_gl(gl);
+        gl.BlitFramebuffer(
+            0,
+            0,
+            640,
+            480,
+            0,
+            0,
+            640,
+            480,
+            gl::COLOR_BUFFER_BIT,
+            gl::NEAREST,
+        );
+        check_gl(gl);
+    }
+}
+
+fn make_fbo(gl: &Gl, texture_target: GLenum, texture: GLuint) -> GLuint {
+    unsafe {
+        let mut framebuffer_object = 0;
+        gl.GenFramebuffers(1, &mut framebuffer_object);
+        check_gl(&gl);
+        gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer_object);
+        check_gl(&gl);
+        gl.FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            texture_target,
+            texture,
+            0,
+        );
+        check_gl(&gl);
+        assert_eq!(
+            gl.CheckFramebufferStatus(gl::FRAMEBUFFER),
+            gl::FRAMEBUFFER_COMPLETE
+        );
+        framebuffer_object
+    }
+}
+
+struct BasicEnvironment {
+    connection: Connection,
+    adapter: Adapter,
+    device: Device,
+    context_descriptor: ContextDescriptor,
+    context: Context,
+    gl: Gl,
+}
+
+impl BasicEnvironment {
+    fn new() -> Option<BasicEnvironment> {
+        let connection = Connection::new().unwrap();
+        let adapter = connection
+            .create_low_power_adapter()
+            .expect("Failed to create adapter!");
+        let mut device = match connection.create_device(&adapter) {
+            Ok(device) => device,
+            Err(Error::RequiredExtensionUnavailable) => {
+                // Can't run these tests on this hardware.
+                return None;
+            }
+            Err(err) => panic!("Failed to create device: {:?}", err),
+        };
+
+        let context_descriptor = device
+            .create_context_descriptor(&ContextAttributes {
+                version: GLVersion::new(3, 0),
+                flags: ContextAttributeFlags::empty(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let mut context = device.create_context(&context_descriptor, None).unwrap();
+        let surface = make_surface(&mut device, &context);
+        device
+            .bind_surface_to_context(&mut context, surface)
+            .unwrap();
+        device.make_context_current(&context).unwrap();
+
+        let gl = Gl::load_with(|symbol| device.get_proc_address(&context, symbol));
+
+        unsafe {
+            let framebuffer_object = device
+                .context_surface_info(&context)
+                .unwrap()
+                .unwrap()
+                .framebuffer_object;
+            gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer_object);
+            gl.Viewport(0, 0, 640, 480);
+        }
+
+        Some(BasicEnvironment {
+            connection,
+            adapter,
+            device,
+            context_descriptor,
+            context,
+            gl,
+        })
+    }
+}
+
+fn clear(gl: &Gl, color: &[u8; 4]) {
+    unsafe {
+        gl.ClearColor(
+            color[0] as f32 / 255.0,
+            color[1] as f32 / 255.0,
+            color[2] as f32 / 255.0,
+            color[3] as f32 / 255.0,
+        );
+        gl.Clear(gl::COLOR_BUFFER_BIT);
+    }
+}
+
+fn clear_bottom_row(gl: &Gl, color: &[u8; 4]) {
+    unsafe {
+        gl.Scissor(0, 0, 640, 1);
+        gl.Enable(gl::SCISSOR_TEST);
+        gl.ClearColor(
+            color[0] as f32 / 255.0,
+            color[1] as f32 / 255.0,
+            color[2] as f32 / 255.0,
+            color[3] as f32 / 255.0,
+        );
+        gl.Clear(gl::COLOR_BUFFER_BIT);
+        gl.Disable(gl::SCISSOR_TEST);
+        gl.Scissor(0, 0, 640, 480);
+    }
+}
+
+fn get_pixel_from_bottom_row(gl: &Gl) -> [u8; 4] {
+    unsafe {
+        let mut pixel: [u8; 4] = [0; 4];
+        gl.ReadPixels(
+            0,
+            0,
+            1,
+            1,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixel.as_mut_ptr() as *mut c_void,
         );
         pixel
     }
@@ -1058,6 +5288,114 @@ fn get_pixel_from_second_from_bottom_row(gl: &Gl) -> [u8; 4] {
     }
 }
 
+// Draws the given texture coordinate of a texture into the bottom left pixel of the current
+// framebuffer and reads it back.
+#[cfg(angle)]
+fn sample_texture(gl: &Gl, texture: GLuint, texcoord: [f32; 2]) -> [u8; 4] {
+    static VERTEX_SHADER_SOURCE: &[u8] =
+        b"attribute vec2 aPosition;\nvoid main() { gl_Position = vec4(aPosition, 0.0, 1.0); }\n";
+    static FRAGMENT_SHADER_SOURCE: &[u8] = b"precision mediump float;
+uniform sampler2D uTexture;
+uniform vec2 uTexCoord;
+void main() { gl_FragColor = texture2D(uTexture, uTexCoord); }
+";
+    static VERTICES: [f32; 6] = [-1.0, -1.0, 3.0, -1.0, -1.0, 3.0];
+
+    unsafe {
+        let program = gl.CreateProgram();
+        let mut shaders = vec![];
+        for &(kind, source) in &[
+            (gl::VERTEX_SHADER, VERTEX_SHADER_SOURCE),
+            (gl::FRAGMENT_SHADER, FRAGMENT_SHADER_SOURCE),
+        ] {
+            let shader = gl.CreateShader(kind);
+            gl.ShaderSource(
+                shader,
+                1,
+                &(source.as_ptr() as *const _),
+                &(source.len() as _),
+            );
+            gl.CompileShader(shader);
+            let mut compile_status = 0;
+            gl.GetShaderiv(shader, gl::COMPILE_STATUS, &mut compile_status);
+            assert_eq!(compile_status, gl::TRUE as _);
+            gl.AttachShader(program, shader);
+            shaders.push(shader);
+        }
+        gl.BindAttribLocation(program, 0, b"aPosition\0".as_ptr() as *const _);
+        gl.LinkProgram(program);
+        let mut link_status = 0;
+        gl.GetProgramiv(program, gl::LINK_STATUS, &mut link_status);
+        assert_eq!(link_status, gl::TRUE as _);
+
+        gl.UseProgram(program);
+        gl.ActiveTexture(gl::TEXTURE0);
+        gl.BindTexture(gl::TEXTURE_2D, texture);
+        gl.Uniform1i(
+            gl.GetUniformLocation(program, b"uTexture\0".as_ptr() as *const _),
+            0,
+        );
+        gl.Uniform2f(
+            gl.GetUniformLocation(program, b"uTexCoord\0".as_ptr() as *const _),
+            texcoord[0],
+            texcoord[1],
+        );
+        gl.BindBuffer(gl::ARRAY_BUFFER, 0);
+        gl.VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, VERTICES.as_ptr() as *const _);
+        gl.EnableVertexAttribArray(0);
+        gl.Viewport(0, 0, 1, 1);
+        gl.DrawArrays(gl::TRIANGLES, 0, 3);
+        let pixel = get_pixel_from_bottom_row(gl);
+
+        gl.DisableVertexAttribArray(0);
+        gl.BindTexture(gl::TEXTURE_2D, 0);
+        gl.UseProgram(0);
+        for shader in shaders {
+            gl.DeleteShader(shader);
+        }
+        gl.DeleteProgram(program);
+        pixel
+    }
+}
+
+// A minimal executor, so that tests don't need an async runtime.
+#[cfg(all(angle, feature = "sm-async"))]
+fn block_on<F: std::future::Future>(mut future: F) -> F::Output {
+    use std::mem;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context as TaskContext, Poll, RawWaker, RawWakerVTable, Waker};
+
+    // The waker holds a reference to the thread to unpark, since it may outlive this function.
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        let thread = Arc::from_raw(data as *const thread::Thread);
+        let clone = thread.clone();
+        mem::forget(thread);
+        RawWaker::new(Arc::into_raw(clone) as *const (), &VTABLE)
+    }
+    unsafe fn wake(data: *const ()) {
+        Arc::from_raw(data as *const thread::Thread).unpark();
+    }
+    unsafe fn wake_by_ref(data: *const ()) {
+        (*(data as *const thread::Thread)).unpark();
+    }
+    unsafe fn drop(data: *const ()) {
+        mem::drop(Arc::from_raw(data as *const thread::Thread));
+    }
+
+    let thread = Arc::into_raw(Arc::new(thread::current())) as *const ();
+    let waker = unsafe { Waker::from_raw(RawWaker::new(thread, &VTABLE)) };
+    let mut task_context = TaskContext::from_waker(&waker);
+    let mut future = unsafe { Pin::new_unchecked(&mut future) };
+    loop {
+        match future.as_mut().poll(&mut task_context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
 fn check_gl(gl: &Gl) {
     unsafe {
         assert_eq!(gl.GetError(), gl::NO_ERROR);
@@ -0,0 +1,219 @@
+// surfman/surfman/src/tiled_surface.rs
+//
+//! Surfaces larger than a single texture, split into a grid of texture-backed tiles.
+
+use crate::context::ContextID;
+use crate::gl;
+use crate::gl::types::{GLint, GLuint};
+use crate::gl_utils::{self, GLStateGuard};
+use crate::{Error, Gl, WindowingApiError};
+
+use euclid::default::{Point2D, Rect, Size2D};
+use std::ptr;
+use std::thread;
+
+/// A surface made up of a grid of textures, for canvases larger than `GL_MAX_TEXTURE_SIZE`.
+///
+/// Each tile is an OpenGL texture with its own framebuffer object, local to the context the
+/// tiled surface was created with. Render to a tile by binding its framebuffer object and
+/// offsetting your drawing by the tile's origin.
+///
+/// Tiled surfaces must be destroyed with the `destroy_tiled_surface()` method, or a panic will
+/// occur.
+pub struct TiledSurface {
+    pub(crate) size: Size2D<i32>,
+    pub(crate) context_id: ContextID,
+    pub(crate) tiles: Vec<Tile>,
+}
+
+/// One tile of a `TiledSurface`.
+#[derive(Clone, Copy, Debug)]
+pub struct Tile {
+    /// The region of the tiled surface that this tile covers.
+    pub rect: Rect<i32>,
+    /// The OpenGL texture object containing the contents of this tile.
+    pub texture_object: GLuint,
+    /// The OpenGL framebuffer object that renders to this tile.
+    pub framebuffer_object: GLuint,
+}
+
+impl Drop for TiledSurface {
+    fn drop(&mut self) {
+        if !self.tiles.is_empty() && !thread::panicking() {
+            panic!("Should have destroyed the tiled surface first with `destroy_tiled_surface()`!")
+        }
+    }
+}
+
+impl TiledSurface {
+    // Creates the tiles. The context that the surface is to be used with must be current.
+    pub(crate) fn new(
+        gl: &Gl,
+        context_id: ContextID,
+        size: Size2D<i32>,
+        tile_size: Size2D<i32>,
+    ) -> Result<TiledSurface, Error> {
+        unsafe {
+            let mut max_texture_size = 0;
+            gl.GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size);
+            if size.width <= 0
+                || size.height <= 0
+                || tile_size.width <= 0
+                || tile_size.height <= 0
+                || tile_size.width > max_texture_size
+                || tile_size.height > max_texture_size
+            {
+                return Err(Error::SurfaceCreationFailed(WindowingApiError::BadValue));
+            }
+
+            let _state_guard = GLStateGuard::new(gl);
+            let mut tiled_surface = TiledSurface {
+                size,
+                context_id,
+                tiles: vec![],
+            };
+            for rect in tile_rects(size, tile_size) {
+                let mut texture_object = 0;
+                gl.GenTextures(1, &mut texture_object);
+                gl.BindTexture(gl::TEXTURE_2D, texture_object);
+                gl.TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA as GLint,
+                    rect.size.width,
+                    rect.size.height,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    ptr::null(),
+                );
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+
+                let framebuffer_object =
+                    gl_utils::create_and_bind_framebuffer(gl, gl::TEXTURE_2D, texture_object);
+                tiled_surface.tiles.push(Tile {
+                    rect,
+                    texture_object,
+                    framebuffer_object,
+                });
+
+                let status = gl.CheckFramebufferStatus(gl::FRAMEBUFFER);
+                if status != gl::FRAMEBUFFER_COMPLETE {
+                    tiled_surface.destroy(gl);
+                    return Err(Error::SurfaceFramebufferIncomplete(status));
+                }
+            }
+            Ok(tiled_surface)
+        }
+    }
+
+    // Deletes the tiles. The context that the surface was created with must be current.
+    pub(crate) fn destroy(&mut self, gl: &Gl) {
+        for tile in self.tiles.drain(..) {
+            gl_utils::destroy_framebuffer(gl, tile.framebuffer_object);
+            unsafe {
+                gl.DeleteTextures(1, &tile.texture_object);
+            }
+        }
+    }
+
+    // Clears every tile to the given color. The context that the surface was created with must
+    // be current.
+    pub(crate) fn clear(&self, gl: &Gl, color: [f32; 4]) {
+        unsafe {
+            let _state_guard = GLStateGuard::new(gl);
+            gl.Disable(gl::SCISSOR_TEST);
+            gl.ClearColor(color[0], color[1], color[2], color[3]);
+            for tile in &self.tiles {
+                gl.BindFramebuffer(gl::FRAMEBUFFER, tile.framebuffer_object);
+                gl.Viewport(0, 0, tile.rect.size.width, tile.rect.size.height);
+                gl.Clear(gl::COLOR_BUFFER_BIT);
+            }
+        }
+    }
+
+    // Reads back the RGBA pixels in the given region, which may span several tiles. Rows are
+    // returned bottom to top, as `glReadPixels()` does. The context that the surface was created
+    // with must be current.
+    pub(crate) fn read_pixels(&self, gl: &Gl, rect: &Rect<i32>) -> Result<Vec<u8>, Error> {
+        let bounds = Rect::new(Point2D::zero(), self.size);
+        if rect.is_empty() || !bounds.contains_rect(rect) {
+            return Err(Error::RectOutOfBounds);
+        }
+
+        let stride = rect.size.width as usize * 4;
+        let mut pixels = vec![0; stride * rect.size.height as usize];
+        unsafe {
+            let _state_guard = GLStateGuard::new(gl);
+            let mut old_pack_alignment = 0;
+            gl.GetIntegerv(gl::PACK_ALIGNMENT, &mut old_pack_alignment);
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+
+            let mut tile_pixels = vec![];
+            for tile in &self.tiles {
+                let region = match tile.rect.intersection(rect) {
+                    Some(region) if !region.is_empty() => region,
+                    _ => continue,
+                };
+
+                let tile_stride = region.size.width as usize * 4;
+                tile_pixels.resize(tile_stride * region.size.height as usize, 0);
+                gl.BindFramebuffer(gl::READ_FRAMEBUFFER, tile.framebuffer_object);
+                gl.ReadPixels(
+                    region.origin.x - tile.rect.origin.x,
+                    region.origin.y - tile.rect.origin.y,
+                    region.size.width,
+                    region.size.height,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    tile_pixels.as_mut_ptr() as *mut _,
+                );
+
+                let x_offset = (region.origin.x - rect.origin.x) as usize * 4;
+                for row in 0..region.size.height as usize {
+                    let dest_row = (region.origin.y - rect.origin.y) as usize + row;
+                    let dest_start = dest_row * stride + x_offset;
+                    pixels[dest_start..(dest_start + tile_stride)].copy_from_slice(
+                        &tile_pixels[(row * tile_stride)..((row + 1) * tile_stride)],
+                    );
+                }
+            }
+
+            gl.PixelStorei(gl::PACK_ALIGNMENT, old_pack_alignment);
+        }
+        Ok(pixels)
+    }
+
+    /// Returns the size of the whole tiled surface.
+    #[inline]
+    pub fn size(&self) -> Size2D<i32> {
+        self.size
+    }
+
+    /// Returns the ID of the context that this tiled surface belongs to.
+    #[inline]
+    pub fn context_id(&self) -> ContextID {
+        self.context_id
+    }
+
+    /// Returns the tiles, in row-major order starting from the origin.
+    #[inline]
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+}
+
+// Splits a surface of the given size into a grid of tiles, in row-major order. Tiles along the
+// right and top edges are shrunk to fit.
+pub(crate) fn tile_rects(size: Size2D<i32>, tile_size: Size2D<i32>) -> Vec<Rect<i32>> {
+    let mut rects = vec![];
+    for y in (0..size.height).step_by(tile_size.height as usize) {
+        for x in (0..size.width).step_by(tile_size.width as usize) {
+            let width = tile_size.width.min(size.width - x);
+            let height = tile_size.height.min(size.height - y);
+            rects.push(Rect::new(Point2D::new(x, y), Size2D::new(width, height)));
+        }
+    }
+    rects
+}
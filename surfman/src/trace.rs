@@ -0,0 +1,23 @@
+// surfman/surfman/src/trace.rs
+//
+//! Optional instrumentation of device operations.
+//!
+//! With the `sm-tracing` feature enabled, device operations are wrapped in `tracing` spans so
+//! that they show up in the application's traces. Without it, `device_span!` expands to nothing.
+
+// Enters a debug-level span named by the first argument for the rest of the enclosing block.
+// The remaining arguments are span fields, in the syntax of `tracing::span!`.
+//
+// Backends invoke this at the top of their `Device` methods rather than in the shared `Device`
+// trait implementation, so that inherent calls are traced too.
+#[cfg(feature = "sm-tracing")]
+macro_rules! device_span {
+    ($($args:tt)*) => {
+        let _span = ::tracing::debug_span!($($args)*).entered();
+    };
+}
+
+#[cfg(not(feature = "sm-tracing"))]
+macro_rules! device_span {
+    ($($args:tt)*) => {};
+}